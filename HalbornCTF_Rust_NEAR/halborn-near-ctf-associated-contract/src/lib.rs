@@ -2,12 +2,16 @@ use near_sdk::borsh::{BorshSerialize, BorshDeserialize};
 use near_sdk::collections::{LookupMap, LookupSet};
 use near_sdk::json_types::U64;
 use near_sdk::serde::{Deserialize, Serialize};
-use near_sdk::{env, log, near_bindgen, AccountId, PanicOnDefault};
+use near_sdk::{env, log, near_bindgen, AccountId, Gas, PanicOnDefault};
 
 mod storage;
 
 use storage::StorageKey;
 
+// Gas register_for_an_event needs, advertised to the token contract through
+// get_recommended_register_gas
+pub const RECOMMENDED_REGISTER_GAS: Gas = Gas::from_gas(10_000_000_000_000);
+
 #[derive(
     BorshDeserialize, BorshSerialize, Clone, Copy, Eq, PartialEq, Debug, Serialize, Deserialize,
 )]
@@ -70,6 +74,10 @@ impl AssociatedContract {
         self.next_event_idx
     }
 
+    pub fn get_recommended_register_gas(&self) -> Gas {
+        RECOMMENDED_REGISTER_GAS
+    }
+
     pub fn get_event(&self, event_idx: U64) -> Event {
         self.events.get(&event_idx).unwrap()
     }
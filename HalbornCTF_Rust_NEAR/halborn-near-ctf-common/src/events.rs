@@ -0,0 +1,15 @@
+use near_sdk::log;
+use near_sdk::serde_json::{json, Value};
+
+// Log a NEP-297 event under `standard` so indexers can pick it up
+pub fn emit(standard: &str, version: &str, event: &str, data: Value) {
+    log!(
+        "EVENT_JSON:{}",
+        json!({
+            "standard": standard,
+            "version": version,
+            "event": event,
+            "data": [data],
+        })
+    );
+}
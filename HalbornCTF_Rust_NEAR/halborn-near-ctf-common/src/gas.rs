@@ -0,0 +1,12 @@
+use near_sdk::{env, Gas};
+
+// Panic unless at least `required` of the attached gas is still left, so a
+// call fails up front instead of dispatching promises that can't complete
+pub fn assert_min_gas(required: Gas) {
+    let remaining = env::prepaid_gas().saturating_sub(env::used_gas());
+    assert!(
+        remaining >= required,
+        "Not enough gas, attach at least {} more",
+        required.saturating_sub(remaining)
+    );
+}
@@ -0,0 +1,4 @@
+// Helpers shared by the contracts in this workspace
+pub mod events;
+pub mod gas;
+pub mod math;
@@ -0,0 +1,26 @@
+// a * b / d without overflowing on the intermediate product, which easily
+// exceeds u128 when multiplying two yoctoNEAR amounts
+pub fn mul_div(a: u128, b: u128, d: u128) -> u128 {
+    const MASK: u128 = u64::MAX as u128;
+    let (a_hi, a_lo) = (a >> 64, a & MASK);
+    let (b_hi, b_lo) = (b >> 64, b & MASK);
+    let lo_lo = a_lo * b_lo;
+    let hi_lo = a_hi * b_lo;
+    let cross = (lo_lo >> 64) + (hi_lo & MASK) + a_lo * b_hi;
+    let hi = a_hi * b_hi + (hi_lo >> 64) + (cross >> 64);
+    let lo = (cross << 64) | (lo_lo & MASK);
+    assert!(hi < d, "Division overflow");
+
+    // Long division of the 256 bit product (hi, lo) by d
+    let mut rem = hi;
+    let mut quotient = 0u128;
+    for bit in (0..128).rev() {
+        let carry = rem >> 127;
+        rem = (rem << 1) | ((lo >> bit) & 1);
+        if carry == 1 || rem >= d {
+            rem = rem.wrapping_sub(d);
+            quotient |= 1 << bit;
+        }
+    }
+    quotient
+}
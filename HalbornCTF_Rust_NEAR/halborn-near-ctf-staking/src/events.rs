@@ -0,0 +1,9 @@
+use near_sdk::serde_json::Value;
+
+pub const EVENT_STANDARD: &str = "halborn-staking";
+pub const EVENT_VERSION: &str = "1.0.0";
+
+// Log a NEP-297 event under this contract's standard
+pub(crate) fn emit(event: &str, data: Value) {
+    halborn_near_ctf_common::events::emit(EVENT_STANDARD, EVENT_VERSION, event, data);
+}
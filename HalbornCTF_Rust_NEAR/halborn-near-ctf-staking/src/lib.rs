@@ -1,8 +1,116 @@
+use halborn_near_ctf_common::gas;
+use halborn_near_ctf_common::math::mul_div;
 use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
-use near_sdk::collections::UnorderedMap;
-use near_sdk::json_types::U128;
-use near_sdk::{env, log, near_bindgen, AccountId, NearToken, PanicOnDefault, Promise};
+use near_sdk::collections::{LookupMap, UnorderedMap};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
+use near_sdk::{
+    env, is_promise_success, log, near_bindgen, AccountId, BlockHeight, Gas, NearToken,
+    PanicOnDefault, Promise, PromiseOrValue,
+};
+use near_contract_standards::fungible_token::core::ext_ft_core;
+use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
 use near_contract_standards::fungible_token::Balance;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+mod events;
+
+// reward_rate is expressed in 1/REWARD_RATE_DENOMINATOR yoctoNEAR
+// earned per staked yoctoNEAR per block
+pub const REWARD_RATE_DENOMINATOR: u128 = 1_000_000_000_000;
+pub const MAX_FEE_BPS: u16 = 10_000;
+pub const GAS_FOR_WITHDRAW_CALLBACK: Gas = Gas::from_gas(5_000_000_000_000);
+pub const GAS_FOR_FT_TRANSFER: Gas = Gas::from_gas(10_000_000_000_000);
+pub const GAS_FOR_REWARD_CALLBACK: Gas = Gas::from_gas(5_000_000_000_000);
+pub const MAX_TOP_STAKERS: u64 = 100;
+pub const BOOST_BPS_DENOMINATOR: u16 = 10_000;
+// Fixed point scale of get_exchange_rate, 1.0 == EXCHANGE_RATE_PRECISION
+pub const EXCHANGE_RATE_PRECISION: u128 = 1_000_000_000_000_000_000_000_000;
+const DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+// (minimum lock duration in nanoseconds, reward multiplier in basis points),
+// longest first. Shorter locks still lock but earn no boost
+pub const LOCK_BOOST_TIERS: [(u64, u16); 3] = [
+    (365 * DAY, 20_000),
+    (90 * DAY, 15_000),
+    (30 * DAY, 12_500),
+];
+
+#[derive(BorshDeserialize, BorshSerialize)]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct RewardCheckpoint {
+    last_update_block: BlockHeight,
+    accrued: u128,
+    // reward_per_token_stored when the checkpoint was moved, what the
+    // account's weight earned before that is already in `accrued`
+    reward_debt: u128,
+    // Block timestamp the checkpoint was moved at, where a lock expiring
+    // since then splits the boost
+    last_update_timestamp: u64,
+}
+
+// NEAR owed to an account whose withdrawal transfer hasn't been confirmed yet.
+// `unstaked` is what gets removed from total_staked once it is
+#[derive(BorshDeserialize, BorshSerialize)]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct PendingWithdrawal {
+    amount: u128,
+    unstaked: u128,
+    in_flight: bool,
+    claimable_at: u64,
+    // Where the pending NEAR is sent, the staker when None. Set by the
+    // latest unstake and reset to the staker when a transfer to it fails
+    recipient: Option<AccountId>,
+    // Native rewards included in `amount`, counted in queued_rewards
+    rewards: u128,
+}
+
+// Owner request to recover residual balance, executable once the drain
+// timelock has passed since `proposed_at`
+#[derive(BorshDeserialize, BorshSerialize)]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct DrainProposal {
+    to: AccountId,
+    amount: u128,
+    proposed_at: u64,
+}
+
+// Voluntary lock on an account's whole stake, set by stake_locked. Rewards
+// accrue on the stake weighted by `multiplier_bps`
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct StakeLock {
+    // Block timestamp before which unstake refuses to release the stake
+    pub until: U64,
+    pub multiplier_bps: u16,
+}
+
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StakeAccount {
+    pub active: U128,
+    pub pending: U128,
+    // Block timestamp from which the pending amount can be withdrawn
+    pub claimable_at: Option<U64>,
+}
+
+// How the contract's NEAR splits between stake, reward pool, rewards owed to
+// stakers and the rest. `claimable` only counts native airdrop claims.
+// `free` saturates at 0, `underfunded` is set when everything committed
+// together exceeds the account balance
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BalanceBreakdown {
+    pub account_balance: U128,
+    pub total_staked: U128,
+    pub reward_pool: U128,
+    pub queued_rewards: U128,
+    pub claimable: U128,
+    pub free: U128,
+    pub underfunded: bool,
+}
 
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
@@ -11,6 +119,45 @@ pub struct StakingContract {
     owner: AccountId,
     stake_balances: UnorderedMap<AccountId, u128>,
     total_staked: u128,
+    reward_rate: u128,
+    reward_pool: u128,
+    reward_checkpoints: LookupMap<AccountId, RewardCheckpoint>,
+    claimable: LookupMap<AccountId, u128>,
+    // Sum of `claimable`, in units of the reward asset
+    total_claimable: u128,
+    pending_withdrawals: LookupMap<AccountId, PendingWithdrawal>,
+    // Native rewards taken from the reward pool that still wait in
+    // pending_withdrawals
+    queued_rewards: u128,
+    // Sum of `unstaked` over pending_withdrawals, stake that stays in
+    // total_staked until its transfer is confirmed
+    unconfirmed_unstakes: u128,
+    unstake_cooldown: u64,
+    unstake_fee_bps: u16,
+    drain_timelock: u64,
+    drain_proposal: Option<DrainProposal>,
+    // Fungible token rewards and airdrops are paid in, native NEAR when None
+    reward_token: Option<AccountId>,
+    // Block of each staker's first stake, cleared when they fully unstake
+    staked_since: UnorderedMap<AccountId, BlockHeight>,
+    locks: LookupMap<AccountId, StakeLock>,
+    // Shares are minted 1:1 with stake and burned as unstakes are confirmed
+    total_shares: u128,
+    // Most accounts that may stake at once, counted by staked_since
+    max_stakers: Option<u64>,
+    // Block rewards were paused at, nothing accrues until they resume
+    rewards_paused_since: Option<BlockHeight>,
+    // Every stake deposit ever made, unstakes don't lower it
+    cumulative_staked: u128,
+    // (start block, end block, multiplier_bps): rewards earned from start up
+    // to but excluding end are multiplied, on top of any lock boost
+    boost_window: Option<(BlockHeight, BlockHeight, u16)>,
+    // What one yoctoNEAR of reward weight earned since the contract was
+    // created, in reward_rate units times boosted blocks in basis points.
+    // Brought up to date by update_reward_per_token
+    reward_per_token_stored: u128,
+    // Block reward_per_token_stored was last brought up to
+    last_reward_block: BlockHeight,
 }
 
 #[near_bindgen]
@@ -21,6 +168,28 @@ impl StakingContract {
             owner: env::predecessor_account_id(),
             stake_balances: UnorderedMap::new(b"s".to_vec()),
             total_staked: 0,
+            reward_rate: 0,
+            reward_pool: 0,
+            reward_checkpoints: LookupMap::new(b"r".to_vec()),
+            claimable: LookupMap::new(b"c".to_vec()),
+            total_claimable: 0,
+            pending_withdrawals: LookupMap::new(b"w".to_vec()),
+            queued_rewards: 0,
+            unconfirmed_unstakes: 0,
+            unstake_cooldown: 0,
+            unstake_fee_bps: 0,
+            drain_timelock: 0,
+            drain_proposal: None,
+            reward_token: None,
+            staked_since: UnorderedMap::new(b"t".to_vec()),
+            locks: LookupMap::new(b"l".to_vec()),
+            total_shares: 0,
+            max_stakers: None,
+            rewards_paused_since: None,
+            cumulative_staked: 0,
+            boost_window: None,
+            reward_per_token_stored: 0,
+            last_reward_block: env::block_height(),
         }
     }
 
@@ -29,39 +198,157 @@ impl StakingContract {
         let deposit = env::attached_deposit();
         let user = env::predecessor_account_id();
         log!("{} is staking {}", user, deposit);
+        if self.staked_since.get(&user).is_none() {
+            assert!(
+                self.max_stakers.is_none_or(|max_stakers| self.staked_since.len() < max_stakers),
+                "Staker limit reached"
+            );
+            self.staked_since.insert(&user, &env::block_height());
+        }
+        self.cumulative_staked = self.cumulative_staked.saturating_add(deposit.as_yoctonear());
 
         match self.stake_balances.get(&user) {
             Some(balance) => {
+                self.accrue_rewards(&user, balance);
                 let new_balance = balance.saturating_add(deposit.as_yoctonear());
                 self.stake_balances.insert(&user, &new_balance);
                 self.total_staked = self.total_staked.saturating_add(deposit.as_yoctonear());
+                self.total_shares = self.total_shares.saturating_add(deposit.as_yoctonear());
                 new_balance
             }
             None => {
+                self.accrue_rewards(&user, 0);
                 let new_balance = deposit.as_yoctonear();
                 self.stake_balances.insert(&user, &new_balance);
                 self.total_staked = self.total_staked.saturating_add(deposit.as_yoctonear());
+                self.total_shares = self.total_shares.saturating_add(deposit.as_yoctonear());
                 new_balance
             }
         }
     }
 
-    pub fn unstake(&mut self, amount: U128) -> bool {
+    // Stake the attached deposit and lock the caller's whole stake until
+    // `lock_until` (block timestamp, nanoseconds). The multiplier follows
+    // LOCK_BOOST_TIERS for the lock duration. An existing lock can only be
+    // extended
+    #[payable]
+    pub fn stake_locked(&mut self, lock_until: U64) -> u128 {
+        let user = env::predecessor_account_id();
+        let now = env::block_timestamp();
+        assert!(lock_until.0 > now, "Lock must end in the future");
+        if let Some(lock) = self.locks.get(&user) {
+            assert!(lock_until.0 >= lock.until.0, "Lock can't be shortened");
+        }
+        // Accrues at the previous multiplier before the lock changes
+        let balance = self.stake();
+        let duration = lock_until.0 - now;
+        let multiplier_bps = LOCK_BOOST_TIERS
+            .iter()
+            .find(|(min_duration, _)| duration >= *min_duration)
+            .map_or(BOOST_BPS_DENOMINATOR, |(_, multiplier_bps)| *multiplier_bps);
+        self.locks.insert(
+            &user,
+            &StakeLock {
+                until: lock_until,
+                multiplier_bps,
+            },
+        );
+        events::emit(
+            "stake_locked",
+            json!({
+                "account_id": user,
+                "balance": U128::from(balance),
+                "lock_until": lock_until,
+                "multiplier_bps": multiplier_bps,
+            }),
+        );
+        balance
+    }
+
+    pub fn get_lock(&self, account_id: AccountId) -> Option<StakeLock> {
+        self.locks.get(&account_id)
+    }
+
+    // The refund goes to `to` when given, e.g. a cold wallet, and to the
+    // caller otherwise
+    pub fn unstake(&mut self, amount: U128, to: Option<AccountId>) -> bool {
         assert!(u128::from(amount) > 0);
         let user = env::predecessor_account_id();
+        if let Some(lock) = self.locks.get(&user) {
+            assert!(
+                env::block_timestamp() >= lock.until.0,
+                "Stake is locked until {}",
+                lock.until.0
+            );
+        }
         log!("{} is unstaking {}", user, u128::from(amount));
 
         match self.stake_balances.get(&user) {
             Some(balance) => {
+                // Rewards earned up to now are paid out with the refund as far
+                // as the pool covers them, the remaining stake (if any) keeps
+                // accruing from this block on
+                let rewards = self.settle_rewards(&user, balance);
                 let new_balance = balance.saturating_sub(u128::from(amount));
                 self.stake_balances.insert(&user, &new_balance);
-                self.total_staked = self.total_staked.saturating_sub(u128::from(amount));
-                if new_balance == 0 {
-                    //User unstaked all their balance, so refund it all
-                    let _ = Promise::new(user).transfer(NearToken::from_yoctonear(balance));
+                // total_staked is only reduced once the transfer is confirmed
+                let withdrawn = if new_balance == 0 {
+                    //User unstaked all their balance, so refund it all. Rewards
+                    //the pool couldn't cover stay claimable
+                    if self.reward_checkpoints.get(&user).is_some_and(|checkpoint| checkpoint.accrued == 0) {
+                        self.reward_checkpoints.remove(&user);
+                    }
+                    self.staked_since.remove(&user);
+                    balance
                 } else {
                     //User unstaked a portion of their balance, refund just that
-                    let _ = Promise::new(user).transfer(NearToken::from_yoctonear(amount.0));
+                    amount.0
+                };
+                // The fee only applies to the withdrawn stake, not to rewards
+                let fee = mul_div(withdrawn, self.unstake_fee_bps.into(), MAX_FEE_BPS.into());
+                self.reward_pool += fee;
+                // Token rewards can't ride along with the NEAR refund
+                let native_rewards = if self.reward_token.is_some() {
+                    if rewards > 0 {
+                        self.pay_reward(user.clone(), rewards);
+                    }
+                    0
+                } else {
+                    rewards
+                };
+                let refund = withdrawn - fee + native_rewards;
+                events::emit(
+                    "unstake",
+                    json!({
+                        "account_id": user,
+                        "gross": U128::from(withdrawn),
+                        "fee": U128::from(fee),
+                        "net": U128::from(withdrawn - fee),
+                    }),
+                );
+                let mut pending = self.pending_withdrawals.get(&user).unwrap_or(PendingWithdrawal {
+                    amount: 0,
+                    unstaked: 0,
+                    in_flight: false,
+                    claimable_at: 0,
+                    recipient: None,
+                    rewards: 0,
+                });
+                pending.recipient = to;
+                pending.amount += refund;
+                pending.unstaked += u128::from(amount);
+                self.unconfirmed_unstakes += u128::from(amount);
+                pending.rewards += native_rewards;
+                self.queued_rewards += native_rewards;
+                // Unstaking again restarts the cooldown for everything pending
+                pending.claimable_at = env::block_timestamp() + self.unstake_cooldown;
+                let in_flight = pending.in_flight;
+                self.pending_withdrawals.insert(&user, &pending);
+                // Without a cooldown the refund is sent right away. Otherwise,
+                // or if an earlier transfer is still unresolved, it waits in
+                // pending_withdrawals until withdraw is called
+                if self.unstake_cooldown == 0 && !in_flight {
+                    self.send_pending_withdrawal(user);
                 }
                 true
             }
@@ -69,18 +356,542 @@ impl StakingContract {
         }
     }
 
-    pub fn airdrop(&mut self, amount: u128) {
+    // Unstake the caller's whole balance and drop them from stake_balances.
+    // Returns the amount unstaked. As with unstake, total_staked follows
+    // once the refund is confirmed
+    pub fn unstake_all(&mut self) -> U128 {
+        let user = env::predecessor_account_id();
+        let balance = self.stake_balances.get(&user).unwrap_or(0);
+        assert!(balance > 0, "Nothing staked");
+        self.unstake(U128::from(balance), None);
+        self.stake_balances.remove(&user);
+        U128::from(balance)
+    }
+
+    // Retry sending everything still pending for the caller, e.g. after a
+    // failed transfer. Returns the amount sent
+    pub fn withdraw(&mut self) -> U128 {
+        let user = env::predecessor_account_id();
+        let pending = self
+            .pending_withdrawals
+            .get(&user)
+            .expect("No pending withdrawal");
+        assert!(!pending.in_flight, "Withdrawal already in flight");
+        assert!(
+            env::block_timestamp() >= pending.claimable_at,
+            "Withdrawal is still in cooldown"
+        );
+        log!("{} is withdrawing {}", user, pending.amount);
+        self.send_pending_withdrawal(user);
+        U128::from(pending.amount)
+    }
+
+    #[private]
+    pub fn on_withdraw(
+        &mut self,
+        account_id: AccountId,
+        amount: U128,
+        unstaked: U128,
+        rewards: U128,
+    ) {
+        let mut pending = self.pending_withdrawals.get(&account_id).unwrap();
+        pending.in_flight = false;
+        if is_promise_success() {
+            pending.amount -= amount.0;
+            pending.unstaked -= unstaked.0;
+            self.unconfirmed_unstakes = self.unconfirmed_unstakes.saturating_sub(unstaked.0);
+            pending.rewards -= rewards.0;
+            self.queued_rewards = self.queued_rewards.saturating_sub(rewards.0);
+            self.total_staked = self.total_staked.saturating_sub(unstaked.0);
+            self.total_shares = self.total_shares.saturating_sub(unstaked.0);
+        } else {
+            log!("Withdrawal of {} to {} failed", amount.0, account_id);
+            // e.g. a mistyped `to` that doesn't exist, withdraw retries
+            // to the staker instead of failing the same way forever
+            pending.recipient = None;
+        }
+        if pending.amount == 0 {
+            self.pending_withdrawals.remove(&account_id);
+        } else {
+            self.pending_withdrawals.insert(&account_id, &pending);
+        }
+    }
+
+    // Active stake plus what is waiting in the withdrawal queue
+    pub fn get_account_state(&self, account_id: AccountId) -> StakeAccount {
+        let pending = self.pending_withdrawals.get(&account_id);
+        StakeAccount {
+            active: U128::from(self.stake_balances.get(&account_id).unwrap_or(0)),
+            pending: U128::from(pending.as_ref().map_or(0, |pending| pending.amount)),
+            claimable_at: pending.map(|pending| U64::from(pending.claimable_at)),
+        }
+    }
+
+    pub fn get_pending_withdrawal(&self, account_id: AccountId) -> U128 {
+        U128::from(
+            self.pending_withdrawals
+                .get(&account_id)
+                .map_or(0, |pending| pending.amount),
+        )
+    }
+
+    // Send `amount` to at most `limit` stakers starting at `from_index`.
+    // Returns the index to resume from, equal to the staker count once done
+    pub fn airdrop_range(&mut self, from_index: u64, limit: u64, amount: u128) -> u64 {
         let user = env::predecessor_account_id();
         assert!(user == self.owner);
-        for (staker, _) in self.stake_balances.iter() {
-            let _ = Promise::new(staker).transfer(NearToken::from_yoctonear(amount));
+        let stakers = self.stake_balances.keys_as_vector();
+        let end = from_index.saturating_add(limit).min(stakers.len());
+        for index in from_index..end {
+            if let Some(staker) = stakers.get(index) {
+                self.pay_reward(staker, amount);
+            }
+        }
+        end.max(from_index)
+    }
+
+    // Wind-down: send stakers `from_index..from_index + limit` their whole
+    // stake back, ignoring locks and the unstake fee, and return the next
+    // index. Entries are zeroed rather than removed so indexes stay stable
+    // across pages. Rewards earned so far stay claimable, and as with
+    // unstake, total_staked follows once each transfer is confirmed
+    pub fn return_stakes_range(&mut self, from_index: u64, limit: u64) -> u64 {
+        self.only_owner();
+        let keys = self.stake_balances.keys_as_vector();
+        let end = from_index.saturating_add(limit).min(keys.len());
+        let stakers: Vec<AccountId> = (from_index..end).filter_map(|index| keys.get(index)).collect();
+        for staker in stakers {
+            let balance = self.stake_balances.get(&staker).unwrap_or(0);
+            if balance == 0 {
+                continue;
+            }
+            self.accrue_rewards(&staker, balance);
+            self.stake_balances.insert(&staker, &0);
+            self.staked_since.remove(&staker);
+            self.locks.remove(&staker);
+
+            let mut pending = self.pending_withdrawals.get(&staker).unwrap_or(PendingWithdrawal {
+                amount: 0,
+                unstaked: 0,
+                in_flight: false,
+                claimable_at: 0,
+                recipient: None,
+                rewards: 0,
+            });
+            pending.amount += balance;
+            pending.unstaked += balance;
+            self.unconfirmed_unstakes += balance;
+            pending.claimable_at = env::block_timestamp();
+            let in_flight = pending.in_flight;
+            self.pending_withdrawals.insert(&staker, &pending);
+            events::emit(
+                "stake_returned",
+                json!({
+                    "account_id": staker,
+                    "amount": U128::from(balance),
+                }),
+            );
+            // An unresolved earlier transfer leaves this one to withdraw
+            if !in_flight {
+                self.send_pending_withdrawal(staker);
+            }
+        }
+        end.max(from_index)
+    }
+
+    // Record a share of `total_amount` for every staker, proportional to
+    // their stake. Stakers pull their share with claim_airdrop
+    pub fn airdrop_pull(&mut self, total_amount: u128) {
+        self.only_owner();
+        assert!(self.total_staked > 0, "No stakers");
+        let shares: Vec<(AccountId, u128)> = self
+            .stake_balances
+            .iter()
+            .map(|(staker, balance)| (staker, mul_div(balance, total_amount, self.total_staked)))
+            .collect();
+        for (staker, share) in shares {
+            if share > 0 {
+                self.add_claimable(&staker, share);
+            }
+        }
+    }
+
+    // Like airdrop_pull, but each staker is weighted by
+    // stake * (blocks since their first stake). If every weight is zero,
+    // e.g. everyone staked this block, shares follow stake alone
+    pub fn airdrop_time_weighted(&mut self, total_amount: u128) {
+        self.only_owner();
+        assert!(self.total_staked > 0, "No stakers");
+        let now = env::block_height();
+        let weights: Vec<(AccountId, u128)> = self
+            .stake_balances
+            .iter()
+            .map(|(staker, balance)| {
+                let since = self.staked_since.get(&staker).unwrap_or(now);
+                let weight = balance
+                    .checked_mul(now.saturating_sub(since).into())
+                    .expect("Airdrop weight overflow");
+                (staker, weight)
+            })
+            .collect();
+        let total_weight = weights
+            .iter()
+            .try_fold(0u128, |total, (_, weight)| total.checked_add(*weight))
+            .expect("Airdrop weight overflow");
+        if total_weight == 0 {
+            return self.airdrop_pull(total_amount);
+        }
+
+        for (staker, weight) in weights {
+            let share = mul_div(weight, total_amount, total_weight);
+            if share > 0 {
+                self.add_claimable(&staker, share);
+            }
+        }
+    }
+
+    // Block of the account's reward checkpoint, see accrue_rewards
+    pub fn get_last_update(&self, account_id: AccountId) -> Option<U64> {
+        self.reward_checkpoints
+            .get(&account_id)
+            .map(|checkpoint| U64::from(checkpoint.last_update_block))
+    }
+
+    pub fn get_staked_since(&self, account_id: AccountId) -> Option<U64> {
+        self.staked_since.get(&account_id).map(U64::from)
+    }
+
+    pub fn claim_airdrop(&mut self) -> U128 {
+        let user = env::predecessor_account_id();
+        let amount = self.claimable.remove(&user).expect("Nothing to claim");
+        self.total_claimable -= amount;
+        log!("{} is claiming {}", user, amount);
+        self.pay_reward(user, amount);
+        U128::from(amount)
+    }
+
+    // Pay out everything the caller earned so far without unstaking
+    // Pays at most what is left in the reward pool. The rest stays accrued
+    // and can be claimed once the pool is funded again
+    pub fn claim_rewards(&mut self) -> U128 {
+        let user = env::predecessor_account_id();
+        let balance = self.stake_balances.get(&user).unwrap_or(0);
+        self.accrue_rewards(&user, balance);
+        let accrued = self.reward_checkpoints.get(&user).unwrap().accrued;
+        assert!(accrued > 0, "No rewards to claim");
+        let rewards = self.take_accrued(&user, accrued.min(self.reward_pool));
+        assert!(rewards > 0, "Reward pool exhausted");
+        if rewards < accrued {
+            events::emit(
+                "partial_claim",
+                json!({
+                    "account_id": user,
+                    "paid": U128::from(rewards),
+                    "remaining": U128::from(accrued - rewards),
+                }),
+            );
+        }
+        log!("{} is claiming {} in rewards", user, rewards);
+        self.pay_reward(user, rewards);
+        U128::from(rewards)
+    }
+
+    // A failed token payout is kept claimable through claim_airdrop
+    #[private]
+    pub fn on_reward_paid(&mut self, account_id: AccountId, amount: U128) {
+        if !is_promise_success() {
+            log!("Reward payout of {} to {} failed", amount.0, account_id);
+            self.add_claimable(&account_id, amount.0);
+        }
+    }
+
+    // Pay rewards in `reward_token` instead of NEAR, or back in NEAR with None.
+    // The pool and airdrop claims are in units of the reward asset, so both
+    // must be empty to switch
+    pub fn set_reward_token(&mut self, reward_token: Option<AccountId>) {
+        self.only_owner();
+        assert!(
+            self.reward_pool == 0,
+            "Reward pool must be empty to change the reward token"
+        );
+        assert!(
+            self.total_claimable == 0,
+            "Airdrop claims must be paid out to change the reward token"
+        );
+        self.reward_token = reward_token;
+    }
+
+    pub fn get_reward_token(&self) -> Option<AccountId> {
+        self.reward_token.clone()
+    }
+
+    pub fn get_claimable(&self, account_id: AccountId) -> U128 {
+        U128::from(self.claimable.get(&account_id).unwrap_or(0))
+    }
+
+    // Fee in basis points kept from withdrawn stake and added to the reward pool
+    pub fn set_unstake_fee_bps(&mut self, fee_bps: u16) {
+        self.only_owner();
+        assert!(fee_bps <= MAX_FEE_BPS, "Fee can be at most {} bps", MAX_FEE_BPS);
+        self.unstake_fee_bps = fee_bps;
+    }
+
+    pub fn get_unstake_fee_bps(&self) -> u16 {
+        self.unstake_fee_bps
+    }
+
+    // Time in nanoseconds unstaked funds wait before they can be withdrawn
+    pub fn set_unstake_cooldown(&mut self, cooldown: U64) {
+        self.only_owner();
+        self.unstake_cooldown = cooldown.into();
+    }
+
+    pub fn get_unstake_cooldown(&self) -> U64 {
+        U64::from(self.unstake_cooldown)
+    }
+
+    // Time in nanoseconds a drain proposal waits before it can be executed
+    pub fn set_drain_timelock(&mut self, timelock: U64) {
+        self.only_owner();
+        self.drain_timelock = timelock.into();
+    }
+
+    pub fn get_drain_timelock(&self) -> U64 {
+        U64::from(self.drain_timelock)
+    }
+
+    // Propose sending `amount` of the residual balance to `to`, replacing any
+    // earlier proposal and restarting the timelock
+    pub fn propose_drain(&mut self, to: AccountId, amount: U128) {
+        self.only_owner();
+        assert!(amount.0 > 0, "Drain amount must be positive");
+        log!("Drain of {} to {} proposed", amount.0, to);
+        self.drain_proposal = Some(DrainProposal {
+            to,
+            amount: amount.into(),
+            proposed_at: env::block_timestamp(),
+        });
+    }
+
+    // Execute the proposed drain. Only the balance above total_staked and the
+    // reward pool can leave, stakers' principal is never touched
+    pub fn execute_drain(&mut self) -> U128 {
+        self.only_owner();
+        let proposal = self.drain_proposal.take().expect("No drain proposed");
+        assert!(
+            env::block_timestamp() >= proposal.proposed_at.saturating_add(self.drain_timelock),
+            "Drain is still timelocked"
+        );
+        assert!(
+            proposal.amount <= self.get_residual_balance().0,
+            "Drain exceeds residual balance"
+        );
+        log!("Draining {} to {}", proposal.amount, proposal.to);
+        let _ = Promise::new(proposal.to).transfer(NearToken::from_yoctonear(proposal.amount));
+        U128::from(proposal.amount)
+    }
+
+    pub fn cancel_drain(&mut self) {
+        self.only_owner();
+        assert!(self.drain_proposal.take().is_some(), "No drain proposed");
+    }
+
+    // Balance not owed to stakers, reserved for rewards or waiting to pay out
+    // rewards already earned
+    pub fn get_residual_balance(&self) -> U128 {
+        self.get_balance_breakdown().free
+    }
+
+    pub fn get_balance_breakdown(&self) -> BalanceBreakdown {
+        let account_balance = env::account_balance().as_yoctonear();
+        // Token claims are paid from the token balance, not from NEAR
+        let claimable = if self.reward_token.is_none() { self.total_claimable } else { 0 };
+        let committed = self
+            .total_staked
+            .checked_add(self.reward_pool)
+            .and_then(|committed| committed.checked_add(self.queued_rewards))
+            .and_then(|committed| committed.checked_add(claimable));
+        BalanceBreakdown {
+            account_balance: U128::from(account_balance),
+            total_staked: U128::from(self.total_staked),
+            reward_pool: U128::from(self.reward_pool),
+            queued_rewards: U128::from(self.queued_rewards),
+            claimable: U128::from(claimable),
+            free: U128::from(committed.map_or(0, |committed| account_balance.saturating_sub(committed))),
+            underfunded: committed.is_none_or(|committed| committed > account_balance),
+        }
+    }
+
+    // Recompute total_staked from stake_balances plus everything unstaked but
+    // not yet confirmed, including accounts unstake_all already dropped from
+    // stake_balances, and overwrite it. Visits every staker, so gas grows
+    // with the size of stake_balances. Returns the new total
+    pub fn reconcile_total_staked(&mut self) -> U128 {
+        self.only_owner();
+        let total = self
+            .stake_balances
+            .values()
+            .try_fold(self.unconfirmed_unstakes, |total, balance| total.checked_add(balance))
+            .expect("Stake overflow");
+        events::emit(
+            "total_staked_reconciled",
+            json!({
+                "old": U128::from(self.total_staked),
+                "new": U128::from(total),
+            }),
+        );
+        self.total_staked = total;
+        U128::from(total)
+    }
+
+    // Whether the account balance covers every staked yoctoNEAR
+    pub fn is_solvent(&self) -> bool {
+        self.solvency_margin() >= 0
+    }
+
+    // Account balance minus total_staked, negative when insolvent
+    pub fn solvency_margin(&self) -> i128 {
+        let balance = env::account_balance().as_yoctonear();
+        if balance >= self.total_staked {
+            i128::try_from(balance - self.total_staked).unwrap_or(i128::MAX)
+        } else {
+            i128::try_from(self.total_staked - balance).map_or(i128::MIN, |deficit| -deficit)
+        }
+    }
+
+    // Stop or resume reward accrual without affecting staking itself, e.g.
+    // while reward parameters are being migrated
+    pub fn set_rewards_paused(&mut self, paused: bool) {
+        self.only_owner();
+        self.update_reward_per_token();
+        match (paused, self.rewards_paused_since) {
+            (true, None) => self.rewards_paused_since = Some(env::block_height()),
+            (false, Some(_)) => self.rewards_paused_since = None,
+            _ => {}
+        }
+    }
+
+    pub fn are_rewards_paused(&self) -> bool {
+        self.rewards_paused_since.is_some()
+    }
+
+    // Run a reward promotion between two block heights, or end it with None.
+    // The multiplier is in BOOST_BPS_DENOMINATOR units
+    pub fn set_boost_window(&mut self, boost_window: Option<(U64, U64, u16)>) {
+        self.only_owner();
+        if let Some((start, end, multiplier_bps)) = boost_window {
+            assert!(start.0 < end.0, "Boost window must end after it starts");
+            assert!(
+                multiplier_bps >= BOOST_BPS_DENOMINATOR,
+                "Boost multiplier must be at least {}",
+                BOOST_BPS_DENOMINATOR
+            );
+        }
+        self.update_reward_per_token();
+        self.boost_window = boost_window.map(|(start, end, multiplier_bps)| (start.0, end.0, multiplier_bps));
+    }
+
+    pub fn get_boost_window(&self) -> Option<(U64, U64, u16)> {
+        self.boost_window
+            .map(|(start, end, multiplier_bps)| (U64::from(start), U64::from(end), multiplier_bps))
+    }
+
+    // Set the per-block reward rate, see REWARD_RATE_DENOMINATOR. Blocks up
+    // to this one keep earning at the previous rate
+    pub fn set_reward_rate(&mut self, reward_rate: U128) {
+        self.only_owner();
+        self.update_reward_per_token();
+        self.reward_rate = reward_rate.into();
+    }
+
+    // Add the attached deposit to the pool rewards are paid from
+    #[payable]
+    pub fn fund_rewards(&mut self) -> U128 {
+        assert!(
+            self.reward_token.is_none(),
+            "Rewards are funded by transferring the reward token"
+        );
+        let deposit = env::attached_deposit().as_yoctonear();
+        assert!(deposit > 0, "Deposit is required to fund rewards");
+        self.reward_pool = self.reward_pool.saturating_add(deposit);
+        U128::from(self.reward_pool)
+    }
+
+    pub fn get_reward_rate(&self) -> U128 {
+        U128::from(self.reward_rate)
+    }
+
+    pub fn get_reward_pool(&self) -> U128 {
+        U128::from(self.reward_pool)
+    }
+
+    pub fn get_pending_rewards(&self, account_id: AccountId) -> U128 {
+        let balance = self.stake_balances.get(&account_id).unwrap_or(0);
+        let checkpoint = match self.reward_checkpoints.get(&account_id) {
+            Some(checkpoint) => checkpoint,
+            None => return U128::from(0),
+        };
+        U128::from(checkpoint.accrued + self.earned_since(&account_id, &checkpoint, balance))
+    }
+
+    // The `limit` largest stakers, largest first. `limit` is capped at
+    // MAX_TOP_STAKERS to bound the heap, though every staker is still visited
+    pub fn get_top_stakers(&self, limit: u64) -> Vec<(AccountId, U128)> {
+        let limit = limit.min(MAX_TOP_STAKERS) as usize;
+        if limit == 0 {
+            return Vec::new();
+        }
+        // Min-heap on balance, so the smallest of the current top is evicted
+        let mut top = BinaryHeap::with_capacity(limit + 1);
+        for (staker, balance) in self.stake_balances.iter() {
+            top.push(Reverse((balance, staker)));
+            if top.len() > limit {
+                top.pop();
+            }
         }
+        top.into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((balance, staker))| (staker, U128::from(balance)))
+            .collect()
+    }
+
+    // Value of one share in staked plus pooled yoctoNEAR, scaled by
+    // EXCHANGE_RATE_PRECISION. 1.0 while no shares exist
+    pub fn get_exchange_rate(&self) -> U128 {
+        if self.total_shares == 0 {
+            return U128::from(EXCHANGE_RATE_PRECISION);
+        }
+        let backing = self.total_staked.saturating_add(self.reward_pool);
+        U128::from(mul_div(backing, EXCHANGE_RATE_PRECISION, self.total_shares))
+    }
+
+    // Lowering the cap below the current count only stops new stakers
+    pub fn set_max_stakers(&mut self, max_stakers: Option<U64>) {
+        self.only_owner();
+        self.max_stakers = max_stakers.map(u64::from);
+    }
+
+    pub fn get_max_stakers(&self) -> Option<U64> {
+        self.max_stakers.map(U64::from)
+    }
+
+    // Accounts with an active stake
+    pub fn get_staker_count(&self) -> U64 {
+        U64::from(self.staked_since.len())
+    }
+
+    pub fn is_owner(&self, account_id: AccountId) -> bool {
+        account_id == self.owner
     }
 
     pub fn get_total_staked(&self) -> u128 {
         self.total_staked
     }
 
+    pub fn get_cumulative_staked(&self) -> U128 {
+        U128::from(self.cumulative_staked)
+    }
+
     pub fn get_user_staked(&self) -> u128 {
         let user = env::predecessor_account_id();
         match self.stake_balances.get(&user) {
@@ -96,13 +907,212 @@ impl StakingContract {
     pub fn get_account_id(&self) -> AccountId {
         env::current_account_id()
     }
+
+    // **** Helpers ****
+
+    fn only_owner(&self) {
+        if env::predecessor_account_id() != self.owner {
+            env::panic_str("Can only be called by owner");
+        }
+    }
+
+    // reward_per_token_stored as of the current block. Nothing is added
+    // while rewards are paused, blocks inside the boost window add its
+    // multiplier
+    fn reward_per_token(&self) -> u128 {
+        let now = env::block_height();
+        if self.rewards_paused_since.is_some() || now <= self.last_reward_block {
+            return self.reward_per_token_stored;
+        }
+        let boost_bps = u128::from(BOOST_BPS_DENOMINATOR);
+        let mut weighted_blocks = u128::from(now - self.last_reward_block) * boost_bps;
+        if let Some((start, end, multiplier_bps)) = self.boost_window {
+            let boosted = now.min(end).saturating_sub(self.last_reward_block.max(start));
+            weighted_blocks += u128::from(boosted) * (u128::from(multiplier_bps) - boost_bps);
+        }
+        self.reward_rate
+            .checked_mul(weighted_blocks)
+            .and_then(|added| self.reward_per_token_stored.checked_add(added))
+            .expect("Reward overflow")
+    }
+
+    // Bank what the current reward parameters earned up to this block. Must
+    // run before reward_rate, the pause or the boost window change
+    fn update_reward_per_token(&mut self) {
+        self.reward_per_token_stored = self.reward_per_token();
+        self.last_reward_block = env::block_height();
+    }
+
+    // Rewards earned by `balance` since the checkpoint was last moved
+    fn rewards_since(&self, checkpoint: &RewardCheckpoint, balance: u128) -> u128 {
+        mul_div(
+            balance,
+            self.reward_per_token() - checkpoint.reward_debt,
+            REWARD_RATE_DENOMINATOR * u128::from(BOOST_BPS_DENOMINATOR),
+        )
+    }
+
+    // Rewards earned by `balance` since the checkpoint, boosted by the
+    // account's lock multiplier up to lock.until. Rewards are taken to accrue
+    // evenly over time in between, so the boost of a lock that expired since
+    // the checkpoint covers the share of the time before the expiry
+    fn earned_since(
+        &self,
+        account_id: &AccountId,
+        checkpoint: &RewardCheckpoint,
+        balance: u128,
+    ) -> u128 {
+        let earned = self.rewards_since(checkpoint, balance);
+        let lock = match self.locks.get(account_id) {
+            Some(lock) => lock,
+            None => return earned,
+        };
+        let boosted = mul_div(earned, lock.multiplier_bps.into(), BOOST_BPS_DENOMINATOR.into());
+        let now = env::block_timestamp();
+        if now <= lock.until.0 {
+            return boosted;
+        }
+        let elapsed = now - checkpoint.last_update_timestamp;
+        let locked = lock.until.0.saturating_sub(checkpoint.last_update_timestamp).min(elapsed);
+        if locked == 0 {
+            return earned;
+        }
+        earned + mul_div(boosted - earned, locked.into(), elapsed.into())
+    }
+
+    // Move the user's checkpoint to the current block, banking what `balance`
+    // earned in between. Must run before the stake balance changes.
+    // An expired lock boosts the time up to its expiry and is then dropped
+    // Emits checkpoint_updated whenever the checkpoint moves to a new block
+    fn accrue_rewards(&mut self, account_id: &AccountId, balance: u128) {
+        self.update_reward_per_token();
+        let previous = self.reward_checkpoints.get(account_id);
+        let accrued = match &previous {
+            Some(checkpoint) => checkpoint.accrued + self.earned_since(account_id, checkpoint, balance),
+            None => 0,
+        };
+        let now = env::block_height();
+        if previous.is_none_or(|checkpoint| checkpoint.last_update_block != now) {
+            events::emit(
+                "checkpoint_updated",
+                json!({
+                    "account_id": account_id,
+                    "block_height": U64::from(now),
+                    "accrued": U128::from(accrued),
+                }),
+            );
+        }
+        if matches!(self.locks.get(account_id), Some(lock) if env::block_timestamp() >= lock.until.0) {
+            self.locks.remove(account_id);
+        }
+        self.reward_checkpoints.insert(
+            account_id,
+            &RewardCheckpoint {
+                last_update_block: now,
+                accrued,
+                reward_debt: self.reward_per_token_stored,
+                last_update_timestamp: env::block_timestamp(),
+            },
+        );
+    }
+
+    // Transfer everything pending for the account, settled in on_withdraw
+    fn send_pending_withdrawal(&mut self, account_id: AccountId) {
+        let mut pending = self.pending_withdrawals.get(&account_id).unwrap();
+        pending.in_flight = true;
+        self.pending_withdrawals.insert(&account_id, &pending);
+        gas::assert_min_gas(GAS_FOR_WITHDRAW_CALLBACK);
+        let recipient = pending.recipient.clone().unwrap_or_else(|| account_id.clone());
+        let _ = Promise::new(recipient)
+            .transfer(NearToken::from_yoctonear(pending.amount))
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_WITHDRAW_CALLBACK)
+                    .on_withdraw(
+                        account_id,
+                        U128::from(pending.amount),
+                        U128::from(pending.unstaked),
+                        U128::from(pending.rewards),
+                    ),
+            );
+    }
+
+    // Send `amount` of the reward asset, settled in on_reward_paid when it is
+    // a fungible token
+    fn pay_reward(&self, account_id: AccountId, amount: u128) {
+        match &self.reward_token {
+            None => {
+                let _ = Promise::new(account_id).transfer(NearToken::from_yoctonear(amount));
+            }
+            Some(reward_token) => {
+                gas::assert_min_gas(GAS_FOR_FT_TRANSFER.saturating_add(GAS_FOR_REWARD_CALLBACK));
+                let _ = ext_ft_core::ext(reward_token.clone())
+                    .with_attached_deposit(NearToken::from_yoctonear(1))
+                    .with_static_gas(GAS_FOR_FT_TRANSFER)
+                    .ft_transfer(account_id.clone(), U128::from(amount), None)
+                    .then(
+                        Self::ext(env::current_account_id())
+                            .with_static_gas(GAS_FOR_REWARD_CALLBACK)
+                            .on_reward_paid(account_id, U128::from(amount)),
+                    );
+            }
+        }
+    }
+
+    // Accrue and take everything the user earned so far out of the reward
+    // pool, or as much as it holds. The rest stays accrued, as with
+    // claim_rewards, so an underfunded pool never blocks an unstake
+    fn settle_rewards(&mut self, account_id: &AccountId, balance: u128) -> u128 {
+        self.accrue_rewards(account_id, balance);
+        let accrued = self.reward_checkpoints.get(account_id).unwrap().accrued;
+        self.take_accrued(account_id, accrued.min(self.reward_pool))
+    }
+
+    // Record `amount` more for the account to pull with claim_airdrop
+    fn add_claimable(&mut self, account_id: &AccountId, amount: u128) {
+        let claimable = self.claimable.get(account_id).unwrap_or(0);
+        self.claimable.insert(account_id, &(claimable + amount));
+        self.total_claimable += amount;
+    }
+
+    // Move `amount` of the user's accrued rewards out of the reward pool
+    fn take_accrued(&mut self, account_id: &AccountId, amount: u128) -> u128 {
+        let mut checkpoint = self.reward_checkpoints.get(account_id).unwrap();
+        checkpoint.accrued -= amount;
+        self.reward_pool -= amount;
+        self.reward_checkpoints.insert(account_id, &checkpoint);
+        amount
+    }
+}
+
+#[near_bindgen]
+impl FungibleTokenReceiver for StakingContract {
+    // Transfers of the reward token fund the reward pool
+    fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        // No message formats are defined, any transfer just funds the pool
+        let _ = msg;
+        assert_eq!(
+            Some(env::predecessor_account_id()),
+            self.reward_token,
+            "Only the reward token can fund rewards"
+        );
+        log!("{} funded rewards with {}", sender_id, amount.0);
+        self.reward_pool = self.reward_pool.saturating_add(amount.0);
+        PromiseOrValue::Value(U128::from(0))
+    }
 }
 
 #[cfg(all(test, not(target_arch = "wasm32")))]
 mod tests {
     use super::*;
-    use near_sdk::test_utils::{accounts, VMContextBuilder};
-    use near_sdk::testing_env;
+    use near_sdk::mock::MockAction;
+    use near_sdk::test_utils::{accounts, get_created_receipts, get_logs, VMContextBuilder};
+    use near_sdk::{testing_env, test_vm_config, PromiseResult, RuntimeFeesConfig};
 
     fn get_context(
         predecessor_account_id: AccountId,
@@ -116,6 +1126,63 @@ mod tests {
         builder
     }
 
+    // Sum of NEAR transferred to `account_id` by the last call
+    fn transferred_to(account_id: &AccountId) -> u128 {
+        get_created_receipts()
+            .iter()
+            .filter(|receipt| &receipt.receiver_id == account_id)
+            .flat_map(|receipt| receipt.actions.iter())
+            .map(|action| match action {
+                MockAction::Transfer { deposit, .. } => deposit.as_yoctonear(),
+                _ => 0,
+            })
+            .sum()
+    }
+
+    // Run on_withdraw for everything in flight for `account_id` with the given
+    // transfer outcome, then switch back to calling as `account_id`
+    fn resolve_withdrawal(
+        context: &mut VMContextBuilder,
+        contract: &mut StakingContract,
+        account_id: AccountId,
+        result: PromiseResult,
+    ) {
+        let pending = contract.pending_withdrawals.get(&account_id).unwrap();
+        testing_env!(
+            context.predecessor_account_id(accounts(0)).build(),
+            test_vm_config(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![result],
+        );
+        contract.on_withdraw(
+            account_id.clone(),
+            U128::from(pending.amount),
+            U128::from(pending.unstaked),
+            U128::from(pending.rewards),
+        );
+        testing_env!(context.predecessor_account_id(account_id).build());
+    }
+
+    // Owner accounts(1) sets a rate of 0.001 per staked yocto per block and
+    // funds the pool with 1 NEAR, accounts(2) stakes 10 NEAR at block 0
+    fn setup_rewards(context: &mut VMContextBuilder) -> StakingContract {
+        testing_env!(context.build());
+        let mut contract = StakingContract::new();
+        contract.set_reward_rate(U128::from(1_000_000_000));
+
+        testing_env!(context.attached_deposit(NearToken::from_near(1)).build());
+        contract.fund_rewards();
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(10))
+            .predecessor_account_id(accounts(2))
+            .signer_account_id(accounts(2))
+            .build());
+        contract.stake();
+        contract
+    }
+
     #[test]
     fn test_stake_and_unstake() {
         let mut context = get_context(accounts(1), accounts(1));
@@ -134,71 +1201,1424 @@ mod tests {
         testing_env!(context
             .attached_deposit(NearToken::from_yoctonear(0))
             .build());
-        let result = contract.unstake(U128::from(5_000_000_000_000_000_000_000_000)); // 5 NEAR
+        let result = contract.unstake(U128::from(5_000_000_000_000_000_000_000_000), None); // 5 NEAR
         assert!(result);
     }
 
-    // ========== VULNERABILITY TEST CASES ==========
-
     #[test]
-    fn test_unstake_bug_when_balance_reaches_zero() {
-        // Bug: When unstaking brings balance to 0, refunds old balance instead of amount
-        // However, if balance == amount (unstaking all), this is correct
-        // The bug is more subtle - it's about consistency and edge cases
-        let mut context = get_context(accounts(1), accounts(1));
+    fn test_unstake_all() {
+        let mut context = get_context(accounts(2), accounts(2));
         testing_env!(context.build());
-
         let mut contract = StakingContract::new();
 
-        // Stake 10 NEAR
-        testing_env!(context
-            .attached_deposit(NearToken::from_near(10))
-            .build());
+        testing_env!(context.attached_deposit(NearToken::from_near(10)).build());
         contract.stake();
 
-        // Unstake exactly 10 NEAR (bringing balance to 0)
-        testing_env!(context
-            .attached_deposit(NearToken::from_yoctonear(0))
-            .build());
-        
-        // This should refund 10 NEAR (the old balance)
-        // In this case, it's correct because we're unstaking all
-        let result = contract.unstake(U128::from(NearToken::from_near(10).as_yoctonear()));
-        assert!(result);
-        
-        // User balance should be 0
-        assert_eq!(contract.get_user_staked(), 0);
+        testing_env!(context.attached_deposit(NearToken::from_yoctonear(0)).build());
+        let amount = NearToken::from_near(10).as_yoctonear();
+        assert_eq!(contract.unstake_all().0, amount);
+        assert_eq!(transferred_to(&accounts(2)), amount);
+        assert!(contract.stake_balances.get(&accounts(2)).is_none());
+        assert_eq!(contract.get_account_state(accounts(2)).active.0, 0);
+
+        resolve_withdrawal(&mut context, &mut contract, accounts(2), PromiseResult::Successful(vec![]));
+        assert_eq!(contract.get_total_staked(), 0);
+        assert_eq!(contract.get_pending_withdrawal(accounts(2)).0, 0);
+        assert!(contract.get_top_stakers(10).is_empty());
     }
 
     #[test]
-    fn test_unstake_bug_logic_inconsistency() {
-        // Demonstrates the logic inconsistency in unstake()
-        // When new_balance == 0, it refunds 'balance' (old balance)
-        // When new_balance > 0, it refunds 'amount' (unstaked amount)
-        // This is inconsistent - should always refund 'amount'
-        let mut context = get_context(accounts(1), accounts(1));
-        testing_env!(context.build());
-
+    fn test_unstake_refunds_caller_by_default() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.attached_deposit(NearToken::from_near(10)).build());
         let mut contract = StakingContract::new();
+        contract.stake();
 
-        // Stake 100 NEAR
-        testing_env!(context
-            .attached_deposit(NearToken::from_near(100))
-            .build());
+        testing_env!(context.attached_deposit(NearToken::from_yoctonear(0)).build());
+        contract.unstake(U128::from(NearToken::from_near(4).as_yoctonear()), None);
+        assert_eq!(transferred_to(&accounts(2)), NearToken::from_near(4).as_yoctonear());
+    }
+
+    #[test]
+    fn test_unstake_to_other_account() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.attached_deposit(NearToken::from_near(10)).build());
+        let mut contract = StakingContract::new();
         contract.stake();
 
-        // Unstake 50 NEAR (balance will be 50, not 0)
-        testing_env!(context
-            .attached_deposit(NearToken::from_yoctonear(0))
-            .build());
-        contract.unstake(U128::from(NearToken::from_near(50).as_yoctonear()));
-        
-        // Now unstake the remaining 50 NEAR (balance will be 0)
-        // This will refund 50 NEAR (the old balance), which is correct
-        // But the logic is inconsistent - it should always refund 'amount'
-        contract.unstake(U128::from(NearToken::from_near(50).as_yoctonear()));
-        
-        assert_eq!(contract.get_user_staked(), 0);
+        testing_env!(context.attached_deposit(NearToken::from_yoctonear(0)).build());
+        contract.unstake(U128::from(NearToken::from_near(4).as_yoctonear()), Some(accounts(3)));
+        assert_eq!(transferred_to(&accounts(3)), NearToken::from_near(4).as_yoctonear());
+        assert_eq!(transferred_to(&accounts(2)), 0);
+
+        // The withdrawal is still tracked for the staker
+        resolve_withdrawal(&mut context, &mut contract, accounts(2), PromiseResult::Successful(vec![]));
+        assert_eq!(contract.get_total_staked(), NearToken::from_near(6).as_yoctonear());
+        assert_eq!(contract.get_pending_withdrawal(accounts(2)).0, 0);
+    }
+
+    #[test]
+    fn test_failed_transfer_to_other_account_retries_to_staker() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.attached_deposit(NearToken::from_near(10)).build());
+        let mut contract = StakingContract::new();
+        contract.stake();
+
+        testing_env!(context.attached_deposit(NearToken::from_yoctonear(0)).build());
+        let amount = NearToken::from_near(10).as_yoctonear();
+        contract.unstake(U128::from(amount), Some(accounts(3)));
+        assert_eq!(transferred_to(&accounts(3)), amount);
+
+        resolve_withdrawal(&mut context, &mut contract, accounts(2), PromiseResult::Failed);
+        assert_eq!(contract.withdraw().0, amount);
+        assert_eq!(transferred_to(&accounts(2)), amount);
+        assert_eq!(transferred_to(&accounts(3)), 0);
+
+        resolve_withdrawal(&mut context, &mut contract, accounts(2), PromiseResult::Successful(vec![]));
+        assert_eq!(contract.get_pending_withdrawal(accounts(2)).0, 0);
+        assert_eq!(contract.get_total_staked(), 0);
+    }
+
+    #[test]
+    fn test_cumulative_staked_only_grows() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.attached_deposit(NearToken::from_near(10)).build());
+        let mut contract = StakingContract::new();
+        contract.stake();
+
+        testing_env!(context.attached_deposit(NearToken::from_yoctonear(0)).build());
+        contract.unstake(U128::from(NearToken::from_near(4).as_yoctonear()), None);
+        resolve_withdrawal(&mut context, &mut contract, accounts(2), PromiseResult::Successful(vec![]));
+        assert_eq!(contract.get_total_staked(), NearToken::from_near(6).as_yoctonear());
+        assert_eq!(contract.get_cumulative_staked().0, NearToken::from_near(10).as_yoctonear());
+
+        testing_env!(context.attached_deposit(NearToken::from_near(3)).build());
+        contract.stake();
+        assert_eq!(contract.get_total_staked(), NearToken::from_near(9).as_yoctonear());
+        assert_eq!(contract.get_cumulative_staked().0, NearToken::from_near(13).as_yoctonear());
+    }
+
+    #[test]
+    #[should_panic(expected = "Nothing staked")]
+    fn test_unstake_all_without_stake() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = StakingContract::new();
+        contract.unstake_all();
+    }
+
+    #[test]
+    #[should_panic(expected = "Stake is locked until")]
+    fn test_stake_locked_rejects_early_unstake() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = StakingContract::new();
+
+        testing_env!(context.attached_deposit(NearToken::from_near(10)).build());
+        contract.stake_locked(U64::from(90 * DAY));
+        assert_eq!(
+            contract.get_lock(accounts(2)),
+            Some(StakeLock {
+                until: U64::from(90 * DAY),
+                multiplier_bps: 15_000,
+            })
+        );
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .block_timestamp(90 * DAY - 1)
+            .build());
+        contract.unstake(U128::from(1), None);
+    }
+
+    #[test]
+    fn test_stake_locked_unstake_after_lock() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = StakingContract::new();
+
+        testing_env!(context.attached_deposit(NearToken::from_near(10)).build());
+        contract.stake_locked(U64::from(DAY));
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .block_timestamp(DAY)
+            .build());
+        assert!(contract.unstake(U128::from(NearToken::from_near(10).as_yoctonear()), None));
+        assert_eq!(transferred_to(&accounts(2)), NearToken::from_near(10).as_yoctonear());
+        assert_eq!(contract.get_lock(accounts(2)), None);
+    }
+
+    #[test]
+    fn test_stake_locked_boosts_rewards() {
+        let mut context = get_context(accounts(1), accounts(1));
+        let mut contract = setup_rewards(&mut context);
+
+        // accounts(3) stakes the same 10 NEAR as accounts(2), locked for 90 days
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(10))
+            .predecessor_account_id(accounts(3))
+            .signer_account_id(accounts(3))
+            .build());
+        contract.stake_locked(U64::from(90 * DAY));
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .block_height(10)
+            .build());
+        let rewards = NearToken::from_millinear(100).as_yoctonear();
+        assert_eq!(contract.get_pending_rewards(accounts(2)).0, rewards);
+        assert_eq!(contract.get_pending_rewards(accounts(3)).0, rewards * 3 / 2);
+    }
+
+    #[test]
+    fn test_stake_locked_boost_ends_at_lock_expiry() {
+        let mut context = get_context(accounts(1), accounts(1));
+        let mut contract = setup_rewards(&mut context);
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(10))
+            .predecessor_account_id(accounts(3))
+            .signer_account_id(accounts(3))
+            .build());
+        contract.stake_locked(U64::from(90 * DAY));
+
+        // The lock expired halfway through, only that half is boosted 1.5x
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .block_height(10)
+            .block_timestamp(180 * DAY)
+            .build());
+        let rewards = NearToken::from_millinear(100).as_yoctonear();
+        assert_eq!(contract.get_pending_rewards(accounts(3)).0, rewards * 5 / 4);
+        assert_eq!(contract.claim_rewards().0, rewards * 5 / 4);
+        assert_eq!(contract.get_lock(accounts(3)), None);
+    }
+
+    #[test]
+    fn test_full_unstake_settles_rewards() {
+        let mut context = get_context(accounts(1), accounts(1));
+        let mut contract = setup_rewards(&mut context);
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .block_height(10)
+            .build());
+        let rewards = NearToken::from_millinear(100).as_yoctonear();
+        assert_eq!(contract.get_pending_rewards(accounts(2)).0, rewards);
+
+        contract.unstake(U128::from(NearToken::from_near(10).as_yoctonear()), None);
+        assert_eq!(
+            transferred_to(&accounts(2)),
+            NearToken::from_near(10).as_yoctonear() + rewards
+        );
+        assert_eq!(
+            contract.get_reward_pool().0,
+            NearToken::from_near(1).as_yoctonear() - rewards
+        );
+        assert_eq!(contract.get_pending_rewards(accounts(2)).0, 0);
+    }
+
+    #[test]
+    fn test_unstake_with_empty_reward_pool_returns_principal() {
+        let mut context = get_context(accounts(1), accounts(1));
+        testing_env!(context.build());
+        let mut contract = StakingContract::new();
+        contract.set_reward_rate(U128::from(1_000_000_000));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(10))
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.stake();
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .block_height(10)
+            .build());
+        let rewards = NearToken::from_millinear(100).as_yoctonear();
+        assert!(contract.unstake(U128::from(NearToken::from_near(10).as_yoctonear()), None));
+        assert_eq!(transferred_to(&accounts(2)), NearToken::from_near(10).as_yoctonear());
+        assert_eq!(contract.get_pending_rewards(accounts(2)).0, rewards);
+
+        // Once the pool is funded the rewards can still be claimed
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.fund_rewards();
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .predecessor_account_id(accounts(2))
+            .build());
+        assert_eq!(contract.claim_rewards().0, rewards);
+        assert_eq!(contract.get_pending_rewards(accounts(2)).0, 0);
+    }
+
+    #[test]
+    fn test_get_top_stakers() {
+        let mut context = get_context(accounts(1), accounts(1));
+        testing_env!(context.build());
+        let mut contract = StakingContract::new();
+
+        for (index, near) in [(1, 3), (2, 7), (3, 1), (4, 5)] {
+            testing_env!(context
+                .attached_deposit(NearToken::from_near(near))
+                .predecessor_account_id(accounts(index))
+                .build());
+            contract.stake();
+        }
+
+        assert_eq!(
+            contract.get_top_stakers(2),
+            vec![
+                (accounts(2), U128::from(NearToken::from_near(7).as_yoctonear())),
+                (accounts(4), U128::from(NearToken::from_near(5).as_yoctonear())),
+            ]
+        );
+        assert_eq!(contract.get_top_stakers(10).len(), 4);
+        assert!(contract.get_top_stakers(0).is_empty());
+    }
+
+    #[test]
+    fn test_max_stakers() {
+        let mut context = get_context(accounts(1), accounts(1));
+        testing_env!(context.build());
+        let mut contract = StakingContract::new();
+        contract.set_max_stakers(Some(U64::from(2)));
+
+        for staker in [accounts(2), accounts(3)] {
+            testing_env!(context
+                .attached_deposit(NearToken::from_near(1))
+                .predecessor_account_id(staker)
+                .build());
+            contract.stake();
+        }
+        assert_eq!(contract.get_staker_count(), U64::from(2));
+
+        // Existing stakers can still add to their stake
+        assert_eq!(contract.stake(), NearToken::from_near(2).as_yoctonear());
+
+        // A full exit frees a spot
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.unstake(U128::from(NearToken::from_near(1).as_yoctonear()), None);
+        assert_eq!(contract.get_staker_count(), U64::from(1));
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .predecessor_account_id(accounts(4))
+            .build());
+        contract.stake();
+        assert_eq!(contract.get_staker_count(), U64::from(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "Staker limit reached")]
+    fn test_max_stakers_rejects_new_staker() {
+        let mut context = get_context(accounts(1), accounts(1));
+        testing_env!(context.build());
+        let mut contract = StakingContract::new();
+        contract.set_max_stakers(Some(U64::from(1)));
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.stake();
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.stake();
+    }
+
+    #[test]
+    fn test_is_owner() {
+        let context = get_context(accounts(1), accounts(1));
+        testing_env!(context.build());
+        let contract = StakingContract::new();
+        assert!(contract.is_owner(accounts(1)));
+        assert!(!contract.is_owner(accounts(2)));
+    }
+
+    #[test]
+    fn test_exchange_rate_rises_with_reward_pool() {
+        let mut context = get_context(accounts(1), accounts(1));
+        testing_env!(context.build());
+        let mut contract = StakingContract::new();
+        assert_eq!(contract.get_exchange_rate().0, EXCHANGE_RATE_PRECISION);
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(10))
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.stake();
+        assert_eq!(contract.get_exchange_rate().0, EXCHANGE_RATE_PRECISION);
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.fund_rewards();
+        // 11 NEAR backs 10 NEAR of shares
+        assert_eq!(
+            contract.get_exchange_rate().0,
+            EXCHANGE_RATE_PRECISION / 10 * 11
+        );
+    }
+
+    #[test]
+    fn test_checkpoint_advances_on_claim() {
+        let mut context = get_context(accounts(1), accounts(1));
+        let mut contract = setup_rewards(&mut context);
+        assert_eq!(contract.get_last_update(accounts(2)), Some(U64::from(0)));
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .block_height(10)
+            .build());
+        contract.claim_rewards();
+
+        assert_eq!(contract.get_last_update(accounts(2)), Some(U64::from(10)));
+        let rewards = NearToken::from_millinear(100).as_yoctonear();
+        assert!(get_logs().contains(&format!(
+            "EVENT_JSON:{}",
+            json!({
+                "standard": events::EVENT_STANDARD,
+                "version": events::EVENT_VERSION,
+                "event": "checkpoint_updated",
+                "data": [{
+                    "account_id": accounts(2),
+                    "block_height": U64::from(10),
+                    "accrued": U128::from(rewards),
+                }],
+            })
+        )));
+    }
+
+    #[test]
+    fn test_rewards_paused_interval_earns_nothing() {
+        let mut context = get_context(accounts(1), accounts(1));
+        let mut contract = setup_rewards(&mut context);
+        let rewards = NearToken::from_millinear(100).as_yoctonear();
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .predecessor_account_id(accounts(1))
+            .block_height(10)
+            .build());
+        contract.set_rewards_paused(true);
+        assert!(contract.are_rewards_paused());
+
+        testing_env!(context.block_height(30).build());
+        assert_eq!(contract.get_pending_rewards(accounts(2)), U128::from(rewards));
+        contract.set_rewards_paused(false);
+
+        // Only blocks 0-10 and 30-40 earned rewards
+        testing_env!(context.block_height(40).build());
+        assert_eq!(contract.get_pending_rewards(accounts(2)), U128::from(2 * rewards));
+
+        // A checkpoint moved during a pause doesn't count it again
+        testing_env!(context.block_height(50).build());
+        contract.set_rewards_paused(true);
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .block_height(55)
+            .build());
+        contract.claim_rewards();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_height(60)
+            .build());
+        contract.set_rewards_paused(false);
+        testing_env!(context.block_height(70).build());
+        assert_eq!(contract.get_pending_rewards(accounts(2)), U128::from(rewards));
+    }
+
+    #[test]
+    fn test_boost_window_multiplies_rewards_inside_it() {
+        let mut context = get_context(accounts(1), accounts(1));
+        let mut contract = setup_rewards(&mut context);
+        let rewards = NearToken::from_millinear(100).as_yoctonear();
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.set_boost_window(Some((U64::from(10), U64::from(20), 20_000)));
+
+        testing_env!(context.block_height(10).build());
+        assert_eq!(contract.get_pending_rewards(accounts(2)), U128::from(rewards));
+
+        // Blocks 10-20 earn twice the base rate
+        testing_env!(context.block_height(20).build());
+        assert_eq!(contract.get_pending_rewards(accounts(2)), U128::from(3 * rewards));
+
+        testing_env!(context.block_height(30).build());
+        assert_eq!(contract.get_pending_rewards(accounts(2)), U128::from(4 * rewards));
+    }
+
+    #[test]
+    fn test_rate_change_applies_from_its_block_for_every_staker() {
+        let mut context = get_context(accounts(1), accounts(1));
+        let mut contract = setup_rewards(&mut context);
+        let rewards = NearToken::from_millinear(100).as_yoctonear();
+
+        // accounts(3) joins with the same 10 NEAR at block 10
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(10))
+            .predecessor_account_id(accounts(3))
+            .signer_account_id(accounts(3))
+            .block_height(10)
+            .build());
+        contract.stake();
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .predecessor_account_id(accounts(1))
+            .signer_account_id(accounts(1))
+            .block_height(20)
+            .build());
+        contract.set_reward_rate(U128::from(2_000_000_000));
+
+        // Blocks 0-20 at the old rate and 20-30 at twice that, for
+        // accounts(3) only from block 10 on
+        testing_env!(context.block_height(30).build());
+        assert_eq!(contract.get_pending_rewards(accounts(2)), U128::from(4 * rewards));
+        assert_eq!(contract.get_pending_rewards(accounts(3)), U128::from(3 * rewards));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .signer_account_id(accounts(3))
+            .build());
+        assert_eq!(contract.claim_rewards(), U128::from(3 * rewards));
+        assert_eq!(contract.get_pending_rewards(accounts(3)), U128::from(0));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .signer_account_id(accounts(2))
+            .block_height(40)
+            .build());
+        assert_eq!(contract.claim_rewards(), U128::from(6 * rewards));
+    }
+
+    #[test]
+    fn test_claim_rewards_native() {
+        let mut context = get_context(accounts(1), accounts(1));
+        let mut contract = setup_rewards(&mut context);
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .block_height(10)
+            .build());
+        let rewards = NearToken::from_millinear(100).as_yoctonear();
+        assert_eq!(contract.claim_rewards().0, rewards);
+        assert_eq!(transferred_to(&accounts(2)), rewards);
+        assert_eq!(contract.get_user_staked(), NearToken::from_near(10).as_yoctonear());
+        assert_eq!(contract.get_pending_rewards(accounts(2)).0, 0);
+    }
+
+    #[test]
+    fn test_claim_rewards_capped_at_pool() {
+        let mut context = get_context(accounts(1), accounts(1));
+        testing_env!(context.build());
+        let mut contract = StakingContract::new();
+        contract.set_reward_rate(U128::from(1_000_000_000));
+        testing_env!(context.attached_deposit(NearToken::from_millinear(40)).build());
+        contract.fund_rewards();
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(10))
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.stake();
+
+        // 100 milliNEAR accrued, only 40 in the pool
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .block_height(10)
+            .build());
+        let paid = NearToken::from_millinear(40).as_yoctonear();
+        let remaining = NearToken::from_millinear(60).as_yoctonear();
+        assert_eq!(contract.claim_rewards().0, paid);
+        assert_eq!(transferred_to(&accounts(2)), paid);
+        assert_eq!(contract.get_reward_pool().0, 0);
+        assert_eq!(contract.get_pending_rewards(accounts(2)).0, remaining);
+        assert!(get_logs().contains(&format!(
+            "EVENT_JSON:{}",
+            json!({
+                "standard": events::EVENT_STANDARD,
+                "version": events::EVENT_VERSION,
+                "event": "partial_claim",
+                "data": [{
+                    "account_id": accounts(2),
+                    "paid": U128::from(paid),
+                    "remaining": U128::from(remaining),
+                }],
+            })
+        )));
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.fund_rewards();
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .predecessor_account_id(accounts(2))
+            .build());
+        assert_eq!(contract.claim_rewards().0, remaining);
+        assert_eq!(transferred_to(&accounts(2)), remaining);
+        assert_eq!(contract.get_pending_rewards(accounts(2)).0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough gas, attach at least")]
+    fn test_claim_token_rewards_with_too_little_gas() {
+        let mut context = get_context(accounts(1), accounts(1));
+        let mut contract = setup_token_rewards(&mut context);
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .block_height(10)
+            .prepaid_gas(Gas::from_tgas(10))
+            .build());
+        contract.claim_rewards();
+    }
+
+    // Same as setup_rewards, but rewards are paid in token accounts(4) and
+    // the pool is funded by transferring 1 NEAR worth of it
+    fn setup_token_rewards(context: &mut VMContextBuilder) -> StakingContract {
+        testing_env!(context.build());
+        let mut contract = StakingContract::new();
+        contract.set_reward_rate(U128::from(1_000_000_000));
+        contract.set_reward_token(Some(accounts(4)));
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        let _ = contract.ft_on_transfer(
+            accounts(1),
+            U128::from(NearToken::from_near(1).as_yoctonear()),
+            String::new(),
+        );
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(10))
+            .predecessor_account_id(accounts(2))
+            .signer_account_id(accounts(2))
+            .build());
+        contract.stake();
+        contract
+    }
+
+    #[test]
+    fn test_claim_rewards_in_reward_token() {
+        let mut context = get_context(accounts(1), accounts(1));
+        let mut contract = setup_token_rewards(&mut context);
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .block_height(10)
+            .build());
+        let rewards = NearToken::from_millinear(100).as_yoctonear();
+        contract.claim_rewards();
+
+        assert_eq!(transferred_to(&accounts(2)), 0);
+        let receipts = get_created_receipts();
+        let ft_transfer = receipts
+            .iter()
+            .find(|receipt| receipt.receiver_id == accounts(4))
+            .unwrap();
+        match &ft_transfer.actions[0] {
+            MockAction::FunctionCallWeight {
+                method_name,
+                args,
+                attached_deposit,
+                ..
+            } => {
+                assert_eq!(method_name, b"ft_transfer");
+                assert_eq!(*attached_deposit, NearToken::from_yoctonear(1));
+                assert_eq!(
+                    near_sdk::serde_json::from_slice::<near_sdk::serde_json::Value>(args).unwrap(),
+                    json!({
+                        "receiver_id": accounts(2),
+                        "amount": U128::from(rewards),
+                        "memo": null,
+                    })
+                );
+            }
+            action => panic!("Unexpected action {:?}", action),
+        }
+        assert_eq!(
+            contract.get_reward_pool().0,
+            NearToken::from_near(1).as_yoctonear() - rewards
+        );
+    }
+
+    #[test]
+    fn test_failed_token_reward_stays_claimable() {
+        let mut context = get_context(accounts(1), accounts(1));
+        let mut contract = setup_token_rewards(&mut context);
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .block_height(10)
+            .build());
+        let rewards = contract.claim_rewards();
+
+        testing_env!(
+            context.predecessor_account_id(accounts(0)).build(),
+            test_vm_config(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed],
+        );
+        contract.on_reward_paid(accounts(2), rewards);
+        assert_eq!(contract.get_claimable(accounts(2)), rewards);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the reward token can fund rewards")]
+    fn test_ft_on_transfer_rejects_other_tokens() {
+        let mut context = get_context(accounts(1), accounts(1));
+        let mut contract = setup_token_rewards(&mut context);
+
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        let _ = contract.ft_on_transfer(accounts(1), U128::from(1), String::new());
+    }
+
+    #[test]
+    fn test_partial_unstake_settles_rewards_and_keeps_accruing() {
+        let mut context = get_context(accounts(1), accounts(1));
+        let mut contract = setup_rewards(&mut context);
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .block_height(10)
+            .build());
+        contract.unstake(U128::from(NearToken::from_near(4).as_yoctonear()), None);
+        assert_eq!(
+            transferred_to(&accounts(2)),
+            NearToken::from_near(4).as_yoctonear() + NearToken::from_millinear(100).as_yoctonear()
+        );
+        assert_eq!(contract.get_pending_rewards(accounts(2)).0, 0);
+        resolve_withdrawal(
+            &mut context,
+            &mut contract,
+            accounts(2),
+            PromiseResult::Successful(vec![]),
+        );
+
+        // The remaining 6 NEAR keeps earning from block 10 on
+        testing_env!(context.block_height(20).build());
+        let rewards = NearToken::from_millinear(60).as_yoctonear();
+        assert_eq!(contract.get_pending_rewards(accounts(2)).0, rewards);
+
+        contract.unstake(U128::from(NearToken::from_near(6).as_yoctonear()), None);
+        assert_eq!(
+            transferred_to(&accounts(2)),
+            NearToken::from_near(6).as_yoctonear() + rewards
+        );
+        assert_eq!(
+            contract.get_reward_pool().0,
+            NearToken::from_millinear(840).as_yoctonear()
+        );
+    }
+
+    #[test]
+    fn test_airdrop_range_pages_cover_all_stakers_once() {
+        let mut context = get_context(accounts(1), accounts(1));
+        testing_env!(context.build());
+        let mut contract = StakingContract::new();
+
+        for staker in [accounts(2), accounts(3), accounts(4)] {
+            testing_env!(context
+                .attached_deposit(NearToken::from_near(1))
+                .predecessor_account_id(staker.clone())
+                .signer_account_id(staker)
+                .build());
+            contract.stake();
+        }
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .predecessor_account_id(accounts(1))
+            .signer_account_id(accounts(1))
+            .build());
+        let mut received = vec![0u128; 3];
+
+        let next = contract.airdrop_range(0, 2, 7);
+        assert_eq!(next, 2);
+        for (i, staker) in [accounts(2), accounts(3), accounts(4)].iter().enumerate() {
+            received[i] += transferred_to(staker);
+        }
+
+        testing_env!(context.build());
+        let next = contract.airdrop_range(next, 2, 7);
+        assert_eq!(next, 3);
+        for (i, staker) in [accounts(2), accounts(3), accounts(4)].iter().enumerate() {
+            received[i] += transferred_to(staker);
+        }
+
+        assert_eq!(received, vec![7, 7, 7]);
+    }
+
+    #[test]
+    fn test_return_stakes_range_pages_through_stakers() {
+        let mut context = get_context(accounts(1), accounts(1));
+        testing_env!(context.build());
+        let mut contract = StakingContract::new();
+
+        let stakers = [accounts(2), accounts(3), accounts(4)];
+        for (staker, amount) in stakers.iter().zip([1, 2, 3]) {
+            testing_env!(context
+                .attached_deposit(NearToken::from_near(amount))
+                .predecessor_account_id(staker.clone())
+                .build());
+            contract.stake();
+        }
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .predecessor_account_id(accounts(1))
+            .build());
+        let mut returned = vec![0u128; 3];
+
+        let next = contract.return_stakes_range(0, 2);
+        assert_eq!(next, 2);
+        for (i, staker) in stakers.iter().enumerate() {
+            returned[i] += transferred_to(staker);
+        }
+        assert_eq!(
+            get_logs().iter().filter(|log| log.contains("\"stake_returned\"")).count(),
+            2
+        );
+
+        testing_env!(context.build());
+        assert_eq!(contract.return_stakes_range(next, 2), 3);
+        for (i, staker) in stakers.iter().enumerate() {
+            returned[i] += transferred_to(staker);
+        }
+
+        assert_eq!(
+            returned,
+            [1, 2, 3].map(|amount| NearToken::from_near(amount).as_yoctonear())
+        );
+        for staker in &stakers {
+            assert_eq!(contract.get_account_state(staker.clone()).active.0, 0);
+            resolve_withdrawal(&mut context, &mut contract, staker.clone(), PromiseResult::Successful(vec![]));
+        }
+        assert_eq!(contract.get_total_staked(), 0);
+
+        // Nothing is sent twice
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.return_stakes_range(0, 3);
+        assert!(get_created_receipts().is_empty());
+    }
+
+    #[test]
+    fn test_airdrop_time_weighted_favors_older_stakers() {
+        let mut context = get_context(accounts(1), accounts(1));
+        testing_env!(context.build());
+        let mut contract = StakingContract::new();
+
+        for (staker, block) in [(accounts(2), 0), (accounts(3), 50)] {
+            testing_env!(context
+                .block_height(block)
+                .attached_deposit(NearToken::from_near(1))
+                .predecessor_account_id(staker.clone())
+                .signer_account_id(staker)
+                .build());
+            contract.stake();
+        }
+        assert_eq!(contract.get_staked_since(accounts(3)), Some(U64::from(50)));
+
+        // Equal stakes, held for 100 and 50 blocks
+        testing_env!(context
+            .block_height(100)
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .predecessor_account_id(accounts(1))
+            .signer_account_id(accounts(1))
+            .build());
+        contract.airdrop_time_weighted(NearToken::from_near(3).as_yoctonear());
+
+        assert_eq!(contract.get_claimable(accounts(2)).0, NearToken::from_near(2).as_yoctonear());
+        assert_eq!(contract.get_claimable(accounts(3)).0, NearToken::from_near(1).as_yoctonear());
+    }
+
+    #[test]
+    fn test_airdrop_time_weighted_all_zero_weights() {
+        let mut context = get_context(accounts(1), accounts(1));
+        testing_env!(context.build());
+        let mut contract = StakingContract::new();
+
+        for (staker, near) in [(accounts(2), 3), (accounts(3), 1)] {
+            testing_env!(context
+                .block_height(10)
+                .attached_deposit(NearToken::from_near(near))
+                .predecessor_account_id(staker.clone())
+                .signer_account_id(staker)
+                .build());
+            contract.stake();
+        }
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .predecessor_account_id(accounts(1))
+            .signer_account_id(accounts(1))
+            .build());
+        contract.airdrop_time_weighted(NearToken::from_near(2).as_yoctonear());
+
+        assert_eq!(
+            contract.get_claimable(accounts(2)).0,
+            NearToken::from_millinear(1500).as_yoctonear()
+        );
+        assert_eq!(
+            contract.get_claimable(accounts(3)).0,
+            NearToken::from_millinear(500).as_yoctonear()
+        );
+    }
+
+    #[test]
+    fn test_airdrop_pull_records_proportional_entitlements() {
+        let mut context = get_context(accounts(1), accounts(1));
+        testing_env!(context.build());
+        let mut contract = StakingContract::new();
+
+        for (staker, near) in [(accounts(2), 3), (accounts(3), 1)] {
+            testing_env!(context
+                .attached_deposit(NearToken::from_near(near))
+                .predecessor_account_id(staker.clone())
+                .signer_account_id(staker)
+                .build());
+            contract.stake();
+        }
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .predecessor_account_id(accounts(1))
+            .signer_account_id(accounts(1))
+            .build());
+        contract.airdrop_pull(NearToken::from_near(2).as_yoctonear());
+
+        assert_eq!(
+            contract.get_claimable(accounts(2)).0,
+            NearToken::from_millinear(1500).as_yoctonear()
+        );
+        assert_eq!(
+            contract.get_claimable(accounts(3)).0,
+            NearToken::from_millinear(500).as_yoctonear()
+        );
+        assert_eq!(contract.get_claimable(accounts(4)).0, 0);
+        assert!(get_created_receipts().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Nothing to claim")]
+    fn test_claim_airdrop_pays_share_once() {
+        let mut context = get_context(accounts(1), accounts(1));
+        testing_env!(context.build());
+        let mut contract = StakingContract::new();
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(1))
+            .predecessor_account_id(accounts(2))
+            .signer_account_id(accounts(2))
+            .build());
+        contract.stake();
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .predecessor_account_id(accounts(1))
+            .signer_account_id(accounts(1))
+            .build());
+        contract.airdrop_pull(1_000);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .signer_account_id(accounts(2))
+            .build());
+        assert_eq!(contract.claim_airdrop().0, 1_000);
+        assert_eq!(transferred_to(&accounts(2)), 1_000);
+        assert_eq!(contract.get_claimable(accounts(2)).0, 0);
+
+        contract.claim_airdrop();
+    }
+
+    #[test]
+    fn test_failed_withdrawal_can_be_retried() {
+        let mut context = get_context(accounts(1), accounts(1));
+        testing_env!(context.build());
+        let mut contract = StakingContract::new();
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(10))
+            .predecessor_account_id(accounts(2))
+            .signer_account_id(accounts(2))
+            .build());
+        contract.stake();
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .build());
+        let amount = NearToken::from_near(4).as_yoctonear();
+        contract.unstake(U128::from(amount), None);
+        assert_eq!(contract.get_pending_withdrawal(accounts(2)).0, amount);
+        // Nothing is deducted from total_staked before the transfer settles
+        assert_eq!(contract.get_total_staked(), NearToken::from_near(10).as_yoctonear());
+
+        resolve_withdrawal(&mut context, &mut contract, accounts(2), PromiseResult::Failed);
+        assert_eq!(contract.get_pending_withdrawal(accounts(2)).0, amount);
+        assert_eq!(contract.get_total_staked(), NearToken::from_near(10).as_yoctonear());
+
+        assert_eq!(contract.withdraw().0, amount);
+        assert_eq!(transferred_to(&accounts(2)), amount);
+
+        resolve_withdrawal(
+            &mut context,
+            &mut contract,
+            accounts(2),
+            PromiseResult::Successful(vec![]),
+        );
+        assert_eq!(contract.get_pending_withdrawal(accounts(2)).0, 0);
+        assert_eq!(contract.get_total_staked(), NearToken::from_near(6).as_yoctonear());
+    }
+
+    #[test]
+    #[should_panic(expected = "Withdrawal already in flight")]
+    fn test_withdraw_rejects_while_in_flight() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = StakingContract::new();
+
+        testing_env!(context.attached_deposit(NearToken::from_near(10)).build());
+        contract.stake();
+
+        testing_env!(context.attached_deposit(NearToken::from_yoctonear(0)).build());
+        contract.unstake(U128::from(NearToken::from_near(4).as_yoctonear()), None);
+        contract.withdraw();
+    }
+
+    #[test]
+    fn test_account_state_during_cooldown() {
+        let mut context = get_context(accounts(1), accounts(1));
+        testing_env!(context.block_timestamp(1_000).build());
+        let mut contract = StakingContract::new();
+        contract.set_unstake_cooldown(U64::from(500));
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(10))
+            .predecessor_account_id(accounts(2))
+            .signer_account_id(accounts(2))
+            .build());
+        contract.stake();
+        assert_eq!(
+            contract.get_account_state(accounts(2)),
+            StakeAccount {
+                active: U128::from(NearToken::from_near(10).as_yoctonear()),
+                pending: U128::from(0),
+                claimable_at: None,
+            }
+        );
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .build());
+        contract.unstake(U128::from(NearToken::from_near(4).as_yoctonear()), None);
+        assert!(get_created_receipts().is_empty());
+        assert_eq!(
+            contract.get_account_state(accounts(2)),
+            StakeAccount {
+                active: U128::from(NearToken::from_near(6).as_yoctonear()),
+                pending: U128::from(NearToken::from_near(4).as_yoctonear()),
+                claimable_at: Some(U64::from(1_500)),
+            }
+        );
+        // Unchanged, only reports the active stake
+        assert_eq!(contract.get_user_staked(), NearToken::from_near(6).as_yoctonear());
+
+        testing_env!(context.block_timestamp(1_500).build());
+        contract.withdraw();
+        assert_eq!(transferred_to(&accounts(2)), NearToken::from_near(4).as_yoctonear());
+    }
+
+    #[test]
+    #[should_panic(expected = "Withdrawal is still in cooldown")]
+    fn test_withdraw_rejects_during_cooldown() {
+        let mut context = get_context(accounts(1), accounts(1));
+        testing_env!(context.block_timestamp(1_000).build());
+        let mut contract = StakingContract::new();
+        contract.set_unstake_cooldown(U64::from(500));
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(10))
+            .predecessor_account_id(accounts(2))
+            .signer_account_id(accounts(2))
+            .build());
+        contract.stake();
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .block_timestamp(1_499)
+            .build());
+        contract.unstake(U128::from(NearToken::from_near(4).as_yoctonear()), None);
+        contract.withdraw();
+    }
+
+    // Stake 10 NEAR as accounts(2) with the given fee set by owner accounts(1)
+    // and unstake 4 of it
+    fn unstake_with_fee(fee_bps: u16) -> StakingContract {
+        let mut context = get_context(accounts(1), accounts(1));
+        testing_env!(context.build());
+        let mut contract = StakingContract::new();
+        contract.set_unstake_fee_bps(fee_bps);
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(10))
+            .predecessor_account_id(accounts(2))
+            .signer_account_id(accounts(2))
+            .build());
+        contract.stake();
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .build());
+        contract.unstake(U128::from(NearToken::from_near(4).as_yoctonear()), None);
+        contract
+    }
+
+    #[test]
+    fn test_reconcile_total_staked() {
+        let mut context = get_context(accounts(1), accounts(1));
+        testing_env!(context.build());
+        let mut contract = StakingContract::new();
+        for staker in [accounts(2), accounts(3)] {
+            testing_env!(context
+                .attached_deposit(NearToken::from_near(10))
+                .predecessor_account_id(staker)
+                .build());
+            contract.stake();
+        }
+
+        // Unstaking more than the balance refunds the balance but takes the
+        // requested amount off total_staked once confirmed
+        testing_env!(context.attached_deposit(NearToken::from_yoctonear(0)).build());
+        contract.unstake(U128::from(NearToken::from_near(15).as_yoctonear()), None);
+        resolve_withdrawal(&mut context, &mut contract, accounts(3), PromiseResult::Successful(vec![]));
+        assert_eq!(contract.get_total_staked(), NearToken::from_near(5).as_yoctonear());
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let total = NearToken::from_near(10).as_yoctonear();
+        assert_eq!(contract.reconcile_total_staked().0, total);
+        assert_eq!(contract.get_total_staked(), total);
+        assert_eq!(
+            get_logs(),
+            vec![format!(
+                "EVENT_JSON:{}",
+                json!({
+                    "standard": events::EVENT_STANDARD,
+                    "version": events::EVENT_VERSION,
+                    "event": "total_staked_reconciled",
+                    "data": [{
+                        "old": U128::from(NearToken::from_near(5).as_yoctonear()),
+                        "new": U128::from(total),
+                    }],
+                })
+            )]
+        );
+    }
+
+    #[test]
+    fn test_reconcile_total_staked_counts_unconfirmed_unstakes() {
+        let mut context = get_context(accounts(1), accounts(1));
+        testing_env!(context.build());
+        let mut contract = StakingContract::new();
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(10))
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.stake();
+        testing_env!(context.attached_deposit(NearToken::from_yoctonear(0)).build());
+        contract.unstake(U128::from(NearToken::from_near(4).as_yoctonear()), None);
+
+        // The 4 NEAR transfer is still unconfirmed
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        assert_eq!(
+            contract.reconcile_total_staked().0,
+            NearToken::from_near(10).as_yoctonear()
+        );
+    }
+
+    #[test]
+    fn test_reconcile_total_staked_after_unstake_all_in_cooldown() {
+        let mut context = get_context(accounts(1), accounts(1));
+        testing_env!(context.build());
+        let mut contract = StakingContract::new();
+        contract.set_unstake_cooldown(U64::from(500));
+        for staker in [accounts(2), accounts(3)] {
+            testing_env!(context
+                .attached_deposit(NearToken::from_near(10))
+                .predecessor_account_id(staker)
+                .build());
+            contract.stake();
+        }
+
+        // accounts(3) leaves stake_balances while its refund is still pending
+        testing_env!(context.attached_deposit(NearToken::from_yoctonear(0)).build());
+        contract.unstake_all();
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let total = NearToken::from_near(20).as_yoctonear();
+        assert_eq!(contract.reconcile_total_staked().0, total);
+
+        testing_env!(context.predecessor_account_id(accounts(3)).block_timestamp(500).build());
+        contract.withdraw();
+        resolve_withdrawal(&mut context, &mut contract, accounts(3), PromiseResult::Successful(vec![]));
+        assert_eq!(contract.get_total_staked(), NearToken::from_near(10).as_yoctonear());
+    }
+
+    #[test]
+    fn test_unstake_fee_goes_to_reward_pool() {
+        let contract = unstake_with_fee(100);
+        let fee = NearToken::from_millinear(40).as_yoctonear();
+        let net = NearToken::from_near(4).as_yoctonear() - fee;
+
+        assert_eq!(transferred_to(&accounts(2)), net);
+        assert_eq!(contract.get_reward_pool().0, fee);
+        let event = get_logs()
+            .into_iter()
+            .find(|log| log.contains("\"event\":\"unstake\""))
+            .unwrap();
+        assert!(event.contains(&format!("\"net\":\"{}\"", net)));
+        assert!(event.contains(&format!("\"gross\":\"{}\"", NearToken::from_near(4).as_yoctonear())));
+    }
+
+    #[test]
+    fn test_zero_unstake_fee_refunds_everything() {
+        let contract = unstake_with_fee(0);
+        assert_eq!(transferred_to(&accounts(2)), NearToken::from_near(4).as_yoctonear());
+        assert_eq!(contract.get_reward_pool().0, 0);
+    }
+
+    // accounts(2) stakes 10 NEAR into a contract holding 13 NEAR in total,
+    // owner accounts(1) sets a 1000ns drain timelock
+    fn setup_drain(context: &mut VMContextBuilder) -> StakingContract {
+        testing_env!(context.build());
+        let mut contract = StakingContract::new();
+        contract.set_drain_timelock(U64::from(1_000));
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(10))
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.stake();
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .account_balance(NearToken::from_near(13))
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(100)
+            .build());
+        contract
+    }
+
+    #[test]
+    fn test_drain_after_timelock() {
+        let mut context = get_context(accounts(1), accounts(1));
+        let mut contract = setup_drain(&mut context);
+        let residual = NearToken::from_near(3).as_yoctonear();
+        assert_eq!(contract.get_residual_balance().0, residual);
+
+        contract.propose_drain(accounts(3), U128::from(residual));
+        testing_env!(context.block_timestamp(1_100).build());
+        assert_eq!(contract.execute_drain().0, residual);
+        assert_eq!(transferred_to(&accounts(3)), residual);
+    }
+
+    #[test]
+    #[should_panic(expected = "Drain is still timelocked")]
+    fn test_drain_rejected_before_timelock() {
+        let mut context = get_context(accounts(1), accounts(1));
+        let mut contract = setup_drain(&mut context);
+
+        contract.propose_drain(accounts(3), U128::from(1));
+        testing_env!(context.block_timestamp(1_099).build());
+        contract.execute_drain();
+    }
+
+    #[test]
+    #[should_panic(expected = "Drain exceeds residual balance")]
+    fn test_drain_cannot_touch_staked_funds() {
+        let mut context = get_context(accounts(1), accounts(1));
+        let mut contract = setup_drain(&mut context);
+
+        contract.propose_drain(accounts(3), U128::from(NearToken::from_near(4).as_yoctonear()));
+        testing_env!(context.block_timestamp(1_100).build());
+        contract.execute_drain();
+    }
+
+    #[test]
+    #[should_panic(expected = "No drain proposed")]
+    fn test_cancelled_drain_cannot_execute() {
+        let mut context = get_context(accounts(1), accounts(1));
+        let mut contract = setup_drain(&mut context);
+
+        contract.propose_drain(accounts(3), U128::from(1));
+        contract.cancel_drain();
+        testing_env!(context.block_timestamp(1_100).build());
+        contract.execute_drain();
+    }
+
+    #[test]
+    fn test_solvency_follows_account_balance() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.attached_deposit(NearToken::from_near(10)).build());
+        let mut contract = StakingContract::new();
+        contract.stake();
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .account_balance(NearToken::from_near(12))
+            .is_view(true)
+            .build());
+        assert!(contract.is_solvent());
+        assert_eq!(
+            contract.solvency_margin(),
+            NearToken::from_near(2).as_yoctonear() as i128
+        );
+
+        // Simulate 3 NEAR leaving the account behind the contract's back
+        testing_env!(context.account_balance(NearToken::from_near(7)).build());
+        assert!(!contract.is_solvent());
+        assert_eq!(
+            contract.solvency_margin(),
+            -(NearToken::from_near(3).as_yoctonear() as i128)
+        );
+    }
+
+    #[test]
+    fn test_balance_breakdown() {
+        let mut context = get_context(accounts(1), accounts(1));
+        let contract = setup_rewards(&mut context);
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .account_balance(NearToken::from_near(14))
+            .is_view(true)
+            .build());
+        assert_eq!(
+            contract.get_balance_breakdown(),
+            BalanceBreakdown {
+                account_balance: U128::from(NearToken::from_near(14).as_yoctonear()),
+                total_staked: U128::from(NearToken::from_near(10).as_yoctonear()),
+                reward_pool: U128::from(NearToken::from_near(1).as_yoctonear()),
+                queued_rewards: U128::from(0),
+                claimable: U128::from(0),
+                free: U128::from(NearToken::from_near(3).as_yoctonear()),
+                underfunded: false,
+            }
+        );
+
+        testing_env!(context.account_balance(NearToken::from_near(10)).build());
+        let breakdown = contract.get_balance_breakdown();
+        assert_eq!(breakdown.free.0, 0);
+        assert!(breakdown.underfunded);
+    }
+
+    #[test]
+    fn test_residual_balance_excludes_rewards_owed() {
+        let mut context = get_context(accounts(1), accounts(1));
+        let mut contract = setup_rewards(&mut context);
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.set_unstake_cooldown(U64::from(500));
+        contract.airdrop_pull(NearToken::from_near(1).as_yoctonear());
+
+        // 0.1 NEAR of rewards leaves the pool with the refund, which waits
+        // out the cooldown in pending_withdrawals
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .block_height(10)
+            .build());
+        contract.unstake(U128::from(NearToken::from_near(10).as_yoctonear()), None);
+
+        testing_env!(context.account_balance(NearToken::from_near(14)).is_view(true).build());
+        let breakdown = contract.get_balance_breakdown();
+        assert_eq!(breakdown.reward_pool.0, NearToken::from_millinear(900).as_yoctonear());
+        assert_eq!(breakdown.queued_rewards.0, NearToken::from_millinear(100).as_yoctonear());
+        assert_eq!(breakdown.claimable.0, NearToken::from_near(1).as_yoctonear());
+        assert_eq!(contract.get_residual_balance().0, NearToken::from_near(2).as_yoctonear());
+
+        testing_env!(context.block_timestamp(500).is_view(false).build());
+        contract.withdraw();
+        resolve_withdrawal(&mut context, &mut contract, accounts(2), PromiseResult::Successful(vec![]));
+        contract.claim_airdrop();
+        let breakdown = contract.get_balance_breakdown();
+        assert_eq!(breakdown.queued_rewards.0, 0);
+        assert_eq!(breakdown.claimable.0, 0);
+    }
+
+    // ========== VULNERABILITY TEST CASES ==========
+
+    #[test]
+    fn test_unstake_bug_when_balance_reaches_zero() {
+        // Bug: When unstaking brings balance to 0, refunds old balance instead of amount
+        // However, if balance == amount (unstaking all), this is correct
+        // The bug is more subtle - it's about consistency and edge cases
+        let mut context = get_context(accounts(1), accounts(1));
+        testing_env!(context.build());
+
+        let mut contract = StakingContract::new();
+
+        // Stake 10 NEAR
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(10))
+            .build());
+        contract.stake();
+
+        // Unstake exactly 10 NEAR (bringing balance to 0)
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .build());
+        
+        // This should refund 10 NEAR (the old balance)
+        // In this case, it's correct because we're unstaking all
+        let result = contract.unstake(U128::from(NearToken::from_near(10).as_yoctonear()), None);
+        assert!(result);
+        
+        // User balance should be 0
+        assert_eq!(contract.get_user_staked(), 0);
+    }
+
+    #[test]
+    fn test_unstake_bug_logic_inconsistency() {
+        // Demonstrates the logic inconsistency in unstake()
+        // When new_balance == 0, it refunds 'balance' (old balance)
+        // When new_balance > 0, it refunds 'amount' (unstaked amount)
+        // This is inconsistent - should always refund 'amount'
+        let mut context = get_context(accounts(1), accounts(1));
+        testing_env!(context.build());
+
+        let mut contract = StakingContract::new();
+
+        // Stake 100 NEAR
+        testing_env!(context
+            .attached_deposit(NearToken::from_near(100))
+            .build());
+        contract.stake();
+
+        // Unstake 50 NEAR (balance will be 50, not 0)
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .build());
+        contract.unstake(U128::from(NearToken::from_near(50).as_yoctonear()), None);
+        
+        // Now unstake the remaining 50 NEAR (balance will be 0)
+        // This will refund 50 NEAR (the old balance), which is correct
+        // But the logic is inconsistent - it should always refund 'amount'
+        contract.unstake(U128::from(NearToken::from_near(50).as_yoctonear()), None);
+        
+        assert_eq!(contract.get_user_staked(), 0);
     }
 
     #[test]
@@ -231,10 +2651,18 @@ mod tests {
         
         // The function should check amount <= balance, but it doesn't
         // This will set balance to 0 and refund 10 NEAR
-        contract.unstake(U128::from(NearToken::from_near(100).as_yoctonear()));
+        contract.unstake(U128::from(NearToken::from_near(100).as_yoctonear()), None);
         
         // Balance is now 0
         assert_eq!(contract.get_user_staked(), 0);
+
+        // total_staked is only updated once the refund transfer is confirmed
+        resolve_withdrawal(
+            &mut context,
+            &mut contract,
+            accounts(1),
+            PromiseResult::Successful(vec![]),
+        );
         
         // BUG: total_staked was reduced by 100 (the requested amount), not 10 (the actual balance)
         // Line 58: self.total_staked = self.total_staked.saturating_sub(u128::from(amount));
@@ -250,7 +2678,7 @@ mod tests {
 
     #[test]
     fn test_airdrop_zero_amount_wastes_gas() {
-        // Bug: airdrop() has no validation on amount parameter
+        // Bug: airdrop_range() has no validation on amount parameter
         // Calling with amount = 0 wastes gas iterating through all stakers
         let mut context = get_context(accounts(1), accounts(1));
         testing_env!(context.build());
@@ -293,7 +2721,7 @@ mod tests {
         // BUG: No validation that amount > 0
         // This will iterate through all 3 stakers and send 0 NEAR to each
         // Gas is wasted on iteration and promise creation with no effect
-        contract.airdrop(0);
+        contract.airdrop_range(0, 10, 0);
         
         // No tokens were sent, but gas was consumed
         // This demonstrates the lack of input validation
@@ -301,7 +2729,7 @@ mod tests {
 
     #[test]
     fn test_airdrop_insufficient_balance() {
-        // Bug: airdrop() doesn't check if contract has sufficient balance
+        // Bug: airdrop_range() doesn't check if contract has sufficient balance
         // If contract balance is insufficient, promises will fail but gas is still consumed
         let mut context = get_context(accounts(1), accounts(1));
         testing_env!(context.build());
@@ -327,7 +2755,7 @@ mod tests {
         // BUG: No validation that contract balance >= (amount * staker_count)
         // This will create promises that will fail, but gas is still consumed
         // In a real scenario, this could cause DoS if called repeatedly
-        contract.airdrop(NearToken::from_near(10).as_yoctonear());
+        contract.airdrop_range(0, 10, NearToken::from_near(10).as_yoctonear());
         
         // Promises will fail, but function doesn't check balance beforehand
         // This demonstrates the lack of balance validation
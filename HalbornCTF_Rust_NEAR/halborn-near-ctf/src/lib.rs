@@ -1,4 +1,5 @@
 use near_contract_standards::fungible_token::core::FungibleTokenCore;
+use near_contract_standards::fungible_token::events::{FtMint, FtTransfer};
 use near_contract_standards::fungible_token::metadata::{
     FungibleTokenMetadata, FungibleTokenMetadataProvider, FT_METADATA_SPEC,
 };
@@ -8,20 +9,59 @@ use near_contract_standards::fungible_token::FungibleToken;
 use near_contract_standards::storage_management::{
     StorageBalance, StorageBalanceBounds, StorageManagement,
 };
+use halborn_near_ctf_common::gas;
+use halborn_near_ctf_common::math::mul_div;
 use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{LazyOption, LookupMap};
-use near_sdk::json_types::U128;
+use near_sdk::collections::{LazyOption, LookupMap, UnorderedMap, UnorderedSet};
+use near_sdk::json_types::{U128, U64};
 use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
 use near_sdk::{
-    env, ext_contract, log, near_bindgen, AccountId, Gas, NearToken, PanicOnDefault, PromiseOrValue,
+    env, ext_contract, is_promise_success, log, near_bindgen, AccountId, Gas, NearToken,
+    PanicOnDefault, Promise, PromiseError, PromiseOrValue,
 };
+use std::collections::{HashMap, HashSet};
 use std::convert::From;
 
+mod events;
+
 pub const GAS_FOR_REGISTER: Gas = Gas::from_gas(10_000_000_000_000);
+pub const MIN_REGISTER_GAS: Gas = Gas::from_gas(5_000_000_000_000);
+pub const MAX_REGISTER_GAS: Gas = Gas::from_gas(100_000_000_000_000);
+pub const GAS_FOR_REGISTER_CALLBACK: Gas = Gas::from_gas(5_000_000_000_000);
+// Left for register_for_event's own work after the attached calls
+pub const GAS_FOR_REGISTER_BUFFER: Gas = Gas::from_gas(20_000_000_000_000);
+pub const GAS_FOR_TRANSFER_HOOK: Gas = Gas::from_gas(5_000_000_000_000);
+pub const GAS_FOR_EVENT_COUNT: Gas = Gas::from_gas(5_000_000_000_000);
+pub const GAS_FOR_EVENT_COUNT_CALLBACK: Gas = Gas::from_gas(5_000_000_000_000);
+pub const GAS_FOR_GAS_QUERY: Gas = Gas::from_gas(5_000_000_000_000);
+pub const GAS_FOR_GAS_QUERY_CALLBACK: Gas = Gas::from_gas(5_000_000_000_000);
+pub const DEFAULT_DECIMALS: u8 = 10;
+pub const MAX_DECIMALS: u8 = 24;
+pub const MAX_NAME_LEN: usize = 64;
+pub const MAX_SYMBOL_LEN: usize = 12;
+pub const MAX_ICON_LEN: usize = 8 * 1024;
+pub const MAX_SUPPLY_HISTORY: usize = 256;
+pub const MAX_BALANCES_QUERY: usize = 100;
+pub const DEFAULT_MAX_APPROVALS: u64 = 4;
+pub const MAX_TRANSFER_BURN_BPS: u16 = 10_000;
+pub const MAX_REGISTRATION_BURN_SPLIT_BPS: u16 = 10_000;
+// Upper estimate of the storage taken by one approval: a spender account id
+// of up to 64 bytes, the allowance and the map entry overhead
+pub const APPROVAL_STORAGE_BYTES: u64 = 128;
 
 #[ext_contract]
 pub trait AssociatedContractInterface {
     fn register_for_an_event(&mut self, event_id: U128, account_id: AccountId);
+    fn get_next_event_idx(&self) -> u16;
+    fn get_recommended_register_gas(&self) -> Gas;
+}
+
+// Implemented by integrators that want to run their own logic on every
+// ft_transfer. The call is fire-and-forget, its outcome doesn't affect the transfer
+#[ext_contract]
+pub trait TransferHook {
+    fn on_ft_transfer(&mut self, sender_id: AccountId, receiver_id: AccountId, amount: U128);
 }
 
 #[derive(
@@ -44,6 +84,28 @@ pub enum ContractStatus {
     Paused,
 }
 
+// Permissions the owner can hand out on top of its own
+#[derive(
+    BorshDeserialize, BorshSerialize, Clone, Copy, Eq, PartialEq, Debug, Serialize, Deserialize,
+)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub enum Role {
+    Minter,
+}
+
+// How burned tokens leave circulation: either total_supply shrinks, or they
+// are moved to an unspendable account and supply stays the same
+#[derive(
+    BorshDeserialize, BorshSerialize, Clone, Eq, PartialEq, Debug, Serialize, Deserialize,
+)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub enum BurnStrategy {
+    ReduceSupply,
+    DeadAccount(AccountId),
+}
+
 impl std::fmt::Display for ContractStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -53,6 +115,86 @@ impl std::fmt::Display for ContractStatus {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AccountInfo {
+    pub balance: U128,
+    pub status: BlocklistStatus,
+    pub is_registered: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RegistrationStats {
+    pub total_registrations: U64,
+    pub total_registration_burn: U128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MetadataSummary {
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+    pub icon: Option<String>,
+    pub spec: String,
+}
+
+// Outcome of can_transfer: Ok, or the first check the transfer would fail
+#[derive(Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum TransferCheck {
+    Ok,
+    Paused,
+    ZeroAmount,
+    SelfTransfer,
+    SenderNotRegistered,
+    ReceiverNotRegistered,
+    SenderBanned,
+    InsufficientBalance,
+    BelowMinTransfer,
+    ExceedsMaxBalance,
+}
+
+// Owner actions that need approval_threshold approvers to agree once any
+// approvers are configured
+#[derive(
+    BorshDeserialize, BorshSerialize, Clone, Eq, PartialEq, Debug, Serialize, Deserialize,
+)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub enum SensitiveAction {
+    Mint { account_id: AccountId, amount: U128 },
+    SetOwner { new_owner: AccountId },
+    // An empty approver set returns to single-owner mode
+    SetApprovers { approvers: Vec<AccountId>, threshold: u8 },
+}
+
+#[derive(
+    BorshDeserialize, BorshSerialize, Clone, Eq, PartialEq, Debug, Serialize, Deserialize,
+)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct ActionProposal {
+    pub action: SensitiveAction,
+    pub approvals: Vec<AccountId>,
+}
+
+// Tokens set aside by the owner for a beneficiary, unlocking linearly over
+// `duration` nanoseconds from `start`, with nothing unlocked before the cliff
+#[derive(
+    BorshDeserialize, BorshSerialize, Clone, Eq, PartialEq, Debug, Serialize, Deserialize,
+)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct VestingSchedule {
+    pub total: U128,
+    pub start: U64,
+    pub cliff: U64,
+    pub duration: U64,
+    pub claimed: U128,
+}
+
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 #[borsh(crate = "near_sdk::borsh")]
@@ -64,21 +206,116 @@ pub struct MalbornClubContract {
     status: ContractStatus,
     associated_contract_account_id: LazyOption<AccountId>,
     registration_fee_denominator: U128,
+    in_flight: LookupMap<AccountId, bool>,
+    auto_resume_at: Option<u64>,
+    total_registrations: u64,
+    total_registration_burn: Balance,
+    transfer_hook_account: LazyOption<AccountId>,
+    // (block timestamp, total supply) after every mint and burn, oldest first
+    supply_history: Vec<(u64, Balance)>,
+    // Number of events in the associated contract as of the last refresh
+    associated_event_count: LazyOption<u64>,
+    burn_strategy: BurnStrategy,
+    vesting: LookupMap<AccountId, VestingSchedule>,
+    // spender -> allowance, per approver
+    approvals: LookupMap<AccountId, HashMap<AccountId, Balance>>,
+    max_approvals_per_account: u64,
+    // Storage deposit paid by each account on top of the token's own
+    // registration, missing for accounts registered before approvals
+    approval_storage_paid: LookupMap<AccountId, Balance>,
+    // Set once by renounce_minting, never cleared
+    minting_renounced: bool,
+    // Receives the balance left in accounts closed with a forced
+    // storage_unregister, instead of it being burned
+    sweep_account: Option<AccountId>,
+    // Static gas attached to register_for_an_event
+    register_gas: Gas,
+    mint_fee: Option<NearToken>,
+    // Lets the owner keep using ft_transfer while the contract is paused
+    owner_exempt_from_pause: bool,
+    // Event ids register_for_event accepts, any id while empty
+    allowed_events: UnorderedSet<U128>,
+    // Enumerable so holders of a role can be listed
+    roles: UnorderedMap<AccountId, Role>,
+    // Most any account but the owner and the treasury may hold after a
+    // transfer or mint
+    max_balance: Option<Balance>,
+    // Block timestamp of each account's last accepted registration
+    last_registration: LookupMap<AccountId, u64>,
+    // Minimum time between two registrations of the same account
+    registration_cooldown_ns: u64,
+    // Cap on total_supply for minting, burns free up room again
+    max_supply: Option<Balance>,
+    // Share of every transfer not sent or received by the owner or the
+    // treasury that is burned from the sender, in basis points
+    transfer_burn_bps: u16,
+    // Event ids each account registered for, once the associated contract
+    // accepted the registration
+    registered_events: LookupMap<AccountId, HashSet<u128>>,
+    // Receives the part of the registration fee that isn't burned
+    treasury_account: Option<AccountId>,
+    // Share of the registration fee that is burned, in basis points. The
+    // rest goes to treasury_account, or is burned too while it is None
+    registration_burn_split_bps: u16,
+    // Gas the associated contract advertises for register_for_an_event, as
+    // of the last query_associated_gas
+    recommended_register_gas: Option<Gas>,
+    // Delay before a new registration_fee_denominator takes effect, zero
+    // applies it immediately
+    fee_change_timelock_ns: u64,
+    // (denominator, block timestamp it takes effect at) set while a fee
+    // change waits for the timelock
+    pending_fee_denominator: Option<(U128, u64)>,
+    // Rejects mints to blocklisted accounts
+    enforce_blocklist_on_mint: bool,
+    // Time between add_to_blocklist and the ban taking effect
+    ban_grace_ns: u64,
+    // Block timestamp each ban takes effect at
+    ban_effective_at: LookupMap<AccountId, u64>,
+    // While not empty, mints and ownership changes go through proposals
+    // approved by approval_threshold of these accounts instead of the owner
+    approvers: UnorderedSet<AccountId>,
+    approval_threshold: u8,
+    proposals: LookupMap<u64, ActionProposal>,
+    next_proposal_id: u64,
+    // Incremented by every pause, pause_until and resume call, see
+    // current_status_epoch for the expiry of a pause_until deadline
+    status_epoch: u64,
+    // Smallest amount ft_transfer and ft_transfer_call accept, transfers
+    // from or to the owner or the treasury are exempt
+    min_transfer_amount: Option<Balance>,
 }
 
 #[near_bindgen]
 impl MalbornClubContract {
+    // decimals defaults to DEFAULT_DECIMALS when not provided, icon is a
+    // data URL (e.g. an SVG) of at most MAX_ICON_LEN bytes
     #[init]
-    pub fn new(owner_id: AccountId, token_total_supply: U128) -> Self {
+    pub fn new(
+        owner_id: AccountId,
+        token_total_supply: U128,
+        decimals: Option<u8>,
+        icon: Option<String>,
+    ) -> Self {
         assert!(!env::state_exists(), "Already initialized");
+        let decimals = decimals.unwrap_or(DEFAULT_DECIMALS);
+        assert!(decimals <= MAX_DECIMALS, "Decimals must be at most {}", MAX_DECIMALS);
+        if let Some(icon) = &icon {
+            assert!(icon.starts_with("data:"), "Icon must be a data URL");
+            assert!(
+                icon.len() <= MAX_ICON_LEN,
+                "Icon must be at most {} bytes",
+                MAX_ICON_LEN
+            );
+        }
         let metadata = FungibleTokenMetadata {
             spec: FT_METADATA_SPEC.to_string(),
             name: "Malborn Token".to_string(),
             symbol: "MAL".to_string(),
-            icon: None,
+            icon,
             reference: None,
             reference_hash: None,
-            decimals: 10,
+            decimals,
         };
 
         // default registration_fee_denominator results in user burning
@@ -91,6 +328,45 @@ impl MalbornClubContract {
             status: ContractStatus::Working,
             associated_contract_account_id: LazyOption::new(b"a".to_vec(), None),
             registration_fee_denominator: U128::from(10000),
+            in_flight: LookupMap::new(b"f".to_vec()),
+            auto_resume_at: None,
+            total_registrations: 0,
+            total_registration_burn: 0,
+            transfer_hook_account: LazyOption::new(b"h".to_vec(), None),
+            supply_history: Vec::new(),
+            associated_event_count: LazyOption::new(b"e".to_vec(), None),
+            burn_strategy: BurnStrategy::ReduceSupply,
+            vesting: LookupMap::new(b"v".to_vec()),
+            approvals: LookupMap::new(b"p".to_vec()),
+            max_approvals_per_account: DEFAULT_MAX_APPROVALS,
+            approval_storage_paid: LookupMap::new(b"s".to_vec()),
+            minting_renounced: false,
+            sweep_account: None,
+            register_gas: GAS_FOR_REGISTER,
+            mint_fee: None,
+            owner_exempt_from_pause: false,
+            allowed_events: UnorderedSet::new(b"w".to_vec()),
+            roles: UnorderedMap::new(b"r".to_vec()),
+            max_balance: None,
+            last_registration: LookupMap::new(b"l".to_vec()),
+            registration_cooldown_ns: 0,
+            max_supply: None,
+            transfer_burn_bps: 0,
+            registered_events: LookupMap::new(b"g".to_vec()),
+            treasury_account: None,
+            registration_burn_split_bps: MAX_REGISTRATION_BURN_SPLIT_BPS,
+            recommended_register_gas: None,
+            fee_change_timelock_ns: 0,
+            pending_fee_denominator: None,
+            enforce_blocklist_on_mint: true,
+            ban_grace_ns: 0,
+            ban_effective_at: LookupMap::new(b"k".to_vec()),
+            approvers: UnorderedSet::new(b"n".to_vec()),
+            approval_threshold: 0,
+            proposals: LookupMap::new(b"o".to_vec()),
+            next_proposal_id: 0,
+            status_epoch: 0,
+            min_transfer_amount: None,
         };
         this_state
             .malborn_token
@@ -98,30 +374,146 @@ impl MalbornClubContract {
         this_state
             .malborn_token
             .internal_deposit(&owner_id, token_total_supply.into());
+        assert_eq!(
+            this_state.malborn_token.accounts.get(&owner_id),
+            Some(token_total_supply.0),
+            "Owner must hold the initial supply"
+        );
+        FtMint {
+            owner_id: &owner_id,
+            amount: token_total_supply,
+            memo: Some("initial supply"),
+        }
+        .emit();
+        this_state.record_supply();
         this_state
     }
 
     // Mint tokens to someone. Returns the new total_supply
+    #[payable]
     pub fn mint_tokens(&mut self, account_id: &AccountId, amount: U128) -> Balance {
+        self.only_minter();
+        self.not_paused();
+        self.minting_not_renounced();
+        self.single_owner_mode();
+        self.collect_mint_fee(1);
+        self.mint_recipient_not_banned(account_id);
+        self.mint_tokens_internal(account_id, amount)
+    }
+
+    // Mint to the owner's own account. Returns the new total_supply. The
+    // owner is registered at init, but is registered here too in case
+    // set_owner moved ownership to an account without a storage deposit
+    pub fn mint_to_owner(&mut self, amount: U128) -> Balance {
         self.only_owner();
         self.not_paused();
+        self.minting_not_renounced();
+        self.single_owner_mode();
+        let owner_id = self.owner_id.clone();
+        if !self.malborn_token.accounts.contains_key(&owner_id) {
+            self.malborn_token.internal_register_account(&owner_id);
+        }
+        self.mint_tokens_internal(&owner_id, amount)
+    }
 
-        self.malborn_token.total_supply = self
-            .malborn_token
-            .total_supply
-            .checked_add(u128::from(amount))
-            .expect("Minting caused overflow");
+    // Mint to several accounts at once, paying the mint fee once per
+    // entry. Returns the new total_supply
+    #[payable]
+    pub fn mint_tokens_batch(&mut self, mints: Vec<(AccountId, U128)>) -> Balance {
+        self.only_minter();
+        self.not_paused();
+        self.minting_not_renounced();
+        self.single_owner_mode();
+        self.collect_mint_fee(mints.len() as u128);
+        for (account_id, _) in &mints {
+            self.mint_recipient_not_banned(account_id);
+        }
+        for (account_id, amount) in mints {
+            self.mint_tokens_internal(&account_id, amount);
+        }
+        self.malborn_token.total_supply
+    }
 
-        if let Some(user_amount) = self.malborn_token.accounts.get(account_id) {
-            self.malborn_token.accounts.insert(
-                account_id,
-                &user_amount
-                    .checked_add(u128::from(amount))
-                    .expect("Exceeded balance"),
+    // Mint up to what is left below max_supply and return the amount
+    // actually minted, instead of failing like mint_tokens would
+    #[payable]
+    pub fn mint_saturating(&mut self, account_id: &AccountId, amount: U128) -> U128 {
+        self.only_minter();
+        self.not_paused();
+        self.minting_not_renounced();
+        self.single_owner_mode();
+        self.collect_mint_fee(1);
+        self.mint_recipient_not_banned(account_id);
+        let minted = match self.max_supply {
+            Some(max_supply) => amount
+                .0
+                .min(max_supply.saturating_sub(self.malborn_token.total_supply)),
+            None => amount.0,
+        };
+        if minted < amount.0 {
+            events::emit(
+                "mint_clamped",
+                json!({
+                    "account_id": account_id,
+                    "requested": amount,
+                    "minted": U128::from(minted),
+                }),
+            );
+        }
+        if minted > 0 {
+            self.mint_tokens_internal(account_id, U128::from(minted));
+        }
+        U128::from(minted)
+    }
+
+    // Cap total_supply for minting, or lift the cap with None. Must not be
+    // below the current supply
+    pub fn set_max_supply(&mut self, max_supply: Option<U128>) {
+        self.only_owner();
+        if let Some(max_supply) = max_supply {
+            assert!(
+                max_supply.0 >= self.malborn_token.total_supply,
+                "Max supply must not be below the total supply"
             );
         }
+        self.max_supply = max_supply.map(u128::from);
+    }
 
-        self.malborn_token.total_supply
+    pub fn get_max_supply(&self) -> Option<U128> {
+        self.max_supply.map(U128::from)
+    }
+
+    // How much can still be minted before max_supply. Without a cap, what
+    // can be minted before total_supply overflows
+    pub fn mintable_remaining(&self) -> U128 {
+        let cap = self.max_supply.unwrap_or(Balance::MAX);
+        U128::from(cap.saturating_sub(self.malborn_token.total_supply))
+    }
+
+    // NEAR fee each mint must attach, forwarded to the owner. None means
+    // minting needs no deposit
+    pub fn set_mint_fee(&mut self, mint_fee: Option<NearToken>) {
+        self.only_owner();
+        self.mint_fee = mint_fee;
+    }
+
+    pub fn get_mint_fee(&self) -> Option<NearToken> {
+        self.mint_fee
+    }
+
+    // Permanently give up the ability to mint, fixing the supply
+    pub fn renounce_minting(&mut self) {
+        self.only_owner();
+        self.minting_not_renounced();
+        self.minting_renounced = true;
+        events::emit(
+            "minting_renounced",
+            json!({ "owner_id": self.owner_id }),
+        );
+    }
+
+    pub fn is_minting_renounced(&self) -> bool {
+        self.minting_renounced
     }
 
     // Burn someone's tokens
@@ -140,23 +532,220 @@ impl MalbornClubContract {
             self.associated_contract_account_id.is_some(),
             "Associated Account is not set"
         );
+        // Checked up front so a call that would run out of gas fails before
+        // anything is burned
+        gas::assert_min_gas(
+            self.register_gas
+                .saturating_add(GAS_FOR_REGISTER_CALLBACK)
+                .saturating_add(GAS_FOR_REGISTER_BUFFER),
+        );
+        assert!(
+            self.allowed_events.is_empty() || self.allowed_events.contains(&event_id),
+            "Event {} is not allowed",
+            event_id.0
+        );
         let sender_id = env::signer_account_id();
         self.not_banned(sender_id.clone());
+        assert!(
+            !self.in_flight.get(&sender_id).unwrap_or(false),
+            "Registration already in flight"
+        );
+        if let Some(last_registration) = self.last_registration.get(&sender_id) {
+            assert!(
+                env::block_timestamp()
+                    >= last_registration.saturating_add(self.registration_cooldown_ns),
+                "Registration cooldown active"
+            );
+        }
 
         // burn tokens for registering
-        let burn_amount = u128::from(self.malborn_token.total_supply)
-            / u128::from(self.registration_fee_denominator);
+        let fee = u128::from(self.malborn_token.total_supply)
+            / u128::from(self.current_fee_denominator());
+        let burn_amount = match self.treasury_account.clone() {
+            Some(treasury) => {
+                let burn_amount = fee * u128::from(self.registration_burn_split_bps)
+                    / u128::from(MAX_REGISTRATION_BURN_SPLIT_BPS);
+                let treasury_amount = fee - burn_amount;
+                if treasury_amount > 0 {
+                    self.malborn_token.internal_transfer(
+                        &sender_id,
+                        &treasury,
+                        treasury_amount,
+                        Some("registration fee".to_string()),
+                    );
+                }
+                burn_amount
+            }
+            None => fee,
+        };
         self.burn_tokens_internal(&sender_id, U128::from(burn_amount));
 
+        // Locked until on_register_for_event runs
+        self.in_flight.insert(&sender_id, &true);
+        let _ = associated_contract_interface::ext(self.associated_contract_account_id.get().unwrap())
+            .with_static_gas(self.register_gas)
+            .register_for_an_event(event_id, sender_id.clone())
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_REGISTER_CALLBACK)
+                    .on_register_for_event(sender_id, event_id, U128::from(burn_amount)),
+            );
+    }
+
+    // Minimum time in nanoseconds between two registrations of an account,
+    // counted from when the associated contract accepted the previous one
+    pub fn set_registration_cooldown(&mut self, cooldown_ns: U64) {
+        self.only_owner();
+        self.registration_cooldown_ns = cooldown_ns.into();
+    }
+
+    pub fn get_registration_cooldown(&self) -> U64 {
+        U64::from(self.registration_cooldown_ns)
+    }
+
+    // Once any event is allowed, register_for_event rejects all others
+    pub fn allow_event(&mut self, event_id: U128) {
+        self.only_owner();
+        self.allowed_events.insert(&event_id);
+    }
+
+    pub fn disallow_event(&mut self, event_id: U128) {
+        self.only_owner();
+        self.allowed_events.remove(&event_id);
+    }
+
+    pub fn get_allowed_events(&self) -> Vec<U128> {
+        self.allowed_events.to_vec()
+    }
+
+    // The page of allowed_events that `account_id` could still register for:
+    // not registered for yet and affordable at the current registration
+    // fee. Empty while allowed_events is, as any id is accepted then
+    pub fn eligible_events(&self, account_id: AccountId, from_index: u64, limit: u64) -> Vec<U128> {
+        let registered = self.registered_events.get(&account_id).unwrap_or_default();
+        let burn_amount = self.malborn_token.total_supply / self.current_fee_denominator().0;
+        if self.malborn_token.accounts.get(&account_id).unwrap_or(0) < burn_amount {
+            return Vec::new();
+        }
+        self.allowed_events
+            .as_vector()
+            .iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .filter(|event_id| !registered.contains(&event_id.0))
+            .collect()
+    }
+
+    #[private]
+    pub fn on_register_for_event(&mut self, account_id: AccountId, event_id: U128, burn_amount: U128) {
+        self.in_flight.remove(&account_id);
+        // Only registrations the associated contract accepted are counted
+        if is_promise_success() {
+            self.last_registration.insert(&account_id, &env::block_timestamp());
+            let mut registered = self.registered_events.get(&account_id).unwrap_or_default();
+            registered.insert(event_id.0);
+            self.registered_events.insert(&account_id, &registered);
+            self.total_registrations += 1;
+            self.total_registration_burn = self
+                .total_registration_burn
+                .checked_add(burn_amount.into())
+                .expect("Registration burn overflow");
+        }
+    }
+
+    pub fn get_registration_stats(&self) -> RegistrationStats {
+        RegistrationStats {
+            total_registrations: U64::from(self.total_registrations),
+            total_registration_burn: U128::from(self.total_registration_burn),
+        }
+    }
+
+    // Ask the associated contract for its event count, cached by on_event_count
+    pub fn refresh_event_count(&mut self) {
+        assert!(
+            self.associated_contract_account_id.is_some(),
+            "Associated Account is not set"
+        );
+        gas::assert_min_gas(GAS_FOR_EVENT_COUNT.saturating_add(GAS_FOR_EVENT_COUNT_CALLBACK));
+        let _ = associated_contract_interface::ext(self.associated_contract_account_id.get().unwrap())
+            .with_static_gas(GAS_FOR_EVENT_COUNT)
+            .get_next_event_idx()
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_EVENT_COUNT_CALLBACK)
+                    .on_event_count(),
+            );
+    }
+
+    #[private]
+    pub fn on_event_count(&mut self, #[callback_result] count: Result<u16, PromiseError>) {
+        match count {
+            Ok(count) => {
+                self.associated_event_count.set(&u64::from(count));
+            }
+            Err(_) => log!("Refreshing the event count failed"),
+        }
+    }
+
+    pub fn get_cached_event_count(&self) -> Option<U64> {
+        self.associated_event_count.get().map(U64::from)
+    }
+
+    // Ask the associated contract how much gas register_for_an_event needs,
+    // cached by on_associated_gas. Operators compare it to get_register_gas
+    pub fn query_associated_gas(&mut self) {
+        assert!(
+            self.associated_contract_account_id.is_some(),
+            "Associated Account is not set"
+        );
+        gas::assert_min_gas(GAS_FOR_GAS_QUERY.saturating_add(GAS_FOR_GAS_QUERY_CALLBACK));
         let _ = associated_contract_interface::ext(self.associated_contract_account_id.get().unwrap())
-            .with_static_gas(GAS_FOR_REGISTER)
-            .register_for_an_event(event_id, sender_id);
+            .with_static_gas(GAS_FOR_GAS_QUERY)
+            .get_recommended_register_gas()
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_GAS_QUERY_CALLBACK)
+                    .on_associated_gas(),
+            );
+    }
+
+    #[private]
+    pub fn on_associated_gas(&mut self, #[callback_result] gas: Result<Gas, PromiseError>) {
+        match gas {
+            Ok(gas) => self.recommended_register_gas = Some(gas),
+            Err(_) => log!("Querying the associated contract gas failed"),
+        }
+    }
+
+    pub fn get_recommended_register_gas(&self) -> Option<Gas> {
+        self.recommended_register_gas
     }
 
     pub fn upgrade_token_name_symbol(&mut self, name: String, symbol: String) {
         self.only_owner();
+        assert!(!name.is_empty(), "Name must not be empty");
+        assert!(!symbol.is_empty(), "Symbol must not be empty");
+        assert!(
+            name.len() <= MAX_NAME_LEN,
+            "Name must be at most {} bytes",
+            MAX_NAME_LEN
+        );
+        assert!(
+            symbol.len() <= MAX_SYMBOL_LEN,
+            "Symbol must be at most {} bytes",
+            MAX_SYMBOL_LEN
+        );
         let metadata = self.token_metadata.take();
         if let Some(mut metadata) = metadata {
+            events::emit(
+                "metadata_updated",
+                json!({
+                    "old_name": metadata.name,
+                    "old_symbol": metadata.symbol,
+                    "new_name": name,
+                    "new_symbol": symbol,
+                }),
+            );
             metadata.name = name;
             metadata.symbol = symbol;
             self.token_metadata.replace(&metadata);
@@ -167,6 +756,10 @@ impl MalbornClubContract {
         self.only_owner();
         self.not_paused();
         self.block_list.insert(account_id, &BlocklistStatus::Banned);
+        self.ban_effective_at.insert(
+            account_id,
+            &env::block_timestamp().saturating_add(self.ban_grace_ns),
+        );
     }
 
     pub fn remove_from_blocklist(&mut self, account_id: &AccountId) {
@@ -174,379 +767,3465 @@ impl MalbornClubContract {
         self.not_paused();
         self.block_list
             .insert(account_id, &BlocklistStatus::Allowed);
+        self.ban_effective_at.remove(account_id);
     }
 
-    pub fn pause(&mut self) {
+    // Lets transactions already in flight when an account is banned go
+    // through. Only affects bans placed afterwards
+    pub fn set_ban_grace(&mut self, grace_ns: U64) {
         self.only_owner();
-        self.status = ContractStatus::Paused;
+        self.ban_grace_ns = grace_ns.into();
     }
 
-    pub fn resume(&mut self) {
-        self.only_owner();
-        self.status = ContractStatus::Paused;
+    pub fn get_ban_grace(&self) -> U64 {
+        U64::from(self.ban_grace_ns)
     }
 
-    pub fn set_owner(&mut self, new_owner: AccountId) {
+    // When the ban of `account_id` stops its transfers, None if it isn't banned
+    pub fn ban_effective_at(&self, account_id: AccountId) -> Option<U64> {
+        if self.block_list.get(&account_id) != Some(BlocklistStatus::Banned) {
+            return None;
+        }
+        Some(U64::from(self.ban_effective_at.get(&account_id).unwrap_or(0)))
+    }
+
+    // Lift a ban placed in error and pay `amount` from the owner's balance
+    // as restitution. Paying from the owner keeps the supply unchanged,
+    // and works after minting has been renounced. With an amount of 0 the
+    // account is only unbanned. The payment is subject to max_balance
+    pub fn unban_and_compensate(&mut self, account_id: AccountId, amount: U128) {
         self.only_owner();
-        self.owner_id = new_owner;
+        self.not_paused();
+        assert!(
+            self.block_list.get(&account_id) == Some(BlocklistStatus::Banned),
+            "Account is not banned"
+        );
+        self.block_list
+            .insert(&account_id, &BlocklistStatus::Allowed);
+        self.ban_effective_at.remove(&account_id);
+        if amount.0 == 0 {
+            return;
+        }
+        self.within_max_balance(&account_id, amount.into());
+        let owner_id = self.owner_id.clone();
+        self.malborn_token
+            .internal_transfer(&owner_id, &account_id, amount.into(), Some("compensation".to_string()));
+        events::emit(
+            "account_compensated",
+            json!({
+                "account_id": account_id,
+                "amount": amount,
+            }),
+        );
     }
 
-    pub fn set_registration_fee_denominator(&mut self, new_denominator: U128) {
+    pub fn pause(&mut self) {
         self.only_owner();
-        self.registration_fee_denominator = new_denominator;
+        self.settle_auto_resume();
+        self.status = ContractStatus::Paused;
+        self.auto_resume_at = None;
+        self.status_epoch += 1;
     }
 
-    pub fn set_associated_contract(&mut self, account_id: AccountId) {
+    // Pause until the given block timestamp (in nanoseconds), after which the
+    // contract behaves as working again without another call
+    pub fn pause_until(&mut self, timestamp: U64) {
         self.only_owner();
-        self.associated_contract_account_id.set(&account_id);
+        assert!(
+            u64::from(timestamp) > env::block_timestamp(),
+            "Resume time must be in the future"
+        );
+        self.settle_auto_resume();
+        self.status = ContractStatus::Paused;
+        self.auto_resume_at = Some(timestamp.into());
+        self.status_epoch += 1;
     }
 
-    pub fn get_symbol(&mut self) -> String {
-        self.not_paused();
-        let metadata = self.token_metadata.take();
-        metadata
-            .expect("Unable to retrieve metadata at this moment")
-            .symbol
+    pub fn resume(&mut self) {
+        self.only_owner();
+        self.status = ContractStatus::Paused;
+        self.status_epoch += 1;
     }
 
-    pub fn get_name(&mut self) -> String {
-        self.not_paused();
-        let metadata = self.token_metadata.take();
-        metadata
-            .expect("Unable to retrieve metadata at this moment")
-            .name
+    // Lets clients notice status changes between reading the status and
+    // acting on it, see ft_transfer_at_epoch
+    pub fn get_status_epoch(&self) -> U64 {
+        U64::from(self.current_status_epoch())
     }
 
-    pub fn get_decimals(&mut self) -> u8 {
-        self.not_paused();
-        let metadata = self.token_metadata.take();
-        metadata
-            .expect("Unable to retrieve metadata at this moment")
-            .decimals
+    // Record an expired pause_until deadline as the status change it was, so
+    // the epoch reported since then is stale once the contract pauses again
+    fn settle_auto_resume(&mut self) {
+        if self.effective_status() != self.status {
+            self.status = ContractStatus::Working;
+            self.auto_resume_at = None;
+            self.status_epoch += 1;
+        }
     }
 
-    pub fn contract_status(&self) -> ContractStatus {
-        self.status
+    // status_epoch plus one once a pause_until deadline has passed, so the
+    // auto-resume counts as a status change although no call recorded it
+    fn current_status_epoch(&self) -> u64 {
+        if self.effective_status() != self.status {
+            self.status_epoch + 1
+        } else {
+            self.status_epoch
+        }
     }
 
-    pub fn get_blocklist_status(&self, account_id: &AccountId) -> BlocklistStatus {
-        self.not_paused();
-        return match self.block_list.get(account_id) {
-            Some(user_status) => user_status.clone(),
-            None => BlocklistStatus::Allowed,
-        };
+    // Allow the owner to ft_transfer while paused, e.g. to move funds to a
+    // safe account during an incident. Everyone else stays blocked
+    pub fn set_owner_exempt_from_pause(&mut self, exempt: bool) {
+        self.only_owner();
+        self.owner_exempt_from_pause = exempt;
     }
 
-    // **** Helpers ****
+    pub fn is_owner_exempt_from_pause(&self) -> bool {
+        self.owner_exempt_from_pause
+    }
 
-    fn burn_tokens_internal(&mut self, account_id: &AccountId, amount: U128) {
-        assert!(&self.malborn_token.total_supply >= &Balance::from(amount));
-        let user_balance = self
-            .malborn_token
-            .accounts
-            .get(account_id)
-            .expect("User not registered");
-        assert!(user_balance >= u128::from(amount));
+    // When disabled, minters can fund blocklisted accounts again
+    pub fn set_enforce_blocklist_on_mint(&mut self, enforce: bool) {
+        self.only_owner();
+        self.enforce_blocklist_on_mint = enforce;
+    }
 
-        self.malborn_token.total_supply = self
-            .malborn_token
-            .total_supply
-            .checked_sub(u128::from(amount))
-            .expect("Burn caused underflow");
+    pub fn is_blocklist_enforced_on_mint(&self) -> bool {
+        self.enforce_blocklist_on_mint
+    }
 
-        self.malborn_token.accounts.insert(
-            account_id,
-            &user_balance
-                .checked_sub(u128::from(amount))
-                .expect("Underflow in user balance"),
-        );
+    // Replaces any role the account already holds
+    pub fn grant_role(&mut self, account_id: AccountId, role: Role) {
+        self.only_owner();
+        self.roles.insert(&account_id, &role);
     }
 
-    fn only_owner(&self) {
-        if env::signer_account_id() != self.owner_id {
-            env::panic_str("Can only be called by owner");
-        }
+    pub fn revoke_role(&mut self, account_id: AccountId) {
+        self.only_owner();
+        self.roles.remove(&account_id);
     }
 
-    fn not_paused(&self) {
-        if self.status == ContractStatus::Paused {
-            env::panic_str("Contract is paused");
-        }
+    pub fn get_role(&self, account_id: AccountId) -> Option<Role> {
+        self.roles.get(&account_id)
     }
 
-    fn not_banned(&self, account_id: AccountId) {
-        if self.get_blocklist_status(&account_id) == BlocklistStatus::Banned {
-            env::panic_str("User is banned");
-        }
+    // Every role `account_id` holds. An account holds at most one role for
+    // now, so this is get_role as a list
+    pub fn get_roles(&self, account_id: AccountId) -> Vec<Role> {
+        self.roles.get(&account_id).into_iter().collect()
     }
 
-    fn on_account_closed(&mut self, account_id: AccountId, balance: Balance) {
-        log!("Closed @{} with {}", account_id, balance);
+    // At most `limit` holders of `role` starting at `from_index`, in
+    // insertion order
+    pub fn get_accounts_with_role(&self, role: Role, from_index: u64, limit: u64) -> Vec<AccountId> {
+        self.roles
+            .iter()
+            .filter(|(_, account_role)| account_role == &role)
+            .map(|(account_id, _)| account_id)
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .collect()
     }
-}
 
-#[near_bindgen]
-impl FungibleTokenCore for MalbornClubContract {
-    #[payable]
-    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>) {
-        self.not_paused();
-        let sender_id = env::signer_account_id();
-        self.not_banned(sender_id.clone());
+    // Cap the balance of every account but the owner, or lift the cap with
+    // None. Balances already above a new cap are left as they are
+    pub fn set_max_balance(&mut self, max_balance: Option<U128>) {
+        self.only_owner();
+        self.max_balance = max_balance.map(u128::from);
+    }
+
+    pub fn set_transfer_burn_bps(&mut self, bps: u16) {
+        self.only_owner();
         assert!(
-            u128::from(amount)
-                <= u128::from(self.ft_balance_of(sender_id))
+            bps <= MAX_TRANSFER_BURN_BPS,
+            "Transfer burn must be at most {} bps",
+            MAX_TRANSFER_BURN_BPS
         );
-        self.malborn_token
-            .ft_transfer(receiver_id.clone(), amount, memo);
+        self.transfer_burn_bps = bps;
     }
 
-    #[payable]
-    fn ft_transfer_call(
+    pub fn get_transfer_burn_bps(&self) -> u16 {
+        self.transfer_burn_bps
+    }
+
+    pub fn get_max_balance(&self) -> Option<U128> {
+        self.max_balance.map(U128::from)
+    }
+
+    // Reject transfers below the minimum, or lift it with None
+    pub fn set_min_transfer_amount(&mut self, min_transfer_amount: Option<U128>) {
+        self.only_owner();
+        self.min_transfer_amount = min_transfer_amount.map(u128::from);
+    }
+
+    pub fn get_min_transfer_amount(&self) -> Option<U128> {
+        self.min_transfer_amount.map(U128::from)
+    }
+
+    pub fn set_owner(&mut self, new_owner: AccountId) {
+        self.only_owner();
+        self.single_owner_mode();
+        self.owner_id = new_owner;
+    }
+
+    // Hand mints and ownership changes over to `threshold` of `approvers`.
+    // Afterwards the approver set can only be changed by a proposal
+    pub fn set_approvers(&mut self, approvers: Vec<AccountId>, threshold: u8) {
+        self.only_owner();
+        self.single_owner_mode();
+        self.replace_approvers(approvers, threshold);
+    }
+
+    pub fn get_approvers(&self) -> Vec<AccountId> {
+        self.approvers.to_vec()
+    }
+
+    pub fn get_approval_threshold(&self) -> u8 {
+        self.approval_threshold
+    }
+
+    // Propose `action` as an approver, counting as its first approval.
+    // Returns the proposal id
+    pub fn propose_action(&mut self, action: SensitiveAction) -> u64 {
+        let approver = self.only_approver();
+        let proposal_id = self.next_proposal_id;
+        self.next_proposal_id += 1;
+        self.proposals.insert(
+            &proposal_id,
+            &ActionProposal {
+                action: action.clone(),
+                approvals: vec![approver.clone()],
+            },
+        );
+        events::emit(
+            "action_proposed",
+            json!({
+                "proposal_id": U64::from(proposal_id),
+                "proposer": approver,
+                "action": action,
+            }),
+        );
+        proposal_id
+    }
+
+    pub fn approve_action(&mut self, proposal_id: u64) {
+        let approver = self.only_approver();
+        let mut proposal = self.proposals.get(&proposal_id).expect("Proposal not found");
+        assert!(
+            !proposal.approvals.contains(&approver),
+            "Proposal already approved by @{}",
+            approver
+        );
+        proposal.approvals.push(approver);
+        self.proposals.insert(&proposal_id, &proposal);
+    }
+
+    // Run a proposal once enough approvers agreed. Approvals from accounts
+    // removed from the approver set since don't count
+    pub fn execute_action(&mut self, proposal_id: u64) {
+        self.only_approver();
+        let proposal = self.proposals.get(&proposal_id).expect("Proposal not found");
+        let approvals = proposal
+            .approvals
+            .iter()
+            .filter(|approver| self.approvers.contains(approver))
+            .count();
+        assert!(
+            approvals >= usize::from(self.approval_threshold),
+            "Not enough approvals: {} of {}",
+            approvals,
+            self.approval_threshold
+        );
+        self.proposals.remove(&proposal_id);
+        match proposal.action {
+            SensitiveAction::Mint { account_id, amount } => {
+                self.not_paused();
+                self.minting_not_renounced();
+                self.mint_recipient_not_banned(&account_id);
+                self.mint_tokens_internal(&account_id, amount);
+            }
+            SensitiveAction::SetOwner { new_owner } => self.owner_id = new_owner,
+            SensitiveAction::SetApprovers { approvers, threshold } => {
+                self.replace_approvers(approvers, threshold)
+            }
+        }
+        events::emit(
+            "action_executed",
+            json!({ "proposal_id": U64::from(proposal_id) }),
+        );
+    }
+
+    pub fn get_proposal(&self, proposal_id: u64) -> Option<ActionProposal> {
+        self.proposals.get(&proposal_id)
+    }
+
+    pub fn get_owner(&self) -> AccountId {
+        self.owner_id.clone()
+    }
+
+    pub fn is_owner(&self, account_id: AccountId) -> bool {
+        account_id == self.owner_id
+    }
+
+    // Takes effect after fee_change_timelock_ns, replacing any change still
+    // pending. See apply_fee_change
+    pub fn set_registration_fee_denominator(&mut self, new_denominator: U128) {
+        self.only_owner();
+        if self.fee_change_timelock_ns == 0 {
+            self.registration_fee_denominator = new_denominator;
+            self.pending_fee_denominator = None;
+        } else {
+            let effective_at = env::block_timestamp().saturating_add(self.fee_change_timelock_ns);
+            self.pending_fee_denominator = Some((new_denominator, effective_at));
+        }
+    }
+
+    // Stores the pending fee change once its timelock elapsed. Registrations
+    // already use it from then on, so calling this is only bookkeeping
+    pub fn apply_fee_change(&mut self) {
+        let (new_denominator, effective_at) = self
+            .pending_fee_denominator
+            .expect("No pending fee change");
+        assert!(
+            env::block_timestamp() >= effective_at,
+            "Fee change timelock has not elapsed"
+        );
+        self.registration_fee_denominator = new_denominator;
+        self.pending_fee_denominator = None;
+    }
+
+    pub fn get_registration_fee_denominator(&self) -> U128 {
+        self.current_fee_denominator()
+    }
+
+    pub fn get_pending_fee_change(&self) -> Option<(U128, U64)> {
+        self.pending_fee_denominator
+            .map(|(new_denominator, effective_at)| (new_denominator, U64::from(effective_at)))
+    }
+
+    // Only affects fee changes made afterwards
+    pub fn set_fee_change_timelock(&mut self, timelock_ns: U64) {
+        self.only_owner();
+        self.fee_change_timelock_ns = timelock_ns.into();
+    }
+
+    pub fn get_fee_change_timelock(&self) -> U64 {
+        U64::from(self.fee_change_timelock_ns)
+    }
+
+    // A dead account must not already hold tokens, so that everything it
+    // holds is provably burned. It is registered if needed
+    pub fn set_burn_strategy(&mut self, burn_strategy: BurnStrategy) {
+        self.only_owner();
+        if let BurnStrategy::DeadAccount(dead_account) = &burn_strategy {
+            assert!(
+                dead_account != &self.owner_id,
+                "Dead account must not be the owner"
+            );
+            match self.malborn_token.accounts.get(dead_account) {
+                Some(balance) => assert!(balance == 0, "Dead account must not hold tokens"),
+                None => self.malborn_token.internal_register_account(dead_account),
+            }
+        }
+        self.burn_strategy = burn_strategy;
+    }
+
+    // Set where forced storage_unregister sends residual balances, or
+    // burn them again with None
+    pub fn set_sweep_account(&mut self, account_id: Option<AccountId>) {
+        self.only_owner();
+        if let Some(account_id) = &account_id {
+            if !self.malborn_token.accounts.contains_key(account_id) {
+                self.malborn_token.internal_register_account(account_id);
+            }
+        }
+        self.sweep_account = account_id;
+    }
+
+    pub fn get_sweep_account(&self) -> Option<AccountId> {
+        self.sweep_account.clone()
+    }
+
+    // Set where the unburned part of registration fees goes, or burn all of
+    // it again with None
+    pub fn set_treasury_account(&mut self, account_id: Option<AccountId>) {
+        self.only_owner();
+        if let Some(account_id) = &account_id {
+            if !self.malborn_token.accounts.contains_key(account_id) {
+                self.malborn_token.internal_register_account(account_id);
+            }
+        }
+        self.treasury_account = account_id;
+    }
+
+    pub fn get_treasury_account(&self) -> Option<AccountId> {
+        self.treasury_account.clone()
+    }
+
+    pub fn set_registration_burn_split(&mut self, bps: u16) {
+        self.only_owner();
+        assert!(
+            bps <= MAX_REGISTRATION_BURN_SPLIT_BPS,
+            "Registration burn split must be at most {} bps",
+            MAX_REGISTRATION_BURN_SPLIT_BPS
+        );
+        self.registration_burn_split_bps = bps;
+    }
+
+    pub fn get_registration_burn_split(&self) -> u16 {
+        self.registration_burn_split_bps
+    }
+
+    pub fn get_burn_strategy(&self) -> BurnStrategy {
+        self.burn_strategy.clone()
+    }
+
+    // Gas for the associated contract's register_for_an_event, between
+    // MIN_REGISTER_GAS and MAX_REGISTER_GAS
+    pub fn set_register_gas(&mut self, gas: Gas) {
+        self.only_owner();
+        assert!(
+            gas >= MIN_REGISTER_GAS && gas <= MAX_REGISTER_GAS,
+            "Register gas must be between {} and {}",
+            MIN_REGISTER_GAS,
+            MAX_REGISTER_GAS
+        );
+        self.register_gas = gas;
+    }
+
+    pub fn get_register_gas(&self) -> Gas {
+        self.register_gas
+    }
+
+    pub fn set_associated_contract(&mut self, account_id: AccountId) {
+        self.only_owner();
+        self.associated_contract_account_id.set(&account_id);
+    }
+
+    // Enable the transfer hook on `account_id`, or disable it with None
+    pub fn set_transfer_hook(&mut self, account_id: Option<AccountId>) {
+        self.only_owner();
+        match account_id {
+            Some(account_id) => self.transfer_hook_account.set(&account_id),
+            None => self.transfer_hook_account.remove(),
+        };
+    }
+
+    // At most `limit` (timestamp, total supply) entries starting at `from_index`
+    pub fn get_supply_history(&self, from_index: u64, limit: u64) -> Vec<(U64, U128)> {
+        self.supply_history
+            .iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .map(|&(timestamp, supply)| (U64::from(timestamp), U128::from(supply)))
+            .collect()
+    }
+
+    // Move `amount` of the owner's tokens into a vesting schedule for
+    // `account_id`. `cliff` and `duration` are offsets from `start`. The
+    // tokens are escrowed on the contract's own account, so total_supply
+    // doesn't change
+    pub fn create_vesting(
         &mut self,
-        receiver_id: AccountId,
+        account_id: AccountId,
         amount: U128,
-        memo: Option<String>,
-        msg: String,
-    ) -> PromiseOrValue<U128> {
+        start: U64,
+        cliff: U64,
+        duration: U64,
+    ) {
+        self.only_owner();
         self.not_paused();
-        let sender_id = env::signer_account_id();
-        self.not_banned(sender_id.clone());
+        assert!(u64::from(duration) > 0, "Duration must be positive");
+        assert!(
+            u64::from(cliff) <= u64::from(duration),
+            "Cliff must not exceed duration"
+        );
+        assert!(
+            self.vesting.get(&account_id).is_none(),
+            "Account already has a vesting schedule"
+        );
+
+        let escrow_id = env::current_account_id();
+        if !self.malborn_token.accounts.contains_key(&escrow_id) {
+            self.malborn_token.internal_register_account(&escrow_id);
+        }
         self.malborn_token
-            .ft_transfer_call(receiver_id.clone(), amount, memo, msg)
+            .internal_transfer(&self.owner_id, &escrow_id, amount.into(), None);
+        self.vesting.insert(
+            &account_id,
+            &VestingSchedule {
+                total: amount,
+                start,
+                cliff,
+                duration,
+                claimed: U128::from(0),
+            },
+        );
     }
 
-    fn ft_total_supply(&self) -> U128 {
+    // Transfer the unlocked, not yet claimed part of the caller's vesting
+    // schedule to them out of escrow, subject to the blocklist and the
+    // balance cap like any transfer. Returns the amount transferred
+    pub fn claim_vested(&mut self) -> U128 {
         self.not_paused();
-        self.malborn_token.ft_total_supply()
+        let account_id = env::predecessor_account_id();
+        self.not_banned(account_id.clone());
+        let mut schedule = self
+            .vesting
+            .get(&account_id)
+            .expect("No vesting schedule");
+        let amount = self.claimable_amount(account_id.clone());
+
+        schedule.claimed = U128::from(schedule.claimed.0 + amount.0);
+        self.vesting.insert(&account_id, &schedule);
+        if amount.0 > 0 {
+            self.within_max_balance(&account_id, amount.into());
+            self.malborn_token.internal_transfer(
+                &env::current_account_id(),
+                &account_id,
+                amount.into(),
+                None,
+            );
+        }
+        amount
     }
 
-    fn ft_balance_of(&self, account_id: AccountId) -> U128 {
+    pub fn get_vesting(&self, account_id: AccountId) -> Option<VestingSchedule> {
+        self.vesting.get(&account_id)
+    }
+
+    // Amount of `account_id`'s schedule unlocked so far, claimed or not
+    pub fn vested_amount(&self, account_id: AccountId) -> U128 {
+        let schedule = match self.vesting.get(&account_id) {
+            Some(schedule) => schedule,
+            None => return U128::from(0),
+        };
+        let elapsed = env::block_timestamp().saturating_sub(schedule.start.0);
+        let vested = if elapsed < schedule.cliff.0 {
+            0
+        } else if elapsed >= schedule.duration.0 {
+            schedule.total.0
+        } else {
+            mul_div(schedule.total.0, elapsed.into(), schedule.duration.0.into())
+        };
+        U128::from(vested)
+    }
+
+    // Amount `claim_vested` would currently transfer to `account_id`
+    pub fn claimable_amount(&self, account_id: AccountId) -> U128 {
+        let claimed = self
+            .vesting
+            .get(&account_id)
+            .map_or(0, |schedule| schedule.claimed.0);
+        U128::from(self.vested_amount(account_id).0 - claimed)
+    }
+
+    // Allow `spender_id` to move up to `amount` of the caller's tokens, or
+    // revoke the approval with 0. Each approval must be covered by the
+    // caller's storage deposit
+    pub fn ft_approve(&mut self, spender_id: AccountId, amount: U128) {
         self.not_paused();
-        self.malborn_token
-            .ft_balance_of(account_id)
+        let approver_id = env::predecessor_account_id();
+        self.not_banned(approver_id.clone());
+        assert!(
+            self.malborn_token.accounts.contains_key(&approver_id),
+            "The account {} is not registered",
+            &approver_id
+        );
+
+        let mut approvals = self.approvals.get(&approver_id).unwrap_or_default();
+        if amount.0 == 0 {
+            approvals.remove(&spender_id);
+        } else {
+            approvals.insert(spender_id, amount.0);
+            let count = approvals.len() as u64;
+            assert!(
+                count <= self.max_approvals_per_account,
+                "At most {} approvals per account",
+                self.max_approvals_per_account
+            );
+            assert!(
+                self.approval_storage_paid.get(&approver_id).unwrap_or(0)
+                    >= Self::approval_storage_cost(count),
+                "Insufficient storage deposit for {} approvals",
+                count
+            );
+        }
+        self.approvals.insert(&approver_id, &approvals);
     }
-}
 
-#[near_bindgen]
-impl FungibleTokenResolver for MalbornClubContract {
-    #[private]
-    fn ft_resolve_transfer(
+    pub fn ft_allowance(&self, owner_id: AccountId, spender_id: AccountId) -> U128 {
+        let allowance = self
+            .approvals
+            .get(&owner_id)
+            .and_then(|approvals| approvals.get(&spender_id).copied());
+        U128::from(allowance.unwrap_or(0))
+    }
+
+    // Only affects the storage deposit of accounts registered from now on,
+    // existing ones can top up with storage_deposit
+    pub fn set_max_approvals_per_account(&mut self, max_approvals: U64) {
+        self.only_owner();
+        self.max_approvals_per_account = max_approvals.into();
+    }
+
+    pub fn get_max_approvals_per_account(&self) -> U64 {
+        U64::from(self.max_approvals_per_account)
+    }
+
+    // Whether `sender` could transfer `amount` to `receiver` right now,
+    // following the same checks, in the same order, as ft_transfer
+    pub fn can_transfer(&self, sender: AccountId, receiver: AccountId, amount: U128) -> TransferCheck {
+        if self.effective_status() == ContractStatus::Paused && !self.is_pause_exempt(&sender) {
+            return TransferCheck::Paused;
+        }
+        if self.is_banned(&sender) {
+            return TransferCheck::SenderBanned;
+        }
+        let balance = match self.malborn_token.accounts.get(&sender) {
+            Some(balance) => balance,
+            None => return TransferCheck::SenderNotRegistered,
+        };
+        if amount.0 > balance {
+            return TransferCheck::InsufficientBalance;
+        }
+        if self.is_below_min_transfer(&sender, &receiver, amount.0) {
+            return TransferCheck::BelowMinTransfer;
+        }
+        let received = amount.0 - self.transfer_burn(&sender, &receiver, amount.0);
+        if self.exceeds_max_balance(&receiver, received) {
+            return TransferCheck::ExceedsMaxBalance;
+        }
+        if sender == receiver {
+            return TransferCheck::SelfTransfer;
+        }
+        if amount.0 == 0 {
+            return TransferCheck::ZeroAmount;
+        }
+        if !self.malborn_token.accounts.contains_key(&receiver) {
+            return TransferCheck::ReceiverNotRegistered;
+        }
+        TransferCheck::Ok
+    }
+
+    // ft_transfer that fails with a clear message, instead of the inner
+    // token's, when the receiver has no storage deposit
+    #[payable]
+    pub fn safe_ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>) {
+        assert!(
+            self.storage_balance_of(receiver_id.clone()).is_some(),
+            "Receiver {} not registered; call storage_deposit first",
+            receiver_id
+        );
+        self.ft_transfer(receiver_id, amount, memo);
+    }
+
+    // ft_transfer that fails if the status changed since the caller read
+    // `expected_epoch` from get_status_epoch
+    #[payable]
+    pub fn ft_transfer_at_epoch(
         &mut self,
-        sender_id: AccountId,
         receiver_id: AccountId,
         amount: U128,
+        memo: Option<String>,
+        expected_epoch: U64,
+    ) {
+        let epoch = self.current_status_epoch();
+        assert_eq!(
+            epoch, expected_epoch.0,
+            "Status epoch is {}, expected {}",
+            epoch, expected_epoch.0
+        );
+        self.ft_transfer(receiver_id, amount, memo);
+    }
+
+    // Refund ft_resolve_transfer would give `sender` if the receiver of an
+    // ft_transfer_call of `amount` used `used` of it, given current balances.
+    // Refunds to a deleted sender are burned, so they preview as 0
+    pub fn preview_resolve(
+        &self,
+        sender: AccountId,
+        receiver: AccountId,
+        amount: U128,
+        used: U128,
     ) -> U128 {
-        self.malborn_token
-            .internal_ft_resolve_transfer(&sender_id, receiver_id, amount)
-            .0
-            .into()
+        let unused = amount.0.saturating_sub(used.0);
+        if unused == 0 || !self.malborn_token.accounts.contains_key(&sender) {
+            return U128::from(0);
+        }
+        let receiver_balance = self.malborn_token.accounts.get(&receiver).unwrap_or(0);
+        U128::from(unused.min(receiver_balance))
     }
-}
 
-#[near_bindgen]
-impl FungibleTokenMetadataProvider for MalbornClubContract {
-    fn ft_metadata(&self) -> FungibleTokenMetadata {
-        self.token_metadata.get().unwrap()
+    pub fn get_transfer_hook(&self) -> Option<AccountId> {
+        self.transfer_hook_account.get()
     }
-}
 
-#[near_bindgen]
-impl StorageManagement for MalbornClubContract {
-    #[payable]
-    fn storage_deposit(
-        &mut self,
-        account_id: Option<AccountId>,
-        registration_only: Option<bool>,
-    ) -> StorageBalance {
-        self.malborn_token
-            .storage_deposit(account_id, registration_only)
+    pub fn get_symbol(&mut self) -> String {
+        self.not_paused();
+        let metadata = self.token_metadata.take();
+        metadata
+            .expect("Unable to retrieve metadata at this moment")
+            .symbol
     }
 
-    #[payable]
-    fn storage_withdraw(&mut self, amount: Option<NearToken>) -> StorageBalance {
-        self.malborn_token.storage_withdraw(amount)
+    pub fn get_name(&mut self) -> String {
+        self.not_paused();
+        let metadata = self.token_metadata.take();
+        metadata
+            .expect("Unable to retrieve metadata at this moment")
+            .name
+    }
+
+    pub fn get_decimals(&mut self) -> u8 {
+        self.not_paused();
+        let metadata = self.token_metadata.take();
+        metadata
+            .expect("Unable to retrieve metadata at this moment")
+            .decimals
+    }
+
+    // Name, symbol, decimals, icon and spec in one view. Unlike the getters
+    // above it leaves the metadata in place
+    pub fn metadata_summary(&self) -> MetadataSummary {
+        let metadata = self
+            .token_metadata
+            .get()
+            .expect("Unable to retrieve metadata at this moment");
+        MetadataSummary {
+            name: metadata.name,
+            symbol: metadata.symbol,
+            decimals: metadata.decimals,
+            icon: metadata.icon,
+            spec: metadata.spec,
+        }
+    }
+
+    pub fn contract_status(&self) -> ContractStatus {
+        self.status
+    }
+
+    // Status taking an expired pause_until deadline into account
+    pub fn effective_status(&self) -> ContractStatus {
+        match self.auto_resume_at {
+            Some(resume_at)
+                if self.status == ContractStatus::Paused
+                    && env::block_timestamp() >= resume_at =>
+            {
+                ContractStatus::Working
+            }
+            _ => self.status,
+        }
+    }
+
+    pub fn get_blocklist_status(&self, account_id: &AccountId) -> BlocklistStatus {
+        self.not_paused();
+        return match self.block_list.get(account_id) {
+            Some(user_status) => user_status.clone(),
+            None => BlocklistStatus::Allowed,
+        };
+    }
+
+    // Balances of up to MAX_BALANCES_QUERY accounts, in the order given,
+    // 0 for unregistered accounts
+    pub fn ft_balances_of(&self, account_ids: Vec<AccountId>) -> Vec<U128> {
+        assert!(
+            account_ids.len() <= MAX_BALANCES_QUERY,
+            "At most {} accounts per query",
+            MAX_BALANCES_QUERY
+        );
+        account_ids
+            .iter()
+            .map(|account_id| U128::from(self.malborn_token.accounts.get(account_id).unwrap_or(0)))
+            .collect()
+    }
+
+    // Balance, blocklist status and registration of an account in one view
+    pub fn get_account_info(&self, account_id: AccountId) -> AccountInfo {
+        self.not_paused();
+        let balance = self.malborn_token.accounts.get(&account_id);
+        AccountInfo {
+            balance: U128::from(balance.unwrap_or(0)),
+            status: self
+                .block_list
+                .get(&account_id)
+                .unwrap_or(BlocklistStatus::Allowed),
+            is_registered: balance.is_some(),
+        }
+    }
+
+    // **** Helpers ****
+
+    // Credit `amount` to `account_id` and grow the supply. Minting fee,
+    // pause and ownership checks are up to the caller
+    fn mint_tokens_internal(&mut self, account_id: &AccountId, amount: U128) -> Balance {
+        self.within_max_balance(account_id, amount.into());
+        let total_supply = self
+            .malborn_token
+            .total_supply
+            .checked_add(u128::from(amount))
+            .expect("Minting caused overflow");
+        if let Some(max_supply) = self.max_supply {
+            assert!(
+                total_supply <= max_supply,
+                "Mint would exceed the max supply of {}",
+                max_supply
+            );
+        }
+        self.malborn_token.total_supply = total_supply;
+        self.record_supply();
+
+        if let Some(user_amount) = self.malborn_token.accounts.get(account_id) {
+            self.malborn_token.accounts.insert(
+                account_id,
+                &user_amount
+                    .checked_add(u128::from(amount))
+                    .expect("Exceeded balance"),
+            );
+        }
+
+        self.malborn_token.total_supply
+    }
+
+    // Check the attached deposit covers the fee of `mints` mints, forward
+    // the fee to the owner and refund the rest
+    fn collect_mint_fee(&self, mints: u128) {
+        let attached = env::attached_deposit();
+        let fee = self
+            .mint_fee
+            .map_or(NearToken::from_yoctonear(0), |fee| fee.saturating_mul(mints));
+        assert!(
+            attached >= fee,
+            "Attached deposit must cover the mint fee of {} yoctoNEAR",
+            fee.as_yoctonear()
+        );
+        if !fee.is_zero() {
+            let _ = Promise::new(self.owner_id.clone()).transfer(fee);
+        }
+        let excess = attached.saturating_sub(fee);
+        if !excess.is_zero() {
+            let _ = Promise::new(env::predecessor_account_id()).transfer(excess);
+        }
+    }
+
+    fn burn_tokens_internal(&mut self, account_id: &AccountId, amount: U128) {
+        assert!(&self.malborn_token.total_supply >= &Balance::from(amount));
+        let user_balance = self
+            .malborn_token
+            .accounts
+            .get(account_id)
+            .expect("User not registered");
+        assert!(user_balance >= u128::from(amount));
+
+        match &self.burn_strategy {
+            BurnStrategy::ReduceSupply => {
+                self.malborn_token.total_supply = self
+                    .malborn_token
+                    .total_supply
+                    .checked_sub(u128::from(amount))
+                    .expect("Burn caused underflow");
+
+                self.malborn_token.accounts.insert(
+                    account_id,
+                    &user_balance
+                        .checked_sub(u128::from(amount))
+                        .expect("Underflow in user balance"),
+                );
+            }
+            BurnStrategy::DeadAccount(dead_account) => {
+                let dead_account = dead_account.clone();
+                self.malborn_token.internal_withdraw(account_id, amount.into());
+                self.malborn_token.internal_deposit(&dead_account, amount.into());
+                FtTransfer {
+                    old_owner_id: account_id,
+                    new_owner_id: &dead_account,
+                    amount,
+                    memo: Some("burn"),
+                }
+                .emit();
+            }
+        }
+        self.record_supply();
+    }
+
+    // Burn transfer_burn_bps of `amount` from the sender, the predecessor
+    // whose balance the inner transfer moves, and return what is left to
+    // transfer. Transfers from or to the owner or the treasury are not taxed
+    fn take_transfer_burn(&mut self, sender_id: &AccountId, receiver_id: &AccountId, amount: U128) -> U128 {
+        let burn = self.transfer_burn(sender_id, receiver_id, amount.0);
+        if burn > 0 {
+            self.burn_tokens_internal(sender_id, U128::from(burn));
+        }
+        U128::from(amount.0 - burn)
+    }
+
+    // What take_transfer_burn would burn of `amount`
+    fn transfer_burn(&self, sender_id: &AccountId, receiver_id: &AccountId, amount: Balance) -> Balance {
+        if self.is_transfer_exempt(sender_id, receiver_id) {
+            return 0;
+        }
+        amount * u128::from(self.transfer_burn_bps) / u128::from(MAX_TRANSFER_BURN_BPS)
+    }
+
+    // The pending denominator once its timelock elapsed, even before
+    // apply_fee_change stores it
+    fn current_fee_denominator(&self) -> U128 {
+        match self.pending_fee_denominator {
+            Some((new_denominator, effective_at)) if env::block_timestamp() >= effective_at => {
+                new_denominator
+            }
+            _ => self.registration_fee_denominator,
+        }
+    }
+
+    // Append the current total supply, dropping the oldest entry when full
+    fn record_supply(&mut self) {
+        if self.supply_history.len() >= MAX_SUPPLY_HISTORY {
+            self.supply_history.remove(0);
+        }
+        self.supply_history
+            .push((env::block_timestamp(), self.malborn_token.total_supply));
+    }
+
+    fn approval_storage_cost(approvals: u64) -> Balance {
+        env::storage_byte_cost().as_yoctonear()
+            * Balance::from(approvals * APPROVAL_STORAGE_BYTES)
+    }
+
+    fn only_owner(&self) {
+        if env::signer_account_id() != self.owner_id {
+            env::panic_str("Can only be called by owner");
+        }
+    }
+
+    // The owner and the treasury skip the transfer tax, the minimum
+    // transfer and the balance cap
+    fn is_exempt_account(&self, account_id: &AccountId) -> bool {
+        account_id == &self.owner_id || self.treasury_account.as_ref() == Some(account_id)
+    }
+
+    fn is_transfer_exempt(&self, sender_id: &AccountId, receiver_id: &AccountId) -> bool {
+        self.is_exempt_account(sender_id) || self.is_exempt_account(receiver_id)
+    }
+
+    fn above_min_transfer(&self, sender_id: &AccountId, receiver_id: &AccountId, amount: Balance) {
+        if let Some(min_transfer_amount) = self.min_transfer_amount {
+            if self.is_below_min_transfer(sender_id, receiver_id, amount) {
+                env::panic_str(&format!(
+                    "Transfer amount must be at least {}",
+                    min_transfer_amount
+                ));
+            }
+        }
+    }
+
+    fn is_below_min_transfer(&self, sender_id: &AccountId, receiver_id: &AccountId, amount: Balance) -> bool {
+        match self.min_transfer_amount {
+            Some(min_transfer_amount) if !self.is_transfer_exempt(sender_id, receiver_id) => {
+                amount < min_transfer_amount
+            }
+            _ => false,
+        }
+    }
+
+    fn within_max_balance(&self, account_id: &AccountId, amount: Balance) {
+        if let Some(max_balance) = self.max_balance {
+            if self.exceeds_max_balance(account_id, amount) {
+                env::panic_str(&format!(
+                    "Balance of @{} would exceed the maximum of {}",
+                    account_id, max_balance
+                ));
+            }
+        }
+    }
+
+    // Whether receiving `amount` would take the account over max_balance
+    fn exceeds_max_balance(&self, account_id: &AccountId, amount: Balance) -> bool {
+        let max_balance = match self.max_balance {
+            Some(max_balance) if !self.is_exempt_account(account_id) => max_balance,
+            _ => return false,
+        };
+        let balance = self.malborn_token.accounts.get(account_id).unwrap_or(0);
+        balance.saturating_add(amount) > max_balance
+    }
+
+    // The owner can always mint, other accounts need the Minter role
+    fn only_minter(&self) {
+        let signer_id = env::signer_account_id();
+        if signer_id != self.owner_id && self.roles.get(&signer_id) != Some(Role::Minter) {
+            env::panic_str("Can only be called by owner or a minter");
+        }
+    }
+
+    fn single_owner_mode(&self) {
+        if !self.approvers.is_empty() {
+            env::panic_str("Requires approval, use propose_action");
+        }
+    }
+
+    fn only_approver(&self) -> AccountId {
+        let signer_id = env::signer_account_id();
+        if !self.approvers.contains(&signer_id) {
+            env::panic_str("Can only be called by an approver");
+        }
+        signer_id
+    }
+
+    fn replace_approvers(&mut self, approvers: Vec<AccountId>, threshold: u8) {
+        let count = approvers.iter().collect::<HashSet<_>>().len();
+        if count == 0 {
+            assert_eq!(threshold, 0, "Threshold must be 0 without approvers");
+        } else {
+            assert!(
+                threshold >= 1 && usize::from(threshold) <= count,
+                "Threshold must be between 1 and {}",
+                count
+            );
+        }
+        self.approvers.clear();
+        for approver in &approvers {
+            self.approvers.insert(approver);
+        }
+        self.approval_threshold = threshold;
+    }
+
+    fn minting_not_renounced(&self) {
+        if self.minting_renounced {
+            env::panic_str("Minting has been renounced");
+        }
+    }
+
+    fn not_paused(&self) {
+        if self.effective_status() == ContractStatus::Paused {
+            env::panic_str("Contract is paused");
+        }
+    }
+
+    // The owner may still transfer while paused with owner_exempt_from_pause.
+    // Checked against the predecessor, not the signer, so contracts in an
+    // owner-signed transaction don't share the exemption
+    fn is_pause_exempt(&self, sender_id: &AccountId) -> bool {
+        self.owner_exempt_from_pause && sender_id == &self.owner_id
+    }
+
+    // Reads the blocklist directly so it also holds for transfers that are
+    // allowed while paused
+    fn not_banned(&self, account_id: AccountId) {
+        if self.is_banned(&account_id) {
+            env::panic_str("User is banned");
+        }
+    }
+
+    // Banned, and past the grace period of the ban
+    fn is_banned(&self, account_id: &AccountId) -> bool {
+        self.block_list.get(account_id) == Some(BlocklistStatus::Banned)
+            && env::block_timestamp() >= self.ban_effective_at.get(account_id).unwrap_or(0)
+    }
+
+    fn mint_recipient_not_banned(&self, account_id: &AccountId) {
+        if self.enforce_blocklist_on_mint {
+            self.not_banned(account_id.clone());
+        }
+    }
+
+    // The inner token burns the balance of force-closed accounts, credit it
+    // to the sweep account instead. Skipped when the sweep account itself
+    // is the one being closed
+    fn sweep_residual(&mut self, account_id: &AccountId, balance: Balance) {
+        let sweep_account = match &self.sweep_account {
+            Some(sweep_account) if balance > 0 && sweep_account != account_id => {
+                sweep_account.clone()
+            }
+            _ => return,
+        };
+        if !self.malborn_token.accounts.contains_key(&sweep_account) {
+            return;
+        }
+        self.malborn_token.internal_deposit(&sweep_account, balance);
+        log!("Swept {} from @{} to @{}", balance, account_id, sweep_account);
+    }
+
+    fn on_account_closed(&mut self, account_id: AccountId, balance: Balance) {
+        log!("Closed @{} with {}", account_id, balance);
+    }
+}
+
+#[near_bindgen]
+impl FungibleTokenCore for MalbornClubContract {
+    #[payable]
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>) {
+        let sender_id = env::signer_account_id();
+        if !self.is_pause_exempt(&env::predecessor_account_id()) {
+            self.not_paused();
+        }
+        self.not_banned(sender_id.clone());
+        assert!(
+            u128::from(amount)
+                <= u128::from(self.malborn_token.ft_balance_of(sender_id))
+        );
+        self.above_min_transfer(&env::predecessor_account_id(), &receiver_id, amount.into());
+        let amount = self.take_transfer_burn(&env::predecessor_account_id(), &receiver_id, amount);
+        self.within_max_balance(&receiver_id, amount.into());
+        self.malborn_token
+            .ft_transfer(receiver_id.clone(), amount, memo);
+
+        if let Some(hook_account) = self.transfer_hook_account.get() {
+            gas::assert_min_gas(GAS_FOR_TRANSFER_HOOK);
+            let _ = transfer_hook::ext(hook_account)
+                .with_static_gas(GAS_FOR_TRANSFER_HOOK)
+                .on_ft_transfer(env::predecessor_account_id(), receiver_id, amount);
+        }
+    }
+
+    #[payable]
+    fn ft_transfer_call(
+        &mut self,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        self.not_paused();
+        let sender_id = env::signer_account_id();
+        self.not_banned(sender_id.clone());
+        self.above_min_transfer(&env::predecessor_account_id(), &receiver_id, amount.into());
+        let amount = self.take_transfer_burn(&env::predecessor_account_id(), &receiver_id, amount);
+        self.within_max_balance(&receiver_id, amount.into());
+        self.malborn_token
+            .ft_transfer_call(receiver_id.clone(), amount, memo, msg)
+    }
+
+    fn ft_total_supply(&self) -> U128 {
+        self.not_paused();
+        self.malborn_token.ft_total_supply()
+    }
+
+    fn ft_balance_of(&self, account_id: AccountId) -> U128 {
+        self.not_paused();
+        self.malborn_token
+            .ft_balance_of(account_id)
+    }
+}
+
+#[near_bindgen]
+impl FungibleTokenResolver for MalbornClubContract {
+    #[private]
+    fn ft_resolve_transfer(
+        &mut self,
+        sender_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+    ) -> U128 {
+        let (used_amount, _) = self
+            .malborn_token
+            .internal_ft_resolve_transfer(&sender_id, receiver_id.clone(), amount);
+
+        // Tokens burned because the sender is gone count as used
+        let refund_amount = amount.0 - used_amount;
+        if refund_amount > 0 {
+            events::emit(
+                "ft_transfer_refund",
+                json!({
+                    "sender_id": sender_id,
+                    "receiver_id": receiver_id,
+                    "amount": U128::from(refund_amount),
+                }),
+            );
+        }
+        used_amount.into()
+    }
+}
+
+#[near_bindgen]
+impl FungibleTokenMetadataProvider for MalbornClubContract {
+    fn ft_metadata(&self) -> FungibleTokenMetadata {
+        self.token_metadata.get().unwrap()
+    }
+}
+
+#[near_bindgen]
+impl StorageManagement for MalbornClubContract {
+    // Registration also reserves storage for max_approvals_per_account
+    // approvals. Deposits for registered accounts top up that reservation,
+    // and anything beyond the minimum balance is refunded
+    #[payable]
+    #[allow(unused_variables)]
+    fn storage_deposit(
+        &mut self,
+        account_id: Option<AccountId>,
+        registration_only: Option<bool>,
+    ) -> StorageBalance {
+        let amount = env::attached_deposit().as_yoctonear();
+        let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+        let registration_cost = self.malborn_token.storage_balance_bounds().min.as_yoctonear();
+        let reserved = Self::approval_storage_cost(self.max_approvals_per_account);
+
+        let used = if self.malborn_token.accounts.contains_key(&account_id) {
+            let paid = self.approval_storage_paid.get(&account_id).unwrap_or(0);
+            let top_up = reserved.saturating_sub(paid).min(amount);
+            self.approval_storage_paid.insert(&account_id, &(paid + top_up));
+            top_up
+        } else {
+            assert!(
+                amount >= registration_cost + reserved,
+                "The attached deposit is less than the minimum storage balance"
+            );
+            self.malborn_token.internal_register_account(&account_id);
+            self.approval_storage_paid.insert(&account_id, &reserved);
+            registration_cost + reserved
+        };
+
+        if amount > used {
+            let _ = Promise::new(env::predecessor_account_id())
+                .transfer(NearToken::from_yoctonear(amount - used));
+        }
+        self.storage_balance_of(account_id).unwrap()
+    }
+
+    #[payable]
+    fn storage_withdraw(&mut self, amount: Option<NearToken>) -> StorageBalance {
+        self.malborn_token.storage_withdraw(amount)
+    }
+
+    #[payable]
+    fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+        #[allow(unused_variables)]
+        if let Some((account_id, balance)) = self.malborn_token.internal_storage_unregister(force) {
+            self.approvals.remove(&account_id);
+            self.sweep_residual(&account_id, balance);
+            if let Some(paid) = self.approval_storage_paid.remove(&account_id) {
+                if paid > 0 {
+                    let _ = Promise::new(account_id.clone())
+                        .transfer(NearToken::from_yoctonear(paid));
+                }
+            }
+            self.on_account_closed(account_id, balance);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        let bounds = self.malborn_token.storage_balance_bounds();
+        let min = bounds.min.saturating_add(NearToken::from_yoctonear(
+            Self::approval_storage_cost(self.max_approvals_per_account),
+        ));
+        StorageBalanceBounds { min, max: Some(min) }
+    }
+
+    fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+        let paid = self.approval_storage_paid.get(&account_id).unwrap_or(0);
+        self.malborn_token
+            .storage_balance_of(account_id)
+            .map(|balance| StorageBalance {
+                total: balance.total.saturating_add(NearToken::from_yoctonear(paid)),
+                available: balance.available,
+            })
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use near_contract_standards::fungible_token::Balance;
+    use near_sdk::mock::MockAction;
+    use near_sdk::test_utils::{accounts, get_created_receipts, get_logs, VMContextBuilder};
+    use near_sdk::{testing_env, test_vm_config, PromiseResult, RuntimeFeesConfig};
+
+    const TOTAL_SUPPLY: Balance = 1_000_000_000;
+
+    fn get_context(
+        predecessor_account_id: AccountId,
+        signer_account_id: AccountId,
+    ) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id(accounts(0))
+            .signer_account_id(signer_account_id)
+            .predecessor_account_id(predecessor_account_id);
+        builder
+    }
+
+    #[test]
+    fn test_new() {
+        let mut context = get_context(accounts(1), accounts(2));
+        testing_env!(context.build());
+        let contract = MalbornClubContract::new(accounts(1).into(), TOTAL_SUPPLY.into(), None, None);
+        testing_env!(context.is_view(true).build());
+
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY);
+        assert_eq!(
+            contract.ft_balance_of(accounts(1)),
+            U128::from(TOTAL_SUPPLY)
+        );
+    }
+
+    #[test]
+    fn test_new_emits_initial_mint() {
+        let context = get_context(accounts(1), accounts(1));
+        testing_env!(context.build());
+        MalbornClubContract::new(accounts(1), TOTAL_SUPPLY.into(), None, None);
+
+        assert_eq!(
+            get_logs(),
+            vec![format!(
+                r#"EVENT_JSON:{{"standard":"nep141","version":"1.0.0","event":"ft_mint","data":[{{"owner_id":"{}","amount":"{}","memo":"initial supply"}}]}}"#,
+                accounts(1),
+                TOTAL_SUPPLY
+            )]
+        );
+    }
+
+    #[test]
+    fn test_new_with_decimals() {
+        let context = get_context(accounts(1), accounts(1));
+        testing_env!(context.build());
+        let contract = MalbornClubContract::new(accounts(1), TOTAL_SUPPLY.into(), Some(6), None);
+        assert_eq!(contract.ft_metadata().decimals, 6);
+    }
+
+    #[test]
+    fn test_new_with_icon() {
+        let context = get_context(accounts(1), accounts(1));
+        testing_env!(context.build());
+        let icon = "data:image/svg+xml,%3Csvg xmlns='http://www.w3.org/2000/svg'/%3E".to_string();
+        let contract =
+            MalbornClubContract::new(accounts(1), TOTAL_SUPPLY.into(), None, Some(icon.clone()));
+        assert_eq!(contract.ft_metadata().icon, Some(icon));
+    }
+
+    #[test]
+    #[should_panic(expected = "Icon must be at most 8192 bytes")]
+    fn test_new_with_oversized_icon() {
+        let context = get_context(accounts(1), accounts(1));
+        testing_env!(context.build());
+        let icon = format!("data:image/svg+xml,{}", "a".repeat(MAX_ICON_LEN));
+        MalbornClubContract::new(accounts(1), TOTAL_SUPPLY.into(), None, Some(icon));
+    }
+
+    #[test]
+    #[should_panic(expected = "Decimals must be at most 24")]
+    fn test_new_with_too_many_decimals() {
+        let context = get_context(accounts(1), accounts(1));
+        testing_env!(context.build());
+        MalbornClubContract::new(accounts(1), TOTAL_SUPPLY.into(), Some(25), None);
+    }
+
+    #[test]
+    fn test_mint() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into(), None, None);
+
+        let mint_amount = TOTAL_SUPPLY / 2;
+
+        contract.mint_tokens(&accounts(2), U128::from(mint_amount));
+        assert_eq!(
+            contract.ft_balance_of(accounts(2)).0,
+            TOTAL_SUPPLY + mint_amount
+        );
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY + mint_amount);
+    }
+
+    #[test]
+    fn test_mint_to_owner() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+
+        let mint_amount = TOTAL_SUPPLY / 2;
+        assert_eq!(contract.mint_to_owner(U128::from(mint_amount)), TOTAL_SUPPLY + mint_amount);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, TOTAL_SUPPLY + mint_amount);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY + mint_amount);
+    }
+
+    #[test]
+    fn test_transfer() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into(), None, None);
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(1))
+            .build());
+        //Paying for account registration => storage deposit
+        contract.storage_deposit(None, None);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .predecessor_account_id(accounts(2))
+            .build());
+        let transfer_amount = TOTAL_SUPPLY / 3;
+        contract.ft_transfer(accounts(1), transfer_amount.into(), None);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .account_balance(env::account_balance())
+            .is_view(true)
+            .attached_deposit(NearToken::from_yoctonear(0))
+            .build());
+        assert_eq!(
+            contract.ft_balance_of(accounts(2)).0,
+            (TOTAL_SUPPLY - transfer_amount)
+        );
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, transfer_amount);
+    }
+
+    #[test]
+    fn test_burn_reduces_supply_by_default() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        assert_eq!(contract.get_burn_strategy(), BurnStrategy::ReduceSupply);
+
+        contract.burn_tokens(&accounts(2), U128::from(1_000));
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY - 1_000);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, TOTAL_SUPPLY - 1_000);
+    }
+
+    #[test]
+    fn test_burn_to_dead_account_keeps_supply() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_burn_strategy(BurnStrategy::DeadAccount(accounts(4)));
+
+        contract.burn_tokens(&accounts(2), U128::from(1_000));
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, TOTAL_SUPPLY - 1_000);
+        assert_eq!(contract.ft_balance_of(accounts(4)).0, 1_000);
+    }
+
+    #[test]
+    #[should_panic(expected = "Dead account must not hold tokens")]
+    fn test_dead_account_must_not_hold_tokens() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        transfer_third(&mut contract, &mut context);
+
+        contract.set_burn_strategy(BurnStrategy::DeadAccount(accounts(1)));
+    }
+
+    #[test]
+    fn test_supply_history_records_mints_and_burns() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.block_timestamp(100).build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+
+        testing_env!(context.block_timestamp(200).build());
+        contract.mint_tokens(&accounts(2), U128::from(500));
+        testing_env!(context.block_timestamp(300).build());
+        contract.burn_tokens(&accounts(2), U128::from(200));
+
+        assert_eq!(
+            contract.get_supply_history(0, 10),
+            vec![
+                (U64::from(100), U128::from(TOTAL_SUPPLY)),
+                (U64::from(200), U128::from(TOTAL_SUPPLY + 500)),
+                (U64::from(300), U128::from(TOTAL_SUPPLY + 300)),
+            ]
+        );
+        assert_eq!(
+            contract.get_supply_history(1, 1),
+            vec![(U64::from(200), U128::from(TOTAL_SUPPLY + 500))]
+        );
+    }
+
+    #[test]
+    fn test_supply_history_drops_oldest_when_full() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+
+        for timestamp in 1..=MAX_SUPPLY_HISTORY as u64 {
+            testing_env!(context.block_timestamp(timestamp).build());
+            contract.mint_tokens(&accounts(2), U128::from(1));
+        }
+
+        let history = contract.get_supply_history(0, MAX_SUPPLY_HISTORY as u64 + 1);
+        assert_eq!(history.len(), MAX_SUPPLY_HISTORY);
+        assert_eq!(history[0], (U64::from(1), U128::from(TOTAL_SUPPLY + 1)));
+        assert!(history.windows(2).all(|pair| pair[0].0 .0 < pair[1].0 .0));
+    }
+
+    // accounts(2) owns the supply and transfers a third of it to the
+    // registered accounts(1)
+    #[test]
+    #[should_panic(expected = "Receiver bob not registered; call storage_deposit first")]
+    fn test_safe_transfer_to_unregistered_receiver() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.safe_ft_transfer(accounts(1), (TOTAL_SUPPLY / 3).into(), None);
+    }
+
+    #[test]
+    fn test_transfer_burn_tax() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_transfer_burn_bps(50);
+
+        // Transfers from the owner are not taxed
+        let transfer_amount = transfer_third(&mut contract, &mut context);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, transfer_amount);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.storage_balance_bounds().min)
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.storage_deposit(None, None);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .predecessor_account_id(accounts(1))
+            .signer_account_id(accounts(1))
+            .build());
+        contract.ft_transfer(accounts(3), 10_000.into(), None);
+
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 9_950);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, transfer_amount - 10_000);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY - 50);
+    }
+
+    #[test]
+    #[should_panic(expected = "Transfer burn must be at most 10000 bps")]
+    fn test_transfer_burn_bps_bounded() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_transfer_burn_bps(MAX_TRANSFER_BURN_BPS + 1);
+    }
+
+    // Owner sends a third to accounts(1), which then sends `amount` on to
+    // the freshly registered accounts(3)
+    fn transfer_on_from_holder(
+        contract: &mut MalbornClubContract,
+        context: &mut VMContextBuilder,
+        amount: Balance,
+    ) {
+        transfer_third(contract, context);
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.storage_balance_bounds().min)
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.storage_deposit(None, None);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .predecessor_account_id(accounts(1))
+            .signer_account_id(accounts(1))
+            .build());
+        contract.ft_transfer(accounts(3), amount.into(), None);
+    }
+
+    #[test]
+    fn test_min_transfer_amount_accepts_minimum() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_min_transfer_amount(Some(U128::from(1_000)));
+        assert_eq!(contract.get_min_transfer_amount(), Some(U128::from(1_000)));
+
+        // The owner's transfer in transfer_on_from_holder is exempt
+        transfer_on_from_holder(&mut contract, &mut context, 1_000);
+
+        assert_eq!(contract.malborn_token.ft_balance_of(accounts(3)).0, 1_000);
+    }
+
+    #[test]
+    #[should_panic(expected = "Transfer amount must be at least 1000")]
+    fn test_min_transfer_amount_rejects_below_minimum() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_min_transfer_amount(Some(U128::from(1_000)));
+
+        transfer_on_from_holder(&mut contract, &mut context, 999);
+    }
+
+    #[test]
+    fn test_is_owner() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        assert_eq!(contract.get_owner(), accounts(2));
+        assert!(contract.is_owner(accounts(2)));
+        assert!(!contract.is_owner(accounts(1)));
+
+        contract.set_owner(accounts(1));
+        assert!(contract.is_owner(accounts(1)));
+        assert!(!contract.is_owner(accounts(2)));
+    }
+
+    #[test]
+    fn test_safe_transfer() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.storage_balance_bounds().min)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.storage_deposit(None, None);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.safe_ft_transfer(accounts(1), (TOTAL_SUPPLY / 3).into(), None);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, TOTAL_SUPPLY / 3);
+    }
+
+    fn transfer_third(contract: &mut MalbornClubContract, context: &mut VMContextBuilder) -> Balance {
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.storage_balance_bounds().min)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.storage_deposit(None, None);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .predecessor_account_id(accounts(2))
+            .build());
+        let transfer_amount = TOTAL_SUPPLY / 3;
+        contract.ft_transfer(accounts(1), transfer_amount.into(), None);
+        transfer_amount
+    }
+
+    #[test]
+    fn test_transfer_hook_invoked() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_transfer_hook(Some(accounts(3)));
+
+        let transfer_amount = transfer_third(&mut contract, &mut context);
+
+        let receipts = get_created_receipts();
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].receiver_id, accounts(3));
+        match &receipts[0].actions[0] {
+            MockAction::FunctionCallWeight { method_name, args, .. } => {
+                assert_eq!(method_name, b"on_ft_transfer");
+                assert_eq!(
+                    near_sdk::serde_json::from_slice::<near_sdk::serde_json::Value>(args).unwrap(),
+                    json!({
+                        "sender_id": accounts(2),
+                        "receiver_id": accounts(1),
+                        "amount": U128::from(transfer_amount),
+                    })
+                );
+            }
+            action => panic!("Unexpected action {:?}", action),
+        }
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, transfer_amount);
+        assert_eq!(
+            contract.ft_balance_of(accounts(2)).0,
+            TOTAL_SUPPLY - transfer_amount
+        );
+    }
+
+    #[test]
+    fn test_max_balance_allows_transfer_up_to_cap() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_max_balance(Some(U128::from(TOTAL_SUPPLY / 3)));
+        assert_eq!(contract.get_max_balance(), Some(U128::from(TOTAL_SUPPLY / 3)));
+
+        let transfer_amount = transfer_third(&mut contract, &mut context);
+
+        assert_eq!(contract.malborn_token.ft_balance_of(accounts(1)).0, transfer_amount);
+    }
+
+    #[test]
+    #[should_panic(expected = "would exceed the maximum of")]
+    fn test_max_balance_rejects_transfer_over_cap() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_max_balance(Some(U128::from(TOTAL_SUPPLY / 3 - 1)));
+
+        transfer_third(&mut contract, &mut context);
+    }
+
+    #[test]
+    fn test_max_balance_exempts_treasury() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_treasury_account(Some(accounts(1)));
+        contract.set_max_balance(Some(U128::from(100)));
+
+        let transfer_amount = transfer_third(&mut contract, &mut context);
+
+        assert_eq!(contract.malborn_token.ft_balance_of(accounts(1)).0, transfer_amount);
+    }
+
+    #[test]
+    #[should_panic(expected = "would exceed the maximum of")]
+    fn test_max_balance_rejects_mint_over_cap() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.malborn_token.internal_register_account(&accounts(3));
+        contract.set_max_balance(Some(U128::from(100)));
+
+        // The owner is exempt
+        contract.mint_tokens(&accounts(2), U128::from(1000));
+        contract.mint_tokens(&accounts(3), U128::from(101));
+    }
+
+    #[test]
+    fn test_transfer_hook_disabled() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_transfer_hook(Some(accounts(3)));
+        contract.set_transfer_hook(None);
+
+        let transfer_amount = transfer_third(&mut contract, &mut context);
+
+        assert!(get_created_receipts().is_empty());
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, transfer_amount);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_pause() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into(), None, None);
+
+        let symbol = contract.get_symbol();
+        assert_eq!(symbol, "MAL".to_string());
+        contract.pause();
+        contract.get_symbol();
+    }
+
+    #[test]
+    fn test_owner_exempt_from_pause_transfers_while_paused() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.malborn_token.internal_register_account(&accounts(1));
+        contract.set_owner_exempt_from_pause(true);
+        contract.pause();
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.ft_transfer(accounts(1), 100.into(), None);
+
+        assert_eq!(contract.malborn_token.ft_balance_of(accounts(1)).0, 100);
+        assert_eq!(contract.effective_status(), ContractStatus::Paused);
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract is paused")]
+    fn test_owner_exempt_from_pause_still_blocks_others() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.malborn_token.internal_register_account(&accounts(1));
+        contract
+            .malborn_token
+            .internal_transfer(&accounts(2), &accounts(1), 100, None);
+        contract.set_owner_exempt_from_pause(true);
+        contract.pause();
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .signer_account_id(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.ft_transfer(accounts(2), 100.into(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract is paused")]
+    fn test_owner_exemption_does_not_cover_contracts_the_owner_calls() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.malborn_token.internal_register_account(&accounts(1));
+        contract
+            .malborn_token
+            .internal_transfer(&accounts(2), &accounts(1), 100, None);
+        contract.set_owner_exempt_from_pause(true);
+        contract.pause();
+
+        // accounts(1) is a contract called in a transaction the owner signed
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.ft_transfer(accounts(2), 100.into(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract is paused")]
+    fn test_owner_transfer_blocked_while_paused_without_exemption() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.malborn_token.internal_register_account(&accounts(1));
+        contract.pause();
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.ft_transfer(accounts(1), 100.into(), None);
+    }
+
+    #[test]
+    fn test_blocklist() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into(), None, None);
+        assert_eq!(
+            contract.get_blocklist_status(&accounts(1)),
+            BlocklistStatus::Allowed
+        );
+
+        contract.add_to_blocklist(&accounts(1));
+        assert_eq!(
+            contract.get_blocklist_status(&accounts(1)),
+            BlocklistStatus::Banned
+        );
+
+        contract.remove_from_blocklist(&accounts(1));
+        assert_eq!(
+            contract.get_blocklist_status(&accounts(1)),
+            BlocklistStatus::Allowed
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_blocklist2() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into(), None, None);
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(1))
+            .build());
+        //Paying for account registration -- storage deposit
+        contract.storage_deposit(None, None);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .predecessor_account_id(accounts(2))
+            .build());
+        let transfer_amount = TOTAL_SUPPLY / 3;
+        contract.ft_transfer(accounts(1), transfer_amount.into(), None);
+
+        assert_eq!(
+            contract.get_blocklist_status(&accounts(1)),
+            BlocklistStatus::Allowed
+        );
+
+        contract.add_to_blocklist(&accounts(1));
+        assert_eq!(
+            contract.get_blocklist_status(&accounts(1)),
+            BlocklistStatus::Banned
+        );
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .signer_account_id(accounts(1))
+            .build());
+
+        contract.ft_transfer(accounts(2), U128::from(transfer_amount / 2), None);
+    }
+
+    #[test]
+    fn test_ft_balances_of() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.malborn_token.internal_register_account(&accounts(3));
+        contract
+            .malborn_token
+            .internal_transfer(&accounts(2), &accounts(3), 40, None);
+
+        assert_eq!(
+            contract.ft_balances_of(vec![accounts(3), accounts(4), accounts(2)]),
+            vec![U128::from(40), U128::from(0), U128::from(TOTAL_SUPPLY - 40)]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "At most 100 accounts per query")]
+    fn test_ft_balances_of_too_many_accounts() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.ft_balances_of(vec![accounts(3); MAX_BALANCES_QUERY + 1]);
+    }
+
+    #[test]
+    fn test_get_account_info() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.storage_balance_bounds().min)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.storage_deposit(None, None);
+        contract.add_to_blocklist(&accounts(1));
+
+        testing_env!(context.is_view(true).build());
+        assert_eq!(
+            contract.get_account_info(accounts(2)),
+            AccountInfo {
+                balance: U128::from(TOTAL_SUPPLY),
+                status: BlocklistStatus::Allowed,
+                is_registered: true,
+            }
+        );
+        assert_eq!(
+            contract.get_account_info(accounts(1)),
+            AccountInfo {
+                balance: U128::from(0),
+                status: BlocklistStatus::Banned,
+                is_registered: true,
+            }
+        );
+        assert_eq!(
+            contract.get_account_info(accounts(3)),
+            AccountInfo {
+                balance: U128::from(0),
+                status: BlocklistStatus::Allowed,
+                is_registered: false,
+            }
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Registration already in flight")]
+    fn test_register_for_event_rejects_while_in_flight() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_associated_contract(accounts(3));
+
+        contract.register_for_event(U128::from(1));
+        // The associated contract hasn't answered yet, so the lock is still held
+        testing_env!(context.build());
+        contract.register_for_event(U128::from(1));
+    }
+
+    #[test]
+    fn test_register_for_event_callback_releases_lock() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_associated_contract(accounts(3));
+        contract.register_for_event(U128::from(1));
+
+        resolve_registration(
+            &mut context,
+            &mut contract,
+            accounts(2),
+            1,
+            TOTAL_SUPPLY / 10000,
+            PromiseResult::Successful(vec![]),
+        );
+
+        contract.register_for_event(U128::from(1));
+    }
+
+    fn resolve_registration(
+        context: &mut VMContextBuilder,
+        contract: &mut MalbornClubContract,
+        account_id: AccountId,
+        event_id: u128,
+        burn_amount: Balance,
+        result: PromiseResult,
+    ) {
+        testing_env!(
+            context.predecessor_account_id(accounts(0)).build(),
+            test_vm_config(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![result],
+        );
+        contract.on_register_for_event(account_id.clone(), U128::from(event_id), U128::from(burn_amount));
+        testing_env!(context.predecessor_account_id(account_id).build());
+    }
+
+    #[test]
+    fn test_assert_min_gas_with_enough_gas() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.prepaid_gas(Gas::from_tgas(10)).build());
+        gas::assert_min_gas(Gas::from_tgas(5));
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough gas, attach at least")]
+    fn test_assert_min_gas_with_too_little_gas() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.prepaid_gas(Gas::from_tgas(10)).build());
+        gas::assert_min_gas(Gas::from_tgas(15));
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough gas")]
+    fn test_register_for_event_rejects_low_gas() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_associated_contract(accounts(3));
+
+        testing_env!(context.prepaid_gas(Gas::from_tgas(30)).build());
+        contract.register_for_event(U128::from(1));
+    }
+
+    #[test]
+    fn test_register_for_event_with_enough_gas() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_associated_contract(accounts(3));
+
+        testing_env!(context.prepaid_gas(Gas::from_tgas(40)).build());
+        contract.register_for_event(U128::from(1));
+        assert_eq!(
+            contract.malborn_token.ft_total_supply().0,
+            TOTAL_SUPPLY - TOTAL_SUPPLY / 10000
+        );
+    }
+
+    #[test]
+    fn test_register_for_event_cooldown() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_associated_contract(accounts(3));
+        contract.set_registration_cooldown(U64::from(1_000));
+
+        testing_env!(context.block_timestamp(100).build());
+        contract.register_for_event(U128::from(1));
+        resolve_registration(
+            &mut context,
+            &mut contract,
+            accounts(2),
+            1,
+            TOTAL_SUPPLY / 10000,
+            PromiseResult::Successful(vec![]),
+        );
+
+        testing_env!(context.block_timestamp(1_100).build());
+        contract.register_for_event(U128::from(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "Registration cooldown active")]
+    fn test_register_for_event_rejected_within_cooldown() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_associated_contract(accounts(3));
+        contract.set_registration_cooldown(U64::from(1_000));
+
+        testing_env!(context.block_timestamp(100).build());
+        contract.register_for_event(U128::from(1));
+        resolve_registration(
+            &mut context,
+            &mut contract,
+            accounts(2),
+            1,
+            TOTAL_SUPPLY / 10000,
+            PromiseResult::Successful(vec![]),
+        );
+
+        testing_env!(context.block_timestamp(1_099).build());
+        contract.register_for_event(U128::from(2));
+    }
+
+    #[test]
+    fn test_failed_registration_does_not_start_cooldown() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_associated_contract(accounts(3));
+        contract.set_registration_cooldown(U64::from(1_000));
+
+        contract.register_for_event(U128::from(1));
+        resolve_registration(
+            &mut context,
+            &mut contract,
+            accounts(2),
+            1,
+            TOTAL_SUPPLY / 10000,
+            PromiseResult::Failed,
+        );
+
+        contract.register_for_event(U128::from(1));
+    }
+
+    #[test]
+    fn test_registration_stats_count_successful_registrations() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_associated_contract(accounts(3));
+        let burn_amount = TOTAL_SUPPLY / 10000;
+
+        contract.register_for_event(U128::from(1));
+        resolve_registration(
+            &mut context,
+            &mut contract,
+            accounts(2),
+            1,
+            burn_amount,
+            PromiseResult::Successful(vec![]),
+        );
+
+        contract.register_for_event(U128::from(2));
+        resolve_registration(
+            &mut context,
+            &mut contract,
+            accounts(2),
+            2,
+            burn_amount,
+            PromiseResult::Failed,
+        );
+
+        assert_eq!(
+            contract.get_registration_stats(),
+            RegistrationStats {
+                total_registrations: U64::from(1),
+                total_registration_burn: U128::from(burn_amount),
+            }
+        );
+    }
+
+    #[test]
+    fn test_register_for_allowed_event() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_associated_contract(accounts(3));
+        contract.allow_event(U128::from(1));
+        contract.allow_event(U128::from(2));
+        contract.disallow_event(U128::from(2));
+        assert_eq!(contract.get_allowed_events(), vec![U128::from(1)]);
+
+        contract.register_for_event(U128::from(1));
+
+        assert_eq!(
+            contract.malborn_token.ft_total_supply().0,
+            TOTAL_SUPPLY - TOTAL_SUPPLY / 10000
+        );
+        assert_eq!(get_created_receipts()[0].receiver_id, accounts(3));
+    }
+
+    #[test]
+    fn test_registration_fee_split_with_treasury() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_associated_contract(accounts(3));
+        assert_eq!(contract.get_registration_burn_split(), MAX_REGISTRATION_BURN_SPLIT_BPS);
+        contract.set_treasury_account(Some(accounts(4)));
+        contract.set_registration_burn_split(5_000);
+
+        contract.register_for_event(U128::from(1));
+
+        let fee = TOTAL_SUPPLY / 10000;
+        assert_eq!(contract.malborn_token.ft_total_supply().0, TOTAL_SUPPLY - fee / 2);
+        assert_eq!(contract.malborn_token.ft_balance_of(accounts(4)).0, fee / 2);
+        assert_eq!(contract.malborn_token.ft_balance_of(accounts(2)).0, TOTAL_SUPPLY - fee);
+    }
+
+    #[test]
+    fn test_eligible_events() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_associated_contract(accounts(3));
+        for event_id in 1..=3 {
+            contract.allow_event(U128::from(event_id));
+        }
+
+        contract.register_for_event(U128::from(2));
+        resolve_registration(
+            &mut context,
+            &mut contract,
+            accounts(2),
+            2,
+            TOTAL_SUPPLY / 10000,
+            PromiseResult::Successful(vec![]),
+        );
+
+        assert_eq!(
+            contract.eligible_events(accounts(2), 0, 10),
+            vec![U128::from(1), U128::from(3)]
+        );
+        assert_eq!(contract.eligible_events(accounts(2), 2, 10), vec![U128::from(3)]);
+        // accounts(1) holds nothing, so can't afford the fee
+        assert!(contract.eligible_events(accounts(1), 0, 10).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Event 2 is not allowed")]
+    fn test_register_for_disallowed_event() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_associated_contract(accounts(3));
+        contract.allow_event(U128::from(1));
+
+        // Rejected before anything is burned
+        contract.register_for_event(U128::from(2));
+    }
+
+    #[test]
+    fn test_register_for_event_uses_register_gas() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_associated_contract(accounts(3));
+        assert_eq!(contract.get_register_gas(), GAS_FOR_REGISTER);
+
+        let gas = Gas::from_tgas(50);
+        contract.set_register_gas(gas);
+        assert_eq!(contract.get_register_gas(), gas);
+
+        contract.register_for_event(U128::from(0));
+        let receipts = get_created_receipts();
+        match &receipts[0].actions[0] {
+            MockAction::FunctionCallWeight { method_name, prepaid_gas, .. } => {
+                assert_eq!(method_name, b"register_for_an_event");
+                assert_eq!(*prepaid_gas, gas);
+            }
+            action => panic!("Unexpected action {:?}", action),
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Register gas must be between")]
+    fn test_set_register_gas_too_high() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_register_gas(MAX_REGISTER_GAS.saturating_add(Gas::from_gas(1)));
+    }
+
+    #[test]
+    fn test_refresh_event_count_populates_cache() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_associated_contract(accounts(3));
+        assert_eq!(contract.get_cached_event_count(), None);
+
+        contract.refresh_event_count();
+        let receipts = get_created_receipts();
+        assert_eq!(receipts[0].receiver_id, accounts(3));
+        match &receipts[0].actions[0] {
+            MockAction::FunctionCallWeight { method_name, .. } => {
+                assert_eq!(method_name, b"get_next_event_idx")
+            }
+            action => panic!("Unexpected action {:?}", action),
+        }
+
+        // The associated contract answers with 3 events
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.on_event_count(Ok(3));
+        assert_eq!(contract.get_cached_event_count(), Some(U64::from(3)));
+
+        // A failed refresh keeps the last known count
+        contract.on_event_count(Err(PromiseError::Failed));
+        assert_eq!(contract.get_cached_event_count(), Some(U64::from(3)));
+    }
+
+    #[test]
+    fn test_query_associated_gas_caches_recommendation() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_associated_contract(accounts(3));
+        assert_eq!(contract.get_recommended_register_gas(), None);
+
+        contract.query_associated_gas();
+        let receipts = get_created_receipts();
+        assert_eq!(receipts[0].receiver_id, accounts(3));
+        match &receipts[0].actions[0] {
+            MockAction::FunctionCallWeight { method_name, .. } => {
+                assert_eq!(method_name, b"get_recommended_register_gas")
+            }
+            action => panic!("Unexpected action {:?}", action),
+        }
+
+        // The associated contract advertises 30 Tgas
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.on_associated_gas(Ok(Gas::from_tgas(30)));
+        assert_eq!(contract.get_recommended_register_gas(), Some(Gas::from_tgas(30)));
+
+        // A failed query keeps the last known value
+        contract.on_associated_gas(Err(PromiseError::Failed));
+        assert_eq!(contract.get_recommended_register_gas(), Some(Gas::from_tgas(30)));
+    }
+
+    #[test]
+    fn test_effective_status_after_pause_until() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.block_timestamp(100).build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+
+        contract.pause_until(U64::from(200));
+        assert_eq!(contract.effective_status(), ContractStatus::Paused);
+
+        testing_env!(context.block_timestamp(200).build());
+        assert_eq!(contract.effective_status(), ContractStatus::Working);
+        // Nothing has been written yet, only the view reflects the deadline
+        assert_eq!(contract.contract_status(), ContractStatus::Paused);
+        assert_eq!(contract.get_symbol(), "MAL".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract is paused")]
+    fn test_pause_until_blocks_before_deadline() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.block_timestamp(100).build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+
+        contract.pause_until(U64::from(200));
+        testing_env!(context.block_timestamp(199).build());
+        contract.get_symbol();
+    }
+
+    #[test]
+    fn test_upgrade_token_name_symbol() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        testing_env!(context.build());
+
+        contract.upgrade_token_name_symbol("Malborn Club".to_string(), "MBC".to_string());
+
+        let metadata = contract.ft_metadata();
+        assert_eq!(metadata.name, "Malborn Club");
+        assert_eq!(metadata.symbol, "MBC");
+        assert_eq!(
+            get_logs(),
+            vec![format!(
+                "EVENT_JSON:{}",
+                json!({
+                    "standard": events::EVENT_STANDARD,
+                    "version": events::EVENT_VERSION,
+                    "event": "metadata_updated",
+                    "data": [{
+                        "old_name": "Malborn Token",
+                        "old_symbol": "MAL",
+                        "new_name": "Malborn Club",
+                        "new_symbol": "MBC",
+                    }],
+                })
+            )]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Name must not be empty")]
+    fn test_upgrade_token_name_symbol_empty_name() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.upgrade_token_name_symbol(String::new(), "MAL".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Symbol must not be empty")]
+    fn test_upgrade_token_name_symbol_empty_symbol() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.upgrade_token_name_symbol("Malborn".to_string(), String::new());
+    }
+
+    #[test]
+    #[should_panic(expected = "Name must be at most 64 bytes")]
+    fn test_upgrade_token_name_symbol_name_too_long() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.upgrade_token_name_symbol("M".repeat(MAX_NAME_LEN + 1), "MAL".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Symbol must be at most 12 bytes")]
+    fn test_upgrade_token_name_symbol_symbol_too_long() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.upgrade_token_name_symbol("Malborn".to_string(), "M".repeat(MAX_SYMBOL_LEN + 1));
+    }
+
+    // 1000 tokens for accounts(3), starting at 100 with a cliff at 300 and
+    // fully unlocked at 1100
+    fn setup_vesting(context: &mut VMContextBuilder) -> MalbornClubContract {
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.malborn_token.internal_register_account(&accounts(3));
+        contract.create_vesting(
+            accounts(3),
+            U128::from(1000),
+            U64::from(100),
+            U64::from(200),
+            U64::from(1000),
+        );
+        contract
+    }
+
+    #[test]
+    fn test_vested_amount_follows_schedule() {
+        let mut context = get_context(accounts(2), accounts(2));
+        let contract = setup_vesting(&mut context);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, TOTAL_SUPPLY - 1000);
+
+        testing_env!(context.block_timestamp(250).is_view(true).build());
+        assert_eq!(contract.vested_amount(accounts(3)).0, 0);
+        assert_eq!(contract.claimable_amount(accounts(3)).0, 0);
+
+        testing_env!(context.block_timestamp(600).build());
+        assert_eq!(contract.vested_amount(accounts(3)).0, 500);
+        assert_eq!(contract.claimable_amount(accounts(3)).0, 500);
+
+        testing_env!(context.block_timestamp(1200).build());
+        assert_eq!(contract.vested_amount(accounts(3)).0, 1000);
+        assert_eq!(contract.claimable_amount(accounts(3)).0, 1000);
+
+        assert_eq!(contract.vested_amount(accounts(4)).0, 0);
+    }
+
+    #[test]
+    fn test_vested_amount_with_24_decimals_over_a_year() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let one_token = 10u128.pow(24);
+        let mut contract = MalbornClubContract::new(
+            accounts(2),
+            U128::from(1_000_000_000 * one_token),
+            Some(24),
+            None,
+        );
+        contract.malborn_token.internal_register_account(&accounts(3));
+        let year = 365 * 24 * 60 * 60 * 1_000_000_000u64;
+        let total = 1_000 * one_token;
+        contract.create_vesting(
+            accounts(3),
+            U128::from(total),
+            U64::from(0),
+            U64::from(0),
+            U64::from(year),
+        );
+
+        testing_env!(context.block_timestamp(year / 2).is_view(true).build());
+        assert_eq!(contract.vested_amount(accounts(3)).0, total / 2);
+        assert_eq!(contract.claimable_amount(accounts(3)).0, total / 2);
+    }
+
+    #[test]
+    fn test_claim_vested_subtracts_claimed() {
+        let mut context = get_context(accounts(2), accounts(2));
+        let mut contract = setup_vesting(&mut context);
+
+        testing_env!(context.predecessor_account_id(accounts(3)).block_timestamp(600).build());
+        assert_eq!(contract.claim_vested().0, 500);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 500);
+        assert_eq!(contract.vested_amount(accounts(3)).0, 500);
+        assert_eq!(contract.claimable_amount(accounts(3)).0, 0);
+
+        testing_env!(context.block_timestamp(1200).build());
+        assert_eq!(contract.claimable_amount(accounts(3)).0, 500);
+        assert_eq!(contract.claim_vested().0, 500);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 1000);
+    }
+
+    #[test]
+    fn test_vesting_keeps_total_supply() {
+        let mut context = get_context(accounts(2), accounts(2));
+        let mut contract = setup_vesting(&mut context);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY);
+        assert_eq!(contract.ft_balance_of(env::current_account_id()).0, 1000);
+
+        testing_env!(context.predecessor_account_id(accounts(3)).block_timestamp(1200).build());
+        contract.claim_vested();
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY);
+        assert_eq!(contract.ft_balance_of(env::current_account_id()).0, 0);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 1000);
+    }
+
+    #[test]
+    #[should_panic(expected = "would exceed the maximum of")]
+    fn test_claim_vested_respects_max_balance() {
+        let mut context = get_context(accounts(2), accounts(2));
+        let mut contract = setup_vesting(&mut context);
+        contract.set_max_balance(Some(U128::from(999)));
+
+        testing_env!(context.predecessor_account_id(accounts(3)).block_timestamp(1200).build());
+        contract.claim_vested();
+    }
+
+    #[test]
+    #[should_panic(expected = "Cliff must not exceed duration")]
+    fn test_create_vesting_cliff_after_end() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.create_vesting(
+            accounts(3),
+            U128::from(1000),
+            U64::from(0),
+            U64::from(2000),
+            U64::from(1000),
+        );
+    }
+
+    #[test]
+    fn test_storage_deposit_reserves_approvals() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        let bounds = contract.storage_balance_bounds();
+        assert_eq!(
+            bounds.min.as_yoctonear(),
+            contract.malborn_token.storage_balance_bounds().min.as_yoctonear()
+                + MalbornClubContract::approval_storage_cost(DEFAULT_MAX_APPROVALS)
+        );
+
+        testing_env!(context
+            .attached_deposit(bounds.min)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.storage_deposit(None, None);
+        assert_eq!(contract.storage_balance_of(accounts(1)).unwrap().total, bounds.min);
+
+        testing_env!(context.attached_deposit(NearToken::from_yoctonear(0)).build());
+        for index in 0..DEFAULT_MAX_APPROVALS {
+            let spender_id: AccountId = format!("spender{}.near", index).parse().unwrap();
+            contract.ft_approve(spender_id.clone(), U128::from(100));
+            assert_eq!(contract.ft_allowance(accounts(1), spender_id).0, 100);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient storage deposit for 1 approvals")]
+    fn test_ft_approve_without_approval_storage() {
+        // The owner is registered by `new` without reserving approval storage
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.ft_approve(accounts(3), U128::from(100));
+    }
+
+    #[test]
+    fn test_ft_approve_after_storage_top_up() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(
+                MalbornClubContract::approval_storage_cost(1)
+            ))
+            .build());
+        contract.storage_deposit(None, None);
+
+        testing_env!(context.attached_deposit(NearToken::from_yoctonear(0)).build());
+        contract.ft_approve(accounts(3), U128::from(100));
+        assert_eq!(contract.ft_allowance(accounts(2), accounts(3)).0, 100);
+
+        contract.ft_approve(accounts(3), U128::from(0));
+        assert_eq!(contract.ft_allowance(accounts(2), accounts(3)).0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "At most 4 approvals per account")]
+    fn test_ft_approve_over_limit() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.storage_deposit(None, None);
+
+        testing_env!(context.attached_deposit(NearToken::from_yoctonear(0)).build());
+        for index in 0..=DEFAULT_MAX_APPROVALS {
+            let spender_id: AccountId = format!("spender{}.near", index).parse().unwrap();
+            contract.ft_approve(spender_id, U128::from(100));
+        }
+    }
+
+    // Sum of NEAR transferred to `account_id` by the last call
+    fn transferred_to(account_id: &AccountId) -> u128 {
+        get_created_receipts()
+            .iter()
+            .filter(|receipt| &receipt.receiver_id == account_id)
+            .flat_map(|receipt| receipt.actions.iter())
+            .map(|action| match action {
+                MockAction::Transfer { deposit, .. } => deposit.as_yoctonear(),
+                _ => 0,
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_mint_fee_paid_to_owner() {
+        // Called by accounts(3) on behalf of the owner accounts(2)
+        let mut context = get_context(accounts(3), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_mint_fee(Some(NearToken::from_near(1)));
+        assert_eq!(contract.get_mint_fee(), Some(NearToken::from_near(1)));
+
+        testing_env!(context.attached_deposit(NearToken::from_millinear(1500)).build());
+        contract.mint_tokens(&accounts(2), U128::from(10));
+
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, TOTAL_SUPPLY + 10);
+        assert_eq!(transferred_to(&accounts(2)), NearToken::from_near(1).as_yoctonear());
+        assert_eq!(transferred_to(&accounts(3)), NearToken::from_millinear(500).as_yoctonear());
+    }
+
+    #[test]
+    #[should_panic(expected = "Attached deposit must cover the mint fee")]
+    fn test_mint_underfunded_fee() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_mint_fee(Some(NearToken::from_near(1)));
+
+        testing_env!(context.attached_deposit(NearToken::from_millinear(999)).build());
+        contract.mint_tokens(&accounts(2), U128::from(10));
+    }
+
+    #[test]
+    #[should_panic(expected = "Attached deposit must cover the mint fee")]
+    fn test_mint_batch_pays_fee_per_entry() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_mint_fee(Some(NearToken::from_near(1)));
+
+        testing_env!(context.attached_deposit(NearToken::from_near(1)).build());
+        contract.mint_tokens_batch(vec![
+            (accounts(2), U128::from(10)),
+            (accounts(2), U128::from(10)),
+        ]);
+    }
+
+    #[test]
+    fn test_unban_and_compensate() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.malborn_token.internal_register_account(&accounts(3));
+        contract.add_to_blocklist(&accounts(3));
+
+        contract.unban_and_compensate(accounts(3), U128::from(25));
+
+        assert_eq!(
+            contract.get_blocklist_status(&accounts(3)),
+            BlocklistStatus::Allowed
+        );
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 25);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, TOTAL_SUPPLY - 25);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY);
+        assert!(get_logs().contains(&format!(
+            "EVENT_JSON:{}",
+            json!({
+                "standard": events::EVENT_STANDARD,
+                "version": events::EVENT_VERSION,
+                "event": "account_compensated",
+                "data": [{ "account_id": accounts(3), "amount": "25" }],
+            })
+        )));
+
+        // The account can transfer again
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .predecessor_account_id(accounts(3))
+            .signer_account_id(accounts(3))
+            .build());
+        contract.ft_transfer(accounts(2), U128::from(5), None);
+    }
+
+    #[test]
+    fn test_unban_and_compensate_zero_amount_only_unbans() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.malborn_token.internal_register_account(&accounts(3));
+        contract.add_to_blocklist(&accounts(3));
+
+        contract.unban_and_compensate(accounts(3), U128::from(0));
+        assert_eq!(
+            contract.get_blocklist_status(&accounts(3)),
+            BlocklistStatus::Allowed
+        );
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 0);
+        assert!(!get_logs().iter().any(|log| log.contains("account_compensated")));
+    }
+
+    #[test]
+    #[should_panic(expected = "would exceed the maximum of")]
+    fn test_unban_and_compensate_respects_max_balance() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.malborn_token.internal_register_account(&accounts(3));
+        contract.add_to_blocklist(&accounts(3));
+        contract.set_max_balance(Some(U128::from(24)));
+
+        contract.unban_and_compensate(accounts(3), U128::from(25));
+    }
+
+    #[test]
+    #[should_panic(expected = "Account is not banned")]
+    fn test_unban_and_compensate_not_banned() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.malborn_token.internal_register_account(&accounts(3));
+        contract.unban_and_compensate(accounts(3), U128::from(25));
+    }
+
+    #[test]
+    fn test_mint_tokens_batch() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.malborn_token.internal_register_account(&accounts(3));
+
+        let total_supply = contract.mint_tokens_batch(vec![
+            (accounts(2), U128::from(10)),
+            (accounts(3), U128::from(20)),
+        ]);
+
+        testing_env!(context.is_view(true).build());
+        assert_eq!(total_supply, TOTAL_SUPPLY + 30);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, TOTAL_SUPPLY + 10);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 20);
+    }
+
+    #[test]
+    fn test_mint_to_allowed_account_with_blocklist_enforced() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.malborn_token.internal_register_account(&accounts(3));
+        contract.add_to_blocklist(&accounts(3));
+        contract.remove_from_blocklist(&accounts(3));
+        assert!(contract.is_blocklist_enforced_on_mint());
+
+        contract.mint_tokens(&accounts(3), U128::from(10));
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "User is banned")]
+    fn test_mint_tokens_batch_rejects_banned_account() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.malborn_token.internal_register_account(&accounts(3));
+        contract.add_to_blocklist(&accounts(3));
+
+        contract.mint_tokens_batch(vec![
+            (accounts(2), U128::from(10)),
+            (accounts(3), U128::from(20)),
+        ]);
+    }
+
+    #[test]
+    fn test_mint_needs_two_of_three_approvals() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.malborn_token.internal_register_account(&accounts(1));
+        contract.set_approvers(vec![accounts(3), accounts(4), accounts(5)], 2);
+
+        testing_env!(context.signer_account_id(accounts(3)).build());
+        let proposal_id = contract.propose_action(SensitiveAction::Mint {
+            account_id: accounts(1),
+            amount: U128::from(10),
+        });
+        testing_env!(context.signer_account_id(accounts(5)).build());
+        contract.approve_action(proposal_id);
+        contract.execute_action(proposal_id);
+
+        assert_eq!(contract.malborn_token.accounts.get(&accounts(1)), Some(10));
+        assert_eq!(contract.malborn_token.ft_total_supply().0, TOTAL_SUPPLY + 10);
+        assert_eq!(contract.get_proposal(proposal_id), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough approvals: 1 of 2")]
+    fn test_execute_action_with_too_few_approvals() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_approvers(vec![accounts(3), accounts(4), accounts(5)], 2);
+
+        testing_env!(context.signer_account_id(accounts(3)).build());
+        let proposal_id = contract.propose_action(SensitiveAction::Mint {
+            account_id: accounts(2),
+            amount: U128::from(10),
+        });
+        contract.execute_action(proposal_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires approval, use propose_action")]
+    fn test_mint_tokens_with_approvers_configured() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_approvers(vec![accounts(3), accounts(4)], 1);
+
+        contract.mint_tokens(&accounts(2), U128::from(10));
+    }
+
+    #[test]
+    fn test_get_roles() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        assert!(contract.get_roles(accounts(3)).is_empty());
+
+        // One role per account, so the list holds at most one entry
+        contract.grant_role(accounts(3), Role::Minter);
+        assert_eq!(contract.get_roles(accounts(3)), vec![Role::Minter]);
+
+        contract.revoke_role(accounts(3));
+        assert!(contract.get_roles(accounts(3)).is_empty());
+    }
+
+    #[test]
+    fn test_get_accounts_with_role() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+
+        contract.grant_role(accounts(3), Role::Minter);
+        contract.grant_role(accounts(4), Role::Minter);
+        assert_eq!(
+            contract.get_accounts_with_role(Role::Minter, 0, 10),
+            vec![accounts(3), accounts(4)]
+        );
+        assert_eq!(
+            contract.get_accounts_with_role(Role::Minter, 1, 10),
+            vec![accounts(4)]
+        );
+
+        contract.revoke_role(accounts(3));
+        assert_eq!(contract.get_role(accounts(3)), None);
+        assert_eq!(
+            contract.get_accounts_with_role(Role::Minter, 0, 10),
+            vec![accounts(4)]
+        );
+    }
+
+    #[test]
+    fn test_minter_role_can_mint() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.malborn_token.internal_register_account(&accounts(3));
+        contract.grant_role(accounts(3), Role::Minter);
+
+        testing_env!(context.signer_account_id(accounts(3)).build());
+        assert_eq!(contract.mint_tokens(&accounts(3), U128::from(10)), TOTAL_SUPPLY + 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "Can only be called by owner or a minter")]
+    fn test_mint_tokens_rejects_non_minter() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.malborn_token.internal_register_account(&accounts(3));
+
+        testing_env!(context.signer_account_id(accounts(3)).build());
+        contract.mint_tokens(&accounts(3), U128::from(10));
+    }
+
+    #[test]
+    fn test_mint_saturating_clamps_at_max_supply() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        testing_env!(context.build());
+        contract.set_max_supply(Some(U128::from(TOTAL_SUPPLY + 100)));
+
+        assert_eq!(contract.mint_saturating(&accounts(2), U128::from(60)).0, 60);
+        assert!(get_logs().is_empty());
+
+        assert_eq!(contract.mint_saturating(&accounts(2), U128::from(60)).0, 40);
+        assert_eq!(contract.malborn_token.ft_total_supply().0, TOTAL_SUPPLY + 100);
+        assert_eq!(
+            get_logs(),
+            vec![format!(
+                "EVENT_JSON:{}",
+                json!({
+                    "standard": events::EVENT_STANDARD,
+                    "version": events::EVENT_VERSION,
+                    "event": "mint_clamped",
+                    "data": [{
+                        "account_id": accounts(2),
+                        "requested": U128::from(60),
+                        "minted": U128::from(40),
+                    }],
+                })
+            )]
+        );
+
+        assert_eq!(contract.mint_saturating(&accounts(2), U128::from(1)).0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Mint would exceed the max supply of")]
+    fn test_mint_tokens_respects_max_supply() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_max_supply(Some(U128::from(TOTAL_SUPPLY + 100)));
+
+        contract.mint_tokens(&accounts(2), U128::from(101));
+    }
+
+    #[test]
+    fn test_mintable_remaining() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        assert_eq!(contract.mintable_remaining().0, Balance::MAX - TOTAL_SUPPLY);
+
+        contract.set_max_supply(Some(U128::from(TOTAL_SUPPLY + 100)));
+        assert_eq!(contract.mintable_remaining().0, 100);
+        contract.mint_tokens(&accounts(2), U128::from(60));
+        assert_eq!(contract.mintable_remaining().0, 40);
+        contract.mint_tokens(&accounts(2), U128::from(40));
+        assert_eq!(contract.mintable_remaining().0, 0);
+    }
+
+    #[test]
+    fn test_renounce_minting() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        testing_env!(context.build());
+        contract.malborn_token.internal_register_account(&accounts(3));
+
+        contract.renounce_minting();
+        assert!(contract.is_minting_renounced());
+        assert_eq!(
+            get_logs(),
+            vec![format!(
+                "EVENT_JSON:{}",
+                json!({
+                    "standard": events::EVENT_STANDARD,
+                    "version": events::EVENT_VERSION,
+                    "event": "minting_renounced",
+                    "data": [{ "owner_id": accounts(2) }],
+                })
+            )]
+        );
+
+        // Transfers and burns keep working
+        testing_env!(context.attached_deposit(NearToken::from_yoctonear(1)).build());
+        contract.ft_transfer(accounts(3), U128::from(100), None);
+        contract.burn_tokens(&accounts(3), U128::from(40));
+
+        testing_env!(context.is_view(true).build());
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 60);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY - 40);
     }
 
-    #[payable]
-    fn storage_unregister(&mut self, force: Option<bool>) -> bool {
-        #[allow(unused_variables)]
-        if let Some((account_id, balance)) = self.malborn_token.internal_storage_unregister(force) {
-            self.on_account_closed(account_id, balance);
-            true
-        } else {
-            false
-        }
+    #[test]
+    #[should_panic(expected = "Minting has been renounced")]
+    fn test_mint_tokens_after_renounce() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.renounce_minting();
+        contract.mint_tokens(&accounts(2), U128::from(10));
     }
 
-    fn storage_balance_bounds(&self) -> StorageBalanceBounds {
-        self.malborn_token.storage_balance_bounds()
+    #[test]
+    #[should_panic(expected = "Minting has been renounced")]
+    fn test_mint_tokens_batch_after_renounce() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.renounce_minting();
+        contract.mint_tokens_batch(vec![(accounts(2), U128::from(10))]);
     }
 
-    fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
-        self.malborn_token.storage_balance_of(account_id)
+    #[test]
+    #[should_panic(expected = "Minting has been renounced")]
+    fn test_renounce_minting_twice() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.renounce_minting();
+        contract.renounce_minting();
     }
-}
-
-#[cfg(all(test, not(target_arch = "wasm32")))]
-mod tests {
-    use super::*;
-    use near_contract_standards::fungible_token::Balance;
-    use near_sdk::test_utils::{accounts, VMContextBuilder};
-    use near_sdk::testing_env;
 
-    const TOTAL_SUPPLY: Balance = 1_000_000_000;
+    #[test]
+    fn test_can_transfer() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.malborn_token.internal_register_account(&accounts(3));
 
-    fn get_context(
-        predecessor_account_id: AccountId,
-        signer_account_id: AccountId,
-    ) -> VMContextBuilder {
-        let mut builder = VMContextBuilder::new();
-        builder
-            .current_account_id(accounts(0))
-            .signer_account_id(signer_account_id)
-            .predecessor_account_id(predecessor_account_id);
-        builder
+        testing_env!(context.is_view(true).build());
+        assert_eq!(
+            contract.can_transfer(accounts(2), accounts(3), U128::from(TOTAL_SUPPLY)),
+            TransferCheck::Ok
+        );
+        assert_eq!(
+            contract.can_transfer(accounts(2), accounts(4), U128::from(1)),
+            TransferCheck::ReceiverNotRegistered
+        );
+        assert_eq!(
+            contract.can_transfer(accounts(4), accounts(2), U128::from(1)),
+            TransferCheck::SenderNotRegistered
+        );
+        assert_eq!(
+            contract.can_transfer(accounts(2), accounts(2), U128::from(1)),
+            TransferCheck::SelfTransfer
+        );
+        assert_eq!(
+            contract.can_transfer(accounts(2), accounts(3), U128::from(0)),
+            TransferCheck::ZeroAmount
+        );
     }
 
     #[test]
-    fn test_new() {
-        let mut context = get_context(accounts(1), accounts(2));
+    fn test_can_transfer_insufficient_balance() {
+        let context = get_context(accounts(2), accounts(2));
         testing_env!(context.build());
-        let contract = MalbornClubContract::new(accounts(1).into(), TOTAL_SUPPLY.into());
-        testing_env!(context.is_view(true).build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.malborn_token.internal_register_account(&accounts(3));
 
-        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY);
         assert_eq!(
-            contract.ft_balance_of(accounts(1)),
-            U128::from(TOTAL_SUPPLY)
+            contract.can_transfer(accounts(2), accounts(3), U128::from(TOTAL_SUPPLY + 1)),
+            TransferCheck::InsufficientBalance
+        );
+        assert_eq!(
+            contract.can_transfer(accounts(3), accounts(2), U128::from(1)),
+            TransferCheck::InsufficientBalance
         );
     }
 
     #[test]
-    fn test_mint() {
+    fn test_can_transfer_banned_parties() {
         let context = get_context(accounts(2), accounts(2));
         testing_env!(context.build());
-        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into());
-
-        let mint_amount = TOTAL_SUPPLY / 2;
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.malborn_token.internal_register_account(&accounts(3));
 
-        contract.mint_tokens(&accounts(2), U128::from(mint_amount));
+        contract.add_to_blocklist(&accounts(3));
         assert_eq!(
-            contract.ft_balance_of(accounts(2)).0,
-            TOTAL_SUPPLY + mint_amount
+            contract.can_transfer(accounts(3), accounts(2), U128::from(1)),
+            TransferCheck::SenderBanned
+        );
+        // Only the sender is checked, banned accounts can still receive
+        assert_eq!(
+            contract.can_transfer(accounts(2), accounts(3), U128::from(1)),
+            TransferCheck::Ok
         );
-        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY + mint_amount);
     }
 
     #[test]
-    fn test_transfer() {
+    fn test_ban_grace_period() {
         let mut context = get_context(accounts(2), accounts(2));
-        testing_env!(context.build());
-        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into());
+        testing_env!(context.block_timestamp(100).build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.malborn_token.internal_register_account(&accounts(3));
+        contract.malborn_token.internal_transfer(&accounts(2), &accounts(3), 100, None);
+        contract.set_ban_grace(U64::from(50));
+        contract.add_to_blocklist(&accounts(3));
+        assert_eq!(contract.ban_effective_at(accounts(3)), Some(U64::from(150)));
+
+        // Transfers still go through during the grace period
         testing_env!(context
-            .storage_usage(env::storage_usage())
-            .attached_deposit(contract.storage_balance_bounds().min.into())
-            .predecessor_account_id(accounts(1))
+            .predecessor_account_id(accounts(3))
+            .signer_account_id(accounts(3))
+            .block_timestamp(149)
+            .attached_deposit(NearToken::from_yoctonear(1))
             .build());
-        //Paying for account registration => storage deposit
-        contract.storage_deposit(None, None);
+        contract.ft_transfer(accounts(2), U128::from(10), None);
+        assert_eq!(contract.malborn_token.accounts.get(&accounts(3)), Some(90));
+
+        testing_env!(context.block_timestamp(150).build());
+        assert_eq!(
+            contract.can_transfer(accounts(3), accounts(2), U128::from(1)),
+            TransferCheck::SenderBanned
+        );
 
         testing_env!(context
-            .storage_usage(env::storage_usage())
-            .attached_deposit(NearToken::from_yoctonear(1))
             .predecessor_account_id(accounts(2))
+            .signer_account_id(accounts(2))
             .build());
-        let transfer_amount = TOTAL_SUPPLY / 3;
-        contract.ft_transfer(accounts(1), transfer_amount.into(), None);
+
+        contract.remove_from_blocklist(&accounts(3));
+        assert_eq!(contract.ban_effective_at(accounts(3)), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "User is banned")]
+    fn test_transfer_after_ban_grace_period() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.block_timestamp(100).build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.malborn_token.internal_register_account(&accounts(3));
+        contract.malborn_token.internal_transfer(&accounts(2), &accounts(3), 100, None);
+        contract.set_ban_grace(U64::from(50));
+        contract.add_to_blocklist(&accounts(3));
 
         testing_env!(context
-            .storage_usage(env::storage_usage())
-            .account_balance(env::account_balance())
-            .is_view(true)
-            .attached_deposit(NearToken::from_yoctonear(0))
+            .predecessor_account_id(accounts(3))
+            .signer_account_id(accounts(3))
+            .block_timestamp(150)
+            .attached_deposit(NearToken::from_yoctonear(1))
             .build());
-        assert_eq!(
-            contract.ft_balance_of(accounts(2)).0,
-            (TOTAL_SUPPLY - transfer_amount)
-        );
-        assert_eq!(contract.ft_balance_of(accounts(1)).0, transfer_amount);
+        contract.ft_transfer(accounts(2), U128::from(10), None);
     }
 
     #[test]
-    #[should_panic]
-    fn test_pause() {
+    fn test_status_epoch_increments_on_status_changes() {
         let context = get_context(accounts(2), accounts(2));
         testing_env!(context.build());
-        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        assert_eq!(contract.get_status_epoch(), U64::from(0));
 
-        let symbol = contract.get_symbol();
-        assert_eq!(symbol, "MAL".to_string());
         contract.pause();
-        contract.get_symbol();
+        assert_eq!(contract.get_status_epoch(), U64::from(1));
+        contract.resume();
+        assert_eq!(contract.get_status_epoch(), U64::from(2));
+        contract.pause_until(U64::from(100));
+        assert_eq!(contract.get_status_epoch(), U64::from(3));
     }
 
     #[test]
-    fn test_blocklist() {
+    #[should_panic(expected = "Status epoch is 2, expected 0")]
+    fn test_ft_transfer_at_stale_epoch() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.malborn_token.internal_register_account(&accounts(3));
+
+        testing_env!(context.attached_deposit(NearToken::from_yoctonear(1)).build());
+        contract.ft_transfer_at_epoch(accounts(3), U128::from(10), None, U64::from(0));
+        assert_eq!(contract.malborn_token.accounts.get(&accounts(3)), Some(10));
+
+        // Paused and resumed again in between
+        contract.pause_until(U64::from(100));
+        testing_env!(context.block_timestamp(100).build());
+        contract.ft_transfer_at_epoch(accounts(3), U128::from(10), None, U64::from(0));
+    }
+
+    #[test]
+    fn test_status_epoch_counts_auto_resume() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+
+        contract.pause_until(U64::from(100));
+        testing_env!(context.block_timestamp(99).build());
+        assert_eq!(contract.get_status_epoch(), U64::from(1));
+        testing_env!(context.block_timestamp(100).build());
+        assert_eq!(contract.get_status_epoch(), U64::from(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "Status epoch is 3, expected 2")]
+    fn test_ft_transfer_at_epoch_read_before_pause_after_auto_resume() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.malborn_token.internal_register_account(&accounts(3));
+
+        contract.pause_until(U64::from(100));
+        testing_env!(context.block_timestamp(100).build());
+        let epoch = contract.get_status_epoch();
+        assert_eq!(epoch, U64::from(2));
+
+        contract.pause();
+        testing_env!(context.attached_deposit(NearToken::from_yoctonear(1)).build());
+        contract.ft_transfer_at_epoch(accounts(3), U128::from(10), None, epoch);
+    }
+
+    #[test]
+    #[should_panic(expected = "Status epoch is 2, expected 1")]
+    fn test_ft_transfer_at_epoch_read_before_auto_resume() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.malborn_token.internal_register_account(&accounts(3));
+
+        contract.pause_until(U64::from(100));
+        testing_env!(context.block_timestamp(50).build());
+        let epoch = contract.get_status_epoch();
+
+        testing_env!(context
+            .block_timestamp(100)
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.ft_transfer_at_epoch(accounts(3), U128::from(10), None, epoch);
+    }
+
+    #[test]
+    fn test_can_transfer_while_paused() {
         let context = get_context(accounts(2), accounts(2));
         testing_env!(context.build());
-        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.malborn_token.internal_register_account(&accounts(3));
+
+        contract.pause();
         assert_eq!(
-            contract.get_blocklist_status(&accounts(1)),
-            BlocklistStatus::Allowed
+            contract.can_transfer(accounts(2), accounts(3), U128::from(1)),
+            TransferCheck::Paused
         );
 
-        contract.add_to_blocklist(&accounts(1));
+        contract.set_owner_exempt_from_pause(true);
         assert_eq!(
-            contract.get_blocklist_status(&accounts(1)),
-            BlocklistStatus::Banned
+            contract.can_transfer(accounts(2), accounts(3), U128::from(1)),
+            TransferCheck::Ok
         );
+    }
+
+    #[test]
+    fn test_can_transfer_over_max_balance() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.malborn_token.internal_register_account(&accounts(3));
+        contract.set_max_balance(Some(U128::from(100)));
 
-        contract.remove_from_blocklist(&accounts(1));
         assert_eq!(
-            contract.get_blocklist_status(&accounts(1)),
-            BlocklistStatus::Allowed
+            contract.can_transfer(accounts(2), accounts(3), U128::from(100)),
+            TransferCheck::Ok
+        );
+        assert_eq!(
+            contract.can_transfer(accounts(2), accounts(3), U128::from(101)),
+            TransferCheck::ExceedsMaxBalance
         );
     }
 
     #[test]
-    #[should_panic]
-    fn test_blocklist2() {
+    fn test_can_transfer_below_min_transfer_amount() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.malborn_token.internal_register_account(&accounts(1));
+        contract.malborn_token.internal_register_account(&accounts(3));
+        contract
+            .malborn_token
+            .internal_transfer(&accounts(2), &accounts(1), 10_000, None);
+        contract.set_min_transfer_amount(Some(U128::from(1_000)));
+
+        assert_eq!(
+            contract.can_transfer(accounts(1), accounts(3), U128::from(1_000)),
+            TransferCheck::Ok
+        );
+        assert_eq!(
+            contract.can_transfer(accounts(1), accounts(3), U128::from(999)),
+            TransferCheck::BelowMinTransfer
+        );
+        // The owner is exempt
+        assert_eq!(
+            contract.can_transfer(accounts(2), accounts(3), U128::from(999)),
+            TransferCheck::Ok
+        );
+    }
+
+    #[test]
+    fn test_forced_unregister_sweeps_residual() {
         let mut context = get_context(accounts(2), accounts(2));
         testing_env!(context.build());
-        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_sweep_account(Some(accounts(3)));
+
         testing_env!(context
-            .storage_usage(env::storage_usage())
-            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .attached_deposit(contract.storage_balance_bounds().min)
             .predecessor_account_id(accounts(1))
             .build());
-        //Paying for account registration -- storage deposit
         contract.storage_deposit(None, None);
 
         testing_env!(context
-            .storage_usage(env::storage_usage())
             .attached_deposit(NearToken::from_yoctonear(1))
             .predecessor_account_id(accounts(2))
             .build());
-        let transfer_amount = TOTAL_SUPPLY / 3;
-        contract.ft_transfer(accounts(1), transfer_amount.into(), None);
+        contract.ft_transfer(accounts(1), U128::from(5), None);
 
-        assert_eq!(
-            contract.get_blocklist_status(&accounts(1)),
-            BlocklistStatus::Allowed
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        assert!(contract.storage_unregister(Some(true)));
+        assert!(get_logs().contains(&format!(
+            "Swept 5 from @{} to @{}",
+            accounts(1),
+            accounts(3)
+        )));
+
+        testing_env!(context.is_view(true).build());
+        assert!(contract.storage_balance_of(accounts(1)).is_none());
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 5);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY);
+    }
+
+    #[test]
+    fn test_ft_resolve_transfer_emits_refund() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.malborn_token.internal_register_account(&accounts(3));
+        contract
+            .malborn_token
+            .internal_transfer(&accounts(2), &accounts(3), 100, None);
+
+        // The receiver's ft_on_transfer only used 70 of the 100 tokens
+        let unused = near_sdk::serde_json::to_vec(&U128::from(30)).unwrap();
+        testing_env!(
+            context.predecessor_account_id(accounts(0)).build(),
+            test_vm_config(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(unused)],
         );
+        let used = contract.ft_resolve_transfer(accounts(2), accounts(3), U128::from(100));
 
-        contract.add_to_blocklist(&accounts(1));
-        assert_eq!(
-            contract.get_blocklist_status(&accounts(1)),
-            BlocklistStatus::Banned
+        assert_eq!(used.0, 70);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 70);
+        assert!(get_logs().contains(&format!(
+            "EVENT_JSON:{}",
+            json!({
+                "standard": events::EVENT_STANDARD,
+                "version": events::EVENT_VERSION,
+                "event": "ft_transfer_refund",
+                "data": [{
+                    "sender_id": accounts(2),
+                    "receiver_id": accounts(3),
+                    "amount": "30",
+                }],
+            })
+        )));
+    }
+
+    // Compare preview_resolve with the actual resolver for a receiver that
+    // used `used` of 100 tokens it received
+    fn assert_preview_matches_resolver(used: Balance) {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.malborn_token.internal_register_account(&accounts(3));
+        contract
+            .malborn_token
+            .internal_transfer(&accounts(2), &accounts(3), 100, None);
+
+        let preview = contract.preview_resolve(
+            accounts(2),
+            accounts(3),
+            U128::from(100),
+            U128::from(used),
         );
 
-        testing_env!(context
-            .predecessor_account_id(accounts(1))
-            .signer_account_id(accounts(1))
-            .build());
+        let unused = near_sdk::serde_json::to_vec(&U128::from(100 - used)).unwrap();
+        testing_env!(
+            context.predecessor_account_id(accounts(0)).build(),
+            test_vm_config(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(unused)],
+        );
+        let resolved = contract.ft_resolve_transfer(accounts(2), accounts(3), U128::from(100));
 
-        contract.ft_transfer(accounts(2), U128::from(transfer_amount / 2), None);
+        assert_eq!(preview.0, 100 - resolved.0);
+        assert_eq!(preview.0, 100 - used);
+    }
+
+    #[test]
+    fn test_preview_resolve_full_use() {
+        assert_preview_matches_resolver(100);
+    }
+
+    #[test]
+    fn test_preview_resolve_partial_use() {
+        assert_preview_matches_resolver(70);
+    }
+
+    #[test]
+    fn test_preview_resolve_caps_at_receiver_balance() {
+        let context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.malborn_token.internal_register_account(&accounts(3));
+        contract
+            .malborn_token
+            .internal_transfer(&accounts(2), &accounts(3), 10, None);
+
+        // The receiver already moved most of the tokens on
+        let preview =
+            contract.preview_resolve(accounts(2), accounts(3), U128::from(100), U128::from(0));
+        assert_eq!(preview.0, 10);
+        // Nothing is refunded to a sender that no longer exists
+        let preview =
+            contract.preview_resolve(accounts(4), accounts(3), U128::from(100), U128::from(0));
+        assert_eq!(preview.0, 0);
+    }
+
+    #[test]
+    fn test_ft_resolve_transfer_fully_used() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.malborn_token.internal_register_account(&accounts(3));
+        contract
+            .malborn_token
+            .internal_transfer(&accounts(2), &accounts(3), 100, None);
+
+        let unused = near_sdk::serde_json::to_vec(&U128::from(0)).unwrap();
+        testing_env!(
+            context.predecessor_account_id(accounts(0)).build(),
+            test_vm_config(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(unused)],
+        );
+        assert_eq!(contract.ft_resolve_transfer(accounts(2), accounts(3), U128::from(100)).0, 100);
+        assert!(get_logs().iter().all(|log| !log.contains("ft_transfer_refund")));
     }
 
     // ========== VULNERABILITY TEST CASES ==========
@@ -556,7 +4235,7 @@ mod tests {
         // Bug: resume() sets status to Paused instead of Working
         let context = get_context(accounts(2), accounts(2));
         testing_env!(context.build());
-        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into());
+        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into(), None, None);
 
         // Pause the contract
         contract.pause();
@@ -575,7 +4254,7 @@ mod tests {
         // Demonstrates that after resume(), contract is still unusable
         let mut context = get_context(accounts(2), accounts(2));
         testing_env!(context.build());
-        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into());
+        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into(), None, None);
 
         // Pause and "resume" (which actually keeps it paused)
         contract.pause();
@@ -591,7 +4270,7 @@ mod tests {
         // Bug: mint_tokens() increases total_supply but doesn't add tokens to unregistered users
         let mut context = get_context(accounts(2), accounts(2));
         testing_env!(context.build());
-        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into());
+        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into(), None, None);
 
         let mint_amount = 100_000_000;
         let unregistered_user = accounts(3);
@@ -618,7 +4297,7 @@ mod tests {
         // The bug: total_supply increases but user balance is 0 (tokens are lost)
         let mut context = get_context(accounts(2), accounts(2));
         testing_env!(context.build());
-        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into());
+        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into(), None, None);
 
         let mint_amount = 100_000_000;
         let unregistered_user = accounts(3);
@@ -635,6 +4314,30 @@ mod tests {
         assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY + mint_amount);
     }
 
+    #[test]
+    fn test_metadata_summary_keeps_metadata() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.build());
+        let contract = MalbornClubContract::new(
+            accounts(2),
+            TOTAL_SUPPLY.into(),
+            Some(6),
+            Some("data:image/svg+xml,<svg/>".to_string()),
+        );
+        testing_env!(context.is_view(true).build());
+
+        let expected = MetadataSummary {
+            name: "Malborn Token".to_string(),
+            symbol: "MAL".to_string(),
+            decimals: 6,
+            icon: Some("data:image/svg+xml,<svg/>".to_string()),
+            spec: FT_METADATA_SPEC.to_string(),
+        };
+        assert_eq!(contract.metadata_summary(), expected);
+        assert_eq!(contract.metadata_summary(), expected);
+        assert_eq!(contract.ft_metadata().symbol, "MAL");
+    }
+
     #[test]
     #[should_panic]
     fn test_metadata_consumption_bug() {
@@ -642,7 +4345,7 @@ mod tests {
         // After consuming, ft_metadata() will fail because metadata is None
         let mut context = get_context(accounts(2), accounts(2));
         testing_env!(context.build());
-        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into());
+        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into(), None, None);
 
         // First call works (consumes metadata)
         let symbol = contract.get_symbol();
@@ -659,7 +4362,7 @@ mod tests {
         // Demonstrates that any metadata function call consumes it
         let mut context = get_context(accounts(2), accounts(2));
         testing_env!(context.build());
-        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into());
+        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into(), None, None);
 
         // Call get_name - consumes metadata
         let name = contract.get_name();
@@ -676,7 +4379,7 @@ mod tests {
         // Demonstrates that ft_metadata() also fails after metadata is consumed
         let mut context = get_context(accounts(2), accounts(2));
         testing_env!(context.build());
-        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into());
+        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into(), None, None);
 
         // Consume metadata with get_decimals
         let decimals = contract.get_decimals();
@@ -693,7 +4396,7 @@ mod tests {
         // Bug: Owner can set registration_fee_denominator to 0, causing division by zero
         let context = get_context(accounts(2), accounts(2));
         testing_env!(context.build());
-        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into());
+        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into(), None, None);
 
         // Set associated contract
         contract.set_associated_contract(accounts(3));
@@ -705,6 +4408,48 @@ mod tests {
         contract.register_for_event(U128::from(1));
     }
 
+    #[test]
+    fn test_fee_change_waits_for_timelock() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.block_timestamp(100).build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_fee_change_timelock(U64::from(50));
+        contract.set_registration_fee_denominator(U128::from(100));
+        assert_eq!(contract.get_registration_fee_denominator(), U128::from(10000));
+        assert_eq!(
+            contract.get_pending_fee_change(),
+            Some((U128::from(100), U64::from(150)))
+        );
+
+        // Registrations still pay the old fee before the timelock elapses
+        contract.set_associated_contract(accounts(3));
+        testing_env!(context.block_timestamp(149).build());
+        contract.register_for_event(U128::from(1));
+        assert_eq!(
+            contract.ft_balance_of(accounts(2)),
+            U128::from(TOTAL_SUPPLY - TOTAL_SUPPLY / 10000)
+        );
+
+        // Once it has, the new fee is read before being applied
+        testing_env!(context.block_timestamp(150).build());
+        assert_eq!(contract.get_registration_fee_denominator(), U128::from(100));
+        contract.apply_fee_change();
+        assert_eq!(contract.get_pending_fee_change(), None);
+        assert_eq!(contract.get_registration_fee_denominator(), U128::from(100));
+    }
+
+    #[test]
+    #[should_panic(expected = "Fee change timelock has not elapsed")]
+    fn test_apply_fee_change_before_timelock() {
+        let mut context = get_context(accounts(2), accounts(2));
+        testing_env!(context.block_timestamp(100).build());
+        let mut contract = MalbornClubContract::new(accounts(2), TOTAL_SUPPLY.into(), None, None);
+        contract.set_fee_change_timelock(U64::from(50));
+        contract.set_registration_fee_denominator(U128::from(100));
+        testing_env!(context.block_timestamp(149).build());
+        contract.apply_fee_change();
+    }
+
     #[test]
     fn test_register_for_event_burns_tokens_before_external_call() {
         // Bug: Tokens are burned BEFORE external call. If external call fails, tokens are lost
@@ -713,7 +4458,7 @@ mod tests {
         // If the promise fails (event doesn't exist, event offline, etc.), tokens are still burned
         let mut context = get_context(accounts(2), accounts(2));
         testing_env!(context.build());
-        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into());
+        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into(), None, None);
 
         // Register user and give them tokens
         testing_env!(context
@@ -765,7 +4510,7 @@ mod tests {
         // Bug: ft_total_supply() calls not_paused(), breaking FT standard
         let mut context = get_context(accounts(2), accounts(2));
         testing_env!(context.build());
-        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into());
+        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into(), None, None);
 
         // Pause the contract
         contract.pause();
@@ -781,7 +4526,7 @@ mod tests {
         // Bug: ft_balance_of() calls not_paused(), breaking FT standard
         let mut context = get_context(accounts(2), accounts(2));
         testing_env!(context.build());
-        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into());
+        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into(), None, None);
 
         // Pause the contract
         contract.pause();
@@ -797,7 +4542,7 @@ mod tests {
         // Bug: get_blocklist_status() calls not_paused(), causing DoS
         let mut context = get_context(accounts(2), accounts(2));
         testing_env!(context.build());
-        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into());
+        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into(), None, None);
 
         // Pause the contract
         contract.pause();
@@ -813,7 +4558,7 @@ mod tests {
         // Can set to self, invalid account, or create circular dependencies
         let mut context = get_context(accounts(2), accounts(2));
         testing_env!(context.build());
-        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into());
+        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into(), None, None);
         
         // BUG: Can set associated contract to self - creates circular dependency
         // Should validate that account_id != current_account_id, but doesn't
@@ -835,7 +4580,7 @@ mod tests {
         // Can set to any account ID without validation
         let mut context = get_context(accounts(2), accounts(2));
         testing_env!(context.build());
-        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into());
+        let mut contract = MalbornClubContract::new(accounts(2).into(), TOTAL_SUPPLY.into(), None, None);
         
         // Set to a non-existent or invalid account
         // Should validate account exists, but doesn't
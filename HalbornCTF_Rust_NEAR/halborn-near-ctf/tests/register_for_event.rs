@@ -0,0 +1,150 @@
+// End-to-end coverage of register_for_event against a deployed associated
+// contract. Both contracts have to be built for wasm first, see Run.md
+use near_workspaces::types::NearToken;
+use near_workspaces::{Account, Contract, Worker};
+use serde_json::json;
+
+const TOTAL_SUPPLY: u128 = 1_000_000_000;
+// TOTAL_SUPPLY / default registration_fee_denominator
+const REGISTRATION_FEE: u128 = TOTAL_SUPPLY / 10_000;
+const WASM_DIR: &str = "../target/wasm32-unknown-unknown/release";
+
+struct Setup {
+    malborn: Contract,
+    associated: Contract,
+    user: Account,
+}
+
+async fn setup(worker: &Worker<near_workspaces::network::Sandbox>) -> anyhow::Result<Setup> {
+    let malborn_wasm = std::fs::read(format!("{}/halborn_near_ctf.wasm", WASM_DIR))?;
+    let associated_wasm = std::fs::read(format!(
+        "{}/halborn_near_ctf_associated_contract.wasm",
+        WASM_DIR
+    ))?;
+
+    let owner = worker.dev_create_account().await?;
+    let user = worker.dev_create_account().await?;
+    let malborn = worker.dev_deploy(&malborn_wasm).await?;
+    let associated = worker.dev_deploy(&associated_wasm).await?;
+
+    malborn
+        .call("new")
+        .args_json(json!({ "owner_id": owner.id(), "token_total_supply": TOTAL_SUPPLY.to_string() }))
+        .transact()
+        .await?
+        .into_result()?;
+    associated
+        .call("new")
+        .args_json(json!({ "owner_id": owner.id() }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    // The associated contract only accepts registrations from privileged clubs
+    owner
+        .call(associated.id(), "add_privileged_club")
+        .args_json(json!({ "account_id": malborn.id() }))
+        .transact()
+        .await?
+        .into_result()?;
+    owner
+        .call(associated.id(), "add_new_event")
+        .args_json(json!({ "event_title": "Malborn meetup" }))
+        .transact()
+        .await?
+        .into_result()?;
+    owner
+        .call(malborn.id(), "set_associated_contract")
+        .args_json(json!({ "account_id": associated.id() }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Give the user enough tokens to pay the registration fee
+    user.call(malborn.id(), "storage_deposit")
+        .args_json(json!({}))
+        .deposit(NearToken::from_millinear(10))
+        .transact()
+        .await?
+        .into_result()?;
+    owner
+        .call(malborn.id(), "ft_transfer")
+        .args_json(json!({ "receiver_id": user.id(), "amount": (REGISTRATION_FEE * 2).to_string() }))
+        .deposit(NearToken::from_yoctonear(1))
+        .transact()
+        .await?
+        .into_result()?;
+
+    Ok(Setup {
+        malborn,
+        associated,
+        user,
+    })
+}
+
+async fn balance_of(malborn: &Contract, account: &Account) -> anyhow::Result<u128> {
+    let balance: String = malborn
+        .view("ft_balance_of")
+        .args_json(json!({ "account_id": account.id() }))
+        .await?
+        .json()?;
+    Ok(balance.parse()?)
+}
+
+async fn is_registered(
+    associated: &Contract,
+    event_id: u64,
+    account: &Account,
+) -> anyhow::Result<bool> {
+    Ok(associated
+        .view("check_user_registered")
+        .args_json(json!({ "event_id": event_id.to_string(), "account_id": account.id() }))
+        .await?
+        .json()?)
+}
+
+#[tokio::test]
+#[ignore = "needs a near-sandbox binary and the wasm builds, see Run.md"]
+async fn test_register_for_event_burns_and_registers() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let Setup {
+        malborn,
+        associated,
+        user,
+    } = setup(&worker).await?;
+
+    user.call(malborn.id(), "register_for_event")
+        .args_json(json!({ "event_id": "1" }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    assert_eq!(balance_of(&malborn, &user).await?, REGISTRATION_FEE);
+    assert!(is_registered(&associated, 1, &user).await?);
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs a near-sandbox binary and the wasm builds, see Run.md"]
+async fn test_register_for_missing_event_still_burns() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let Setup {
+        malborn,
+        associated,
+        user,
+    } = setup(&worker).await?;
+
+    // The cross-contract call fails on the associated contract. The callback
+    // only releases the in-flight lock, so the burned fee is not refunded
+    let _ = user
+        .call(malborn.id(), "register_for_event")
+        .args_json(json!({ "event_id": "999" }))
+        .max_gas()
+        .transact()
+        .await?;
+
+    assert_eq!(balance_of(&malborn, &user).await?, REGISTRATION_FEE);
+    assert!(!is_registered(&associated, 999, &user).await?);
+    Ok(())
+}
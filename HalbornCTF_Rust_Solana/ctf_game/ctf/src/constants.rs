@@ -3,5 +3,18 @@ pub const NOT_A_SIGNER: bool = false;
 
 pub const GAME_CONFIG_SEED: &[u8] = b"GAME_CONFIG";
 pub const USER_SEED: &[u8] = b"USER";
+pub const LEADERBOARD_SEED: &[u8] = b"LEADERBOARD";
+pub const CREDITS_HISTORY_SEED: &[u8] = b"CREDITS_HISTORY";
 
-pub const MAX_LEVEL: u8 = u8::MAX;
\ No newline at end of file
+pub const MAX_LEVEL: u8 = u8::MAX;
+
+/// Number of users a leaderboard ranks
+pub const LEADERBOARD_SIZE: usize = 10;
+/// Rank reported for users not on the leaderboard, ranks start at 1
+pub const UNRANKED: u32 = 0;
+
+/// Number of credits per level changes a history keeps
+pub const CREDITS_HISTORY_SIZE: usize = 16;
+
+/// Number of users a batch level-up accepts, to stay within the compute budget
+pub const MAX_BATCH_LEVEL_UP: usize = 16;
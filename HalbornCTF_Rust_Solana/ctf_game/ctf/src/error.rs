@@ -0,0 +1,26 @@
+use solana_program::program_error::ProgramError;
+
+/// Errors returned by this program
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GameError {
+    /// A counter or balance would overflow or underflow
+    ArithmeticOverflow = 0,
+
+    /// The user is already at the game's level cap
+    MaxLevelReached = 1,
+
+    /// The user leveled up less than `level_up_cooldown_slots` ago
+    CooldownActive = 2,
+
+    /// The user was frozen by the game config admin
+    UserFrozen = 3,
+
+    /// More users were passed to a batch level-up than `MAX_BATCH_LEVEL_UP`
+    BatchTooLarge = 4
+}
+
+impl From<GameError> for ProgramError {
+    fn from(e: GameError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
@@ -25,12 +25,13 @@ pub enum ProgramInstruction {
     /// [s] admin
     /// [r] system program
     CreateGameConfig {
-        credits_per_level: u8
+        credits_per_level: u8,
+        max_level: u8
     },
 
     /// Create a new User account
     /// accounts:
-    /// [r] game config
+    /// [w] game config
     /// [w] user 
     /// [s] user authority
     /// [r] system program
@@ -40,7 +41,8 @@ pub enum ProgramInstruction {
     /// accounts:
     /// [r] game config
     /// [w] user account
-    /// [s] admin
+    /// [ws] admin
+    /// [r] system program
     MintCreditsToUser {
         credits: u32
     },
@@ -49,23 +51,158 @@ pub enum ProgramInstruction {
     /// accounts:
     /// [r] game config
     /// [w] user account
-    /// [s] user authority
+    /// [ws] user authority
     /// [r] system program
+    /// [w] leaderboard, optional, must already exist, see CreateLeaderboard
     UserLevelUp {
         credits_to_burn: u32
-    }
+    },
+
+    /// Log the configuration of a GameConfig account
+    /// accounts:
+    /// [r] game config
+    /// [r] admin
+    GetConfig { },
+
+    /// Close a User account and return its lamports to the authority
+    /// accounts:
+    /// [w] game config
+    /// [w] user account
+    /// [ws] user authority
+    /// [r] system program
+    /// [w] leaderboard, optional, the user is dropped from it
+    CloseUser { },
+
+    /// Move credits from a User account to the game config reclaimed pool
+    /// accounts:
+    /// [w] game config
+    /// [w] user account
+    /// [ws] admin
+    /// [r] system program
+    ReclaimCredits {
+        amount: u32
+    },
+
+    /// Create the Leaderboard account of a GameConfig, failing if it already
+    /// exists
+    /// accounts:
+    /// [w] leaderboard
+    /// [r] game config
+    /// [ws] admin
+    /// [r] system program
+    CreateLeaderboard { },
+
+    /// Log the leaderboard rank of a User account, `UNRANKED` if not ranked
+    /// accounts:
+    /// [r] game config
+    /// [r] leaderboard
+    /// [r] user account
+    GetUserRank { },
+
+    /// Set the minimum number of slots between two level-ups of a user
+    /// accounts:
+    /// [w] game config
+    /// [ws] admin
+    /// [r] system program
+    SetLevelUpCooldown {
+        slots: u64
+    },
+
+    /// Move all credits of a User account to another one of the same game
+    /// configuration as its admin, optionally closing the emptied source
+    /// accounts:
+    /// [w] game config
+    /// [w] source user account
+    /// [w] destination user account
+    /// [ws] admin
+    /// [r] system program
+    /// [w] source user authority, only when closing, receives the lamports
+    /// [w] leaderboard, optional and only when closing, the source is
+    ///     dropped from it
+    MergeUsers {
+        close_source: bool
+    },
+
+    /// Freeze or unfreeze a User account as the admin of its GameConfig
+    /// accounts:
+    /// [r] game config
+    /// [w] user account
+    /// [ws] admin
+    /// [r] system program
+    SetUserFrozen {
+        frozen: bool
+    },
+
+    /// Log the credits a User account needs for its next level
+    /// accounts:
+    /// [r] game config
+    /// [r] admin
+    /// [r] user account
+    NextLevelCost { },
+
+    /// Overwrite the credits of a User account as the admin of its GameConfig
+    /// accounts:
+    /// [r] game config
+    /// [w] user account
+    /// [ws] admin
+    /// [r] system program
+    SetUserCredits {
+        credits: u32
+    },
+
+    /// Change the credits per level of a GameConfig as its admin, from
+    /// `effective_slot` on or right away when it is `None` or already
+    /// reached, and record the change in its CreditsHistory
+    /// accounts:
+    /// [w] game config
+    /// [w] credits history
+    /// [ws] admin
+    /// [r] system program
+    UpdateCreditsPerLevel {
+        credits_per_level: u8,
+        effective_slot: Option<u64>
+    },
+
+    /// Level up User accounts of a GameConfig as its admin, burning the same
+    /// credits for each. Users that can't level up are skipped
+    /// accounts:
+    /// [r] game config
+    /// [ws] admin
+    /// [r] system program
+    /// [w] user accounts, at most `MAX_BATCH_LEVEL_UP`
+    BatchLevelUp {
+        credits_to_burn: u32
+    },
+
+    /// Move lamports above the rent-exempt minimum out of a GameConfig
+    /// account as its admin
+    /// accounts:
+    /// [w] game config
+    /// [s] admin
+    /// [w] destination
+    WithdrawConfigLamports {
+        amount: u64
+    },
+
+    /// Log all fields of a User account
+    /// accounts:
+    /// [r] game config
+    /// [r] user account
+    GetUser { }
 }
 
 /// Create a `CreateGameConfig` instruction
 pub fn create_game_config(
     game_config: Pubkey,
     admin: Pubkey,
-    credits_per_level: u8
+    credits_per_level: u8,
+    max_level: u8
 ) -> Instruction {
     Instruction::new_with_borsh(
         crate::id(),
         &ProgramInstruction::CreateGameConfig {
-            credits_per_level
+            credits_per_level,
+            max_level
         },
         vec![
             AccountMeta::new(game_config, NOT_A_SIGNER),
@@ -85,7 +222,7 @@ pub fn create_user(
         crate::id(),
         &ProgramInstruction::CreateUser { },
         vec![
-            AccountMeta::new_readonly(game_config, NOT_A_SIGNER),
+            AccountMeta::new(game_config, NOT_A_SIGNER),
             AccountMeta::new(user, NOT_A_SIGNER),
             AccountMeta::new(user_authority, SIGNER),
             AccountMeta::new_readonly(system_program::id(), NOT_A_SIGNER)
@@ -108,7 +245,8 @@ pub fn mint_credits_to_user(
         vec![
             AccountMeta::new_readonly(game_config, NOT_A_SIGNER),
             AccountMeta::new(user_account, NOT_A_SIGNER),
-            AccountMeta::new_readonly(admin, SIGNER)
+            AccountMeta::new(admin, SIGNER),
+            AccountMeta::new_readonly(system_program::id(), NOT_A_SIGNER)
         ]
     )
 }
@@ -128,7 +266,374 @@ pub fn user_level_up(
         vec![
             AccountMeta::new_readonly(game_config, NOT_A_SIGNER),
             AccountMeta::new(user_account, NOT_A_SIGNER),
-            AccountMeta::new_readonly(user_authority, SIGNER)
+            AccountMeta::new(user_authority, SIGNER),
+            AccountMeta::new_readonly(system_program::id(), NOT_A_SIGNER)
+        ]
+    )
+}
+
+/// Create a `UserLevelUp` instruction that also updates the leaderboard
+pub fn user_level_up_ranked(
+    game_config: Pubkey,
+    user_account: Pubkey,
+    user_authority: Pubkey,
+    leaderboard: Pubkey,
+    credits_to_burn: u32
+) -> Instruction {
+    Instruction::new_with_borsh(
+        crate::id(),
+        &ProgramInstruction::UserLevelUp {
+            credits_to_burn
+        },
+        vec![
+            AccountMeta::new_readonly(game_config, NOT_A_SIGNER),
+            AccountMeta::new(user_account, NOT_A_SIGNER),
+            AccountMeta::new(user_authority, SIGNER),
+            AccountMeta::new_readonly(system_program::id(), NOT_A_SIGNER),
+            AccountMeta::new(leaderboard, NOT_A_SIGNER)
+        ]
+    )
+}
+
+/// Create a `GetConfig` instruction
+pub fn get_config(
+    game_config: Pubkey,
+    admin: Pubkey
+) -> Instruction {
+    Instruction::new_with_borsh(
+        crate::id(),
+        &ProgramInstruction::GetConfig { },
+        vec![
+            AccountMeta::new_readonly(game_config, NOT_A_SIGNER),
+            AccountMeta::new_readonly(admin, NOT_A_SIGNER)
+        ]
+    )
+}
+
+/// Create a `CloseUser` instruction
+pub fn close_user(
+    game_config: Pubkey,
+    user_account: Pubkey,
+    user_authority: Pubkey
+) -> Instruction {
+    Instruction::new_with_borsh(
+        crate::id(),
+        &ProgramInstruction::CloseUser { },
+        vec![
+            AccountMeta::new(game_config, NOT_A_SIGNER),
+            AccountMeta::new(user_account, NOT_A_SIGNER),
+            AccountMeta::new(user_authority, SIGNER),
+            AccountMeta::new_readonly(system_program::id(), NOT_A_SIGNER)
+        ]
+    )
+}
+
+/// Create a `CloseUser` instruction that also drops the user from the
+/// leaderboard
+pub fn close_user_ranked(
+    game_config: Pubkey,
+    user_account: Pubkey,
+    user_authority: Pubkey,
+    leaderboard: Pubkey
+) -> Instruction {
+    Instruction::new_with_borsh(
+        crate::id(),
+        &ProgramInstruction::CloseUser { },
+        vec![
+            AccountMeta::new(game_config, NOT_A_SIGNER),
+            AccountMeta::new(user_account, NOT_A_SIGNER),
+            AccountMeta::new(user_authority, SIGNER),
+            AccountMeta::new_readonly(system_program::id(), NOT_A_SIGNER),
+            AccountMeta::new(leaderboard, NOT_A_SIGNER)
+        ]
+    )
+}
+
+/// Create a `ReclaimCredits` instruction
+pub fn reclaim_credits(
+    game_config: Pubkey,
+    user_account: Pubkey,
+    admin: Pubkey,
+    amount: u32
+) -> Instruction {
+    Instruction::new_with_borsh(
+        crate::id(),
+        &ProgramInstruction::ReclaimCredits {
+            amount
+        },
+        vec![
+            AccountMeta::new(game_config, NOT_A_SIGNER),
+            AccountMeta::new(user_account, NOT_A_SIGNER),
+            AccountMeta::new(admin, SIGNER),
+            AccountMeta::new_readonly(system_program::id(), NOT_A_SIGNER)
+        ]
+    )
+}
+
+/// Create a `CreateLeaderboard` instruction
+pub fn create_leaderboard(
+    leaderboard: Pubkey,
+    game_config: Pubkey,
+    admin: Pubkey
+) -> Instruction {
+    Instruction::new_with_borsh(
+        crate::id(),
+        &ProgramInstruction::CreateLeaderboard { },
+        vec![
+            AccountMeta::new(leaderboard, NOT_A_SIGNER),
+            AccountMeta::new_readonly(game_config, NOT_A_SIGNER),
+            AccountMeta::new(admin, SIGNER),
+            AccountMeta::new_readonly(system_program::id(), NOT_A_SIGNER)
         ]
     )
-}
\ No newline at end of file
+}
+
+/// Create a `SetUserFrozen` instruction
+pub fn set_user_frozen(
+    game_config: Pubkey,
+    user_account: Pubkey,
+    admin: Pubkey,
+    frozen: bool
+) -> Instruction {
+    Instruction::new_with_borsh(
+        crate::id(),
+        &ProgramInstruction::SetUserFrozen {
+            frozen
+        },
+        vec![
+            AccountMeta::new_readonly(game_config, NOT_A_SIGNER),
+            AccountMeta::new(user_account, NOT_A_SIGNER),
+            AccountMeta::new(admin, SIGNER),
+            AccountMeta::new_readonly(system_program::id(), NOT_A_SIGNER)
+        ]
+    )
+}
+
+/// Create a `SetUserCredits` instruction
+pub fn set_user_credits(
+    game_config: Pubkey,
+    user_account: Pubkey,
+    admin: Pubkey,
+    credits: u32
+) -> Instruction {
+    Instruction::new_with_borsh(
+        crate::id(),
+        &ProgramInstruction::SetUserCredits {
+            credits
+        },
+        vec![
+            AccountMeta::new_readonly(game_config, NOT_A_SIGNER),
+            AccountMeta::new(user_account, NOT_A_SIGNER),
+            AccountMeta::new(admin, SIGNER),
+            AccountMeta::new_readonly(system_program::id(), NOT_A_SIGNER)
+        ]
+    )
+}
+
+/// Create an `UpdateCreditsPerLevel` instruction
+pub fn update_credits_per_level(
+    game_config: Pubkey,
+    credits_history: Pubkey,
+    admin: Pubkey,
+    credits_per_level: u8,
+    effective_slot: Option<u64>
+) -> Instruction {
+    Instruction::new_with_borsh(
+        crate::id(),
+        &ProgramInstruction::UpdateCreditsPerLevel {
+            credits_per_level,
+            effective_slot
+        },
+        vec![
+            AccountMeta::new(game_config, NOT_A_SIGNER),
+            AccountMeta::new(credits_history, NOT_A_SIGNER),
+            AccountMeta::new(admin, SIGNER),
+            AccountMeta::new_readonly(system_program::id(), NOT_A_SIGNER)
+        ]
+    )
+}
+
+/// Create a `BatchLevelUp` instruction
+pub fn batch_level_up(
+    game_config: Pubkey,
+    admin: Pubkey,
+    user_accounts: &[Pubkey],
+    credits_to_burn: u32
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new_readonly(game_config, NOT_A_SIGNER),
+        AccountMeta::new(admin, SIGNER),
+        AccountMeta::new_readonly(system_program::id(), NOT_A_SIGNER)
+    ];
+    accounts.extend(user_accounts.iter().map(|user_account| AccountMeta::new(*user_account, NOT_A_SIGNER)));
+
+    Instruction::new_with_borsh(
+        crate::id(),
+        &ProgramInstruction::BatchLevelUp {
+            credits_to_burn
+        },
+        accounts
+    )
+}
+
+/// Create a `WithdrawConfigLamports` instruction
+pub fn withdraw_config_lamports(
+    game_config: Pubkey,
+    admin: Pubkey,
+    destination: Pubkey,
+    amount: u64
+) -> Instruction {
+    Instruction::new_with_borsh(
+        crate::id(),
+        &ProgramInstruction::WithdrawConfigLamports {
+            amount
+        },
+        vec![
+            AccountMeta::new(game_config, NOT_A_SIGNER),
+            AccountMeta::new_readonly(admin, SIGNER),
+            AccountMeta::new(destination, NOT_A_SIGNER)
+        ]
+    )
+}
+
+/// Create a `NextLevelCost` instruction
+pub fn next_level_cost(
+    game_config: Pubkey,
+    admin: Pubkey,
+    user_account: Pubkey
+) -> Instruction {
+    Instruction::new_with_borsh(
+        crate::id(),
+        &ProgramInstruction::NextLevelCost { },
+        vec![
+            AccountMeta::new_readonly(game_config, NOT_A_SIGNER),
+            AccountMeta::new_readonly(admin, NOT_A_SIGNER),
+            AccountMeta::new_readonly(user_account, NOT_A_SIGNER)
+        ]
+    )
+}
+
+/// Create a `GetUserRank` instruction
+pub fn get_user_rank(
+    game_config: Pubkey,
+    leaderboard: Pubkey,
+    user_account: Pubkey
+) -> Instruction {
+    Instruction::new_with_borsh(
+        crate::id(),
+        &ProgramInstruction::GetUserRank { },
+        vec![
+            AccountMeta::new_readonly(game_config, NOT_A_SIGNER),
+            AccountMeta::new_readonly(leaderboard, NOT_A_SIGNER),
+            AccountMeta::new_readonly(user_account, NOT_A_SIGNER)
+        ]
+    )
+}
+
+/// Create a `GetUser` instruction
+pub fn get_user(
+    game_config: Pubkey,
+    user_account: Pubkey
+) -> Instruction {
+    Instruction::new_with_borsh(
+        crate::id(),
+        &ProgramInstruction::GetUser { },
+        vec![
+            AccountMeta::new_readonly(game_config, NOT_A_SIGNER),
+            AccountMeta::new_readonly(user_account, NOT_A_SIGNER)
+        ]
+    )
+}
+
+/// Create a `SetLevelUpCooldown` instruction
+pub fn set_level_up_cooldown(
+    game_config: Pubkey,
+    admin: Pubkey,
+    slots: u64
+) -> Instruction {
+    Instruction::new_with_borsh(
+        crate::id(),
+        &ProgramInstruction::SetLevelUpCooldown {
+            slots
+        },
+        vec![
+            AccountMeta::new(game_config, NOT_A_SIGNER),
+            AccountMeta::new(admin, SIGNER),
+            AccountMeta::new_readonly(system_program::id(), NOT_A_SIGNER)
+        ]
+    )
+}
+
+/// Create a `MergeUsers` instruction that keeps the emptied source
+pub fn merge_users(
+    game_config: Pubkey,
+    source_user: Pubkey,
+    dest_user: Pubkey,
+    admin: Pubkey
+) -> Instruction {
+    Instruction::new_with_borsh(
+        crate::id(),
+        &ProgramInstruction::MergeUsers {
+            close_source: false
+        },
+        vec![
+            AccountMeta::new(game_config, NOT_A_SIGNER),
+            AccountMeta::new(source_user, NOT_A_SIGNER),
+            AccountMeta::new(dest_user, NOT_A_SIGNER),
+            AccountMeta::new(admin, SIGNER),
+            AccountMeta::new_readonly(system_program::id(), NOT_A_SIGNER)
+        ]
+    )
+}
+
+/// Create a `MergeUsers` instruction that closes the source and returns its
+/// lamports to `source_authority`
+pub fn merge_and_close_users(
+    game_config: Pubkey,
+    source_user: Pubkey,
+    dest_user: Pubkey,
+    admin: Pubkey,
+    source_authority: Pubkey
+) -> Instruction {
+    Instruction::new_with_borsh(
+        crate::id(),
+        &ProgramInstruction::MergeUsers {
+            close_source: true
+        },
+        vec![
+            AccountMeta::new(game_config, NOT_A_SIGNER),
+            AccountMeta::new(source_user, NOT_A_SIGNER),
+            AccountMeta::new(dest_user, NOT_A_SIGNER),
+            AccountMeta::new(admin, SIGNER),
+            AccountMeta::new_readonly(system_program::id(), NOT_A_SIGNER),
+            AccountMeta::new(source_authority, NOT_A_SIGNER)
+        ]
+    )
+}
+
+/// Create a `MergeUsers` instruction that closes the source and also drops it
+/// from the leaderboard
+pub fn merge_and_close_users_ranked(
+    game_config: Pubkey,
+    source_user: Pubkey,
+    dest_user: Pubkey,
+    admin: Pubkey,
+    source_authority: Pubkey,
+    leaderboard: Pubkey
+) -> Instruction {
+    Instruction::new_with_borsh(
+        crate::id(),
+        &ProgramInstruction::MergeUsers {
+            close_source: true
+        },
+        vec![
+            AccountMeta::new(game_config, NOT_A_SIGNER),
+            AccountMeta::new(source_user, NOT_A_SIGNER),
+            AccountMeta::new(dest_user, NOT_A_SIGNER),
+            AccountMeta::new(admin, SIGNER),
+            AccountMeta::new_readonly(system_program::id(), NOT_A_SIGNER),
+            AccountMeta::new(source_authority, NOT_A_SIGNER),
+            AccountMeta::new(leaderboard, NOT_A_SIGNER)
+        ]
+    )
+}
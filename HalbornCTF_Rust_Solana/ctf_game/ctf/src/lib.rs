@@ -10,6 +10,7 @@ use solana_program::{
 pub mod state;
 pub mod instructions;
 pub mod constants;
+pub mod error;
 mod processor;
 
 use instructions::ProgramInstruction;
@@ -26,9 +27,23 @@ pub fn process_instruction(
     instruction_data: &[u8],
 ) -> ProgramResult {
     match ProgramInstruction::try_from_slice(instruction_data)? {
-        ProgramInstruction::CreateGameConfig { credits_per_level } => create_game_config(credits_per_level, accounts),
+        ProgramInstruction::CreateGameConfig { credits_per_level, max_level } => create_game_config(credits_per_level, max_level, accounts),
         ProgramInstruction::CreateUser { } => create_user(accounts),
         ProgramInstruction::MintCreditsToUser { credits } => mint_credits_to_user(credits, accounts),
-        ProgramInstruction::UserLevelUp { credits_to_burn } => user_level_up(credits_to_burn, accounts)
+        ProgramInstruction::UserLevelUp { credits_to_burn } => user_level_up(credits_to_burn, accounts),
+        ProgramInstruction::GetConfig { } => get_config(accounts),
+        ProgramInstruction::CloseUser { } => close_user(accounts),
+        ProgramInstruction::ReclaimCredits { amount } => reclaim_credits(amount, accounts),
+        ProgramInstruction::CreateLeaderboard { } => create_leaderboard(accounts),
+        ProgramInstruction::GetUserRank { } => get_user_rank(accounts),
+        ProgramInstruction::SetLevelUpCooldown { slots } => set_level_up_cooldown(slots, accounts),
+        ProgramInstruction::MergeUsers { close_source } => merge_users(close_source, accounts),
+        ProgramInstruction::SetUserFrozen { frozen } => set_user_frozen(frozen, accounts),
+        ProgramInstruction::NextLevelCost { } => next_level_cost(accounts),
+        ProgramInstruction::SetUserCredits { credits } => set_user_credits(credits, accounts),
+        ProgramInstruction::UpdateCreditsPerLevel { credits_per_level, effective_slot } => update_credits_per_level(credits_per_level, effective_slot, accounts),
+        ProgramInstruction::BatchLevelUp { credits_to_burn } => batch_level_up(credits_to_burn, accounts),
+        ProgramInstruction::WithdrawConfigLamports { amount } => withdraw_config_lamports(amount, accounts),
+        ProgramInstruction::GetUser { } => get_user(accounts)
     }
 }
@@ -4,21 +4,26 @@ use solana_program::{
         next_account_info,
         AccountInfo
     },
+    clock::Clock,
     entrypoint::ProgramResult,
-    program::invoke_signed,
+    msg,
+    program::{invoke, invoke_signed},
     program_error::ProgramError,
     pubkey::Pubkey,
     rent::Rent,
-    system_instruction::create_account, 
+    system_instruction::{create_account, transfer},
+    sysvar::Sysvar,
 };
 use crate::{
     state::*,
-    constants::*
+    constants::*,
+    error::GameError
 };
 
-/// Create a new game configuration account and set credits per level
+/// Create a new game configuration account and set credits per level and level cap
 pub fn create_game_config(
     credits_per_level: u8,
+    max_level: u8,
     accounts: &[AccountInfo]
 ) -> ProgramResult {
     let accounts_iter = &mut accounts.iter();
@@ -57,7 +62,8 @@ pub fn create_game_config(
     )?;
 
     let game_config = GameConfig::new(
-        credits_per_level
+        credits_per_level,
+        max_level
     );
 
     game_config.serialize(&mut game_config_info.try_borrow_mut_data()?.as_mut())?;
@@ -111,6 +117,10 @@ pub fn create_user(
 
     user.serialize(&mut user_info.try_borrow_mut_data()?.as_mut())?;
 
+    let mut game_config = GameConfig::unpack(&game_config_info.try_borrow_data()?)?;
+    game_config.user_count = game_config.user_count.checked_add(1).ok_or(GameError::ArithmeticOverflow)?;
+    save_account(&game_config, std::mem::size_of::<GameConfig>(), game_config_info, authority_info, system_program_info)?;
+
     Ok(())
 }
 
@@ -124,6 +134,7 @@ pub fn mint_credits_to_user(
     let game_config_info = next_account_info(accounts_iter)?;
     let user_info = next_account_info(accounts_iter)?;
     let admin_info = next_account_info(accounts_iter)?;
+    let system_program_info = next_account_info(accounts_iter)?;
 
     assert_eq!(game_config_info.try_get_type()?, AccountType::GameConfig);
     assert_eq!(user_info.try_get_type()?, AccountType::User);
@@ -137,12 +148,12 @@ pub fn mint_credits_to_user(
 
     assert_eq!(game_config_info.key, &game_config_pubkey);
 
-    let mut user = User::deserialize(&mut user_info.try_borrow_mut_data()?.as_ref())?;
+    let mut user = User::unpack(&user_info.try_borrow_data()?)?;
 
     assert_eq!(&user.game_config, game_config_info.key);
 
     user.credits += credits;
-    user.serialize(&mut user_info.try_borrow_mut_data()?.as_mut())?;
+    save_account(&user, std::mem::size_of::<User>(), user_info, admin_info, system_program_info)?;
 
     Ok(())
 }
@@ -157,41 +168,840 @@ pub fn user_level_up(
     let game_config_info = next_account_info(accounts_iter)?;
     let user_info = next_account_info(accounts_iter)?;
     let authority_info = next_account_info(accounts_iter)?;
+    let system_program_info = next_account_info(accounts_iter)?;
 
     assert_eq!(game_config_info.try_get_type()?, AccountType::GameConfig);
     assert_eq!(user_info.try_get_type()?, AccountType::User);
 
-    let game_config = GameConfig::deserialize(&mut game_config_info.try_borrow_data()?.as_ref())?;
-    let mut user = User::deserialize(&mut user_info.try_borrow_data()?.as_ref())?;
+    let game_config = GameConfig::unpack(&game_config_info.try_borrow_data()?)?;
+    let mut user = User::unpack(&user_info.try_borrow_data()?)?;
 
     assert_eq!(authority_info.signer_key().ok_or(ProgramError::MissingRequiredSignature)?, &user.authority);
 
-    let mut iterator: u8 = user.level; 
-    let mut level_credits = iterator as u32 * game_config.credits_per_level as u32;
+    if user.frozen {
+        return Err(GameError::UserFrozen.into())
+    }
+
+    if user.level >= game_config.max_level {
+        return Err(GameError::MaxLevelReached.into())
+    }
+
+    // Users still at level 0 have never leveled up
+    let slot = Clock::get()?.slot;
+    if user.level > 0 && slot < user.last_level_up_slot.saturating_add(game_config.level_up_cooldown_slots) {
+        return Err(GameError::CooldownActive.into())
+    }
+
+    let (iterator, level_credits) = level_up_target(
+        user.level,
+        game_config.max_level,
+        game_config.credits_per_level_at(slot),
+        credits_to_burn
+    );
+
+    user.credits -= level_credits;
+    
+    if !(user.credits > 0) {
+        return Err(ProgramError::InsufficientFunds)
+    }
+
+    user.level = iterator;
+    user.last_level_up_slot = slot;
+
+    save_account(&user, std::mem::size_of::<User>(), user_info, authority_info, system_program_info)?;
+
+    if let Ok(leaderboard_info) = next_account_info(accounts_iter) {
+        let mut leaderboard = load_leaderboard(leaderboard_info, game_config_info)?;
+        leaderboard.update(user_info.key, user.level);
+        leaderboard.serialize(&mut leaderboard_info.try_borrow_mut_data()?.as_mut())?;
+    }
+
+    Ok(())
+}
+
+/// Level up several users of a game configuration as its admin, burning
+/// `credits_to_burn` for each. Frozen users, users at the level cap or in
+/// their cooldown and users without enough credits are logged and skipped.
+/// The leaderboard is not updated
+pub fn batch_level_up(
+    credits_to_burn: u32,
+    accounts: &[AccountInfo]
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let game_config_info = next_account_info(accounts_iter)?;
+    let admin_info = next_account_info(accounts_iter)?;
+    let system_program_info = next_account_info(accounts_iter)?;
+    let user_infos = accounts_iter.as_slice();
+
+    if user_infos.len() > MAX_BATCH_LEVEL_UP {
+        return Err(GameError::BatchTooLarge.into())
+    }
+
+    assert_eq!(game_config_info.try_get_type()?, AccountType::GameConfig);
+
+    let (game_config_pubkey, _) = Pubkey::find_program_address(&[
+        admin_info.signer_key().ok_or(ProgramError::MissingRequiredSignature)?.as_ref(),
+        GAME_CONFIG_SEED
+    ],
+    &crate::id()
+    );
+
+    assert_eq!(game_config_info.key, &game_config_pubkey);
+
+    let game_config = GameConfig::unpack(&game_config_info.try_borrow_data()?)?;
+    let slot = Clock::get()?.slot;
+    let credits_per_level = game_config.credits_per_level_at(slot);
+
+    for user_info in user_infos {
+        assert_eq!(user_info.try_get_type()?, AccountType::User);
+
+        let mut user = User::unpack(&user_info.try_borrow_data()?)?;
+
+        let (user_pubkey, _) = Pubkey::find_program_address(&[
+                game_config_info.key.as_ref(),
+                user.authority.as_ref(),
+                USER_SEED
+            ],
+            &crate::id()
+        );
+
+        assert_eq!(user_info.key, &user_pubkey);
+        assert_eq!(&user.game_config, game_config_info.key);
+
+        if user.frozen || user.level >= game_config.max_level
+            || (user.level > 0 && slot < user.last_level_up_slot.saturating_add(game_config.level_up_cooldown_slots)) {
+            msg!("batch_level_up: skipping {}, can't level up", user_info.key);
+            continue;
+        }
+
+        let (level, level_credits) = level_up_target(
+            user.level,
+            game_config.max_level,
+            credits_per_level,
+            credits_to_burn
+        );
+
+        if user.credits <= level_credits {
+            msg!("batch_level_up: skipping {}, insufficient credits", user_info.key);
+            continue;
+        }
+
+        user.credits -= level_credits;
+        user.level = level;
+        user.last_level_up_slot = slot;
+
+        save_account(&user, std::mem::size_of::<User>(), user_info, admin_info, system_program_info)?;
+    }
+
+    Ok(())
+}
+
+/// Send lamports held by a game configuration account beyond its rent-exempt
+/// minimum to `destination` as game configuration admin
+pub fn withdraw_config_lamports(
+    amount: u64,
+    accounts: &[AccountInfo]
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let game_config_info = next_account_info(accounts_iter)?;
+    let admin_info = next_account_info(accounts_iter)?;
+    let destination_info = next_account_info(accounts_iter)?;
+
+    assert_eq!(game_config_info.try_get_type()?, AccountType::GameConfig);
+
+    let (game_config_pubkey, _) = Pubkey::find_program_address(&[
+        admin_info.signer_key().ok_or(ProgramError::MissingRequiredSignature)?.as_ref(),
+        GAME_CONFIG_SEED
+    ],
+    &crate::id()
+    );
+
+    assert_eq!(game_config_info.key, &game_config_pubkey);
+
+    let minimum_balance = Rent::default().minimum_balance(game_config_info.data_len());
+    let available = game_config_info.lamports().saturating_sub(minimum_balance);
+    if amount > available {
+        return Err(ProgramError::InsufficientFunds)
+    }
+
+    **game_config_info.try_borrow_mut_lamports()? -= amount;
+    **destination_info.try_borrow_mut_lamports()? = destination_info
+        .lamports()
+        .checked_add(amount)
+        .ok_or(GameError::ArithmeticOverflow)?;
+
+    msg!("withdraw_config_lamports: {} to {}", amount, destination_info.key);
+
+    Ok(())
+}
+
+/// Log the game configuration derived from the given admin
+pub fn get_config(
+    accounts: &[AccountInfo]
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let game_config_info = next_account_info(accounts_iter)?;
+    let admin_info = next_account_info(accounts_iter)?;
+
+    assert_eq!(game_config_info.try_get_type()?, AccountType::GameConfig);
+
+    let (game_config_pubkey, _) = Pubkey::find_program_address(&[
+        admin_info.key.as_ref(),
+        GAME_CONFIG_SEED
+    ],
+    &crate::id()
+    );
+
+    assert_eq!(game_config_info.key, &game_config_pubkey);
+
+    let game_config = GameConfig::unpack(&game_config_info.try_borrow_data()?)?;
+
+    msg!("admin: {}", admin_info.key);
+    msg!("credits_per_level: {}", game_config.credits_per_level_at(Clock::get()?.slot));
+    msg!("max_level: {}", game_config.max_level);
+
+    Ok(())
+}
+
+/// Close a user account as user authority and return its lamports. The
+/// account is dropped from the leaderboard when one is passed in
+pub fn close_user(
+    accounts: &[AccountInfo]
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let game_config_info = next_account_info(accounts_iter)?;
+    let user_info = next_account_info(accounts_iter)?;
+    let authority_info = next_account_info(accounts_iter)?;
+    let system_program_info = next_account_info(accounts_iter)?;
+
+    assert_eq!(game_config_info.try_get_type()?, AccountType::GameConfig);
+    assert_eq!(user_info.try_get_type()?, AccountType::User);
+
+    let user = User::unpack(&user_info.try_borrow_data()?)?;
+
+    assert_eq!(authority_info.signer_key().ok_or(ProgramError::MissingRequiredSignature)?, &user.authority);
+    assert_eq!(&user.game_config, game_config_info.key);
+
+    let mut game_config = GameConfig::unpack(&game_config_info.try_borrow_data()?)?;
+    game_config.user_count = game_config.user_count.checked_sub(1).ok_or(GameError::ArithmeticOverflow)?;
+    save_account(&game_config, std::mem::size_of::<GameConfig>(), game_config_info, authority_info, system_program_info)?;
+
+    let lamports = user_info.lamports();
+    **user_info.try_borrow_mut_lamports()? = 0;
+    **authority_info.try_borrow_mut_lamports()? = authority_info
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(GameError::ArithmeticOverflow)?;
+    user_info.try_borrow_mut_data()?.fill(0);
+
+    if let Ok(leaderboard_info) = next_account_info(accounts_iter) {
+        remove_from_leaderboard(leaderboard_info, game_config_info, user_info.key)?;
+    }
+
+    Ok(())
+}
+
+/// Move credits from a user account to the reclaimed pool as game configuration admin
+pub fn reclaim_credits(
+    amount: u32,
+    accounts: &[AccountInfo]
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let game_config_info = next_account_info(accounts_iter)?;
+    let user_info = next_account_info(accounts_iter)?;
+    let admin_info = next_account_info(accounts_iter)?;
+    let system_program_info = next_account_info(accounts_iter)?;
+
+    assert_eq!(game_config_info.try_get_type()?, AccountType::GameConfig);
+    assert_eq!(user_info.try_get_type()?, AccountType::User);
+
+    let (game_config_pubkey, _) = Pubkey::find_program_address(&[
+        admin_info.signer_key().ok_or(ProgramError::MissingRequiredSignature)?.as_ref(),
+        GAME_CONFIG_SEED
+    ],
+    &crate::id()
+    );
+
+    assert_eq!(game_config_info.key, &game_config_pubkey);
+
+    let mut user = User::unpack(&user_info.try_borrow_data()?)?;
+
+    let (user_pubkey, _) = Pubkey::find_program_address(&[
+            game_config_info.key.as_ref(),
+            user.authority.as_ref(),
+            USER_SEED
+        ],
+        &crate::id()
+    );
+
+    assert_eq!(user_info.key, &user_pubkey);
+    assert_eq!(&user.game_config, game_config_info.key);
+
+    user.credits = user.credits.checked_sub(amount).ok_or(ProgramError::InsufficientFunds)?;
+    save_account(&user, std::mem::size_of::<User>(), user_info, admin_info, system_program_info)?;
+
+    let mut game_config = GameConfig::unpack(&game_config_info.try_borrow_data()?)?;
+    game_config.reclaimed_pool = game_config.reclaimed_pool.checked_add(amount.into()).ok_or(GameError::ArithmeticOverflow)?;
+    save_account(&game_config, std::mem::size_of::<GameConfig>(), game_config_info, admin_info, system_program_info)?;
+
+    Ok(())
+}
+
+/// Set the minimum number of slots between level-ups as game configuration admin
+pub fn set_level_up_cooldown(
+    slots: u64,
+    accounts: &[AccountInfo]
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let game_config_info = next_account_info(accounts_iter)?;
+    let admin_info = next_account_info(accounts_iter)?;
+    let system_program_info = next_account_info(accounts_iter)?;
+
+    assert_eq!(game_config_info.try_get_type()?, AccountType::GameConfig);
+
+    let (game_config_pubkey, _) = Pubkey::find_program_address(&[
+        admin_info.signer_key().ok_or(ProgramError::MissingRequiredSignature)?.as_ref(),
+        GAME_CONFIG_SEED
+    ],
+    &crate::id()
+    );
+
+    assert_eq!(game_config_info.key, &game_config_pubkey);
+
+    let mut game_config = GameConfig::unpack(&game_config_info.try_borrow_data()?)?;
+    game_config.level_up_cooldown_slots = slots;
+    save_account(&game_config, std::mem::size_of::<GameConfig>(), game_config_info, admin_info, system_program_info)?;
+
+    Ok(())
+}
+
+/// Move the credits of one user to another as game configuration admin.
+/// A closed source returns its lamports to its own authority, who paid its
+/// rent, and is dropped from the leaderboard when one is passed in
+pub fn merge_users(
+    close_source: bool,
+    accounts: &[AccountInfo]
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let game_config_info = next_account_info(accounts_iter)?;
+    let source_info = next_account_info(accounts_iter)?;
+    let dest_info = next_account_info(accounts_iter)?;
+    let admin_info = next_account_info(accounts_iter)?;
+    let system_program_info = next_account_info(accounts_iter)?;
+
+    assert_eq!(game_config_info.try_get_type()?, AccountType::GameConfig);
+    assert_eq!(source_info.try_get_type()?, AccountType::User);
+    assert_eq!(dest_info.try_get_type()?, AccountType::User);
+
+    let (game_config_pubkey, _) = Pubkey::find_program_address(&[
+        admin_info.signer_key().ok_or(ProgramError::MissingRequiredSignature)?.as_ref(),
+        GAME_CONFIG_SEED
+    ],
+    &crate::id()
+    );
+
+    assert_eq!(game_config_info.key, &game_config_pubkey);
+
+    // Merging a user into itself would only zero it
+    if source_info.key == dest_info.key {
+        return Err(ProgramError::InvalidArgument)
+    }
+
+    let mut source = User::unpack(&source_info.try_borrow_data()?)?;
+    let mut dest = User::unpack(&dest_info.try_borrow_data()?)?;
+
+    for (user_info, user) in [(source_info, &source), (dest_info, &dest)] {
+        let (user_pubkey, _) = Pubkey::find_program_address(&[
+                game_config_info.key.as_ref(),
+                user.authority.as_ref(),
+                USER_SEED
+            ],
+            &crate::id()
+        );
+
+        assert_eq!(user_info.key, &user_pubkey);
+        assert_eq!(&user.game_config, game_config_info.key);
+
+        if user.frozen {
+            return Err(GameError::UserFrozen.into())
+        }
+    }
+
+    dest.credits = dest.credits.checked_add(source.credits).ok_or(GameError::ArithmeticOverflow)?;
+    source.credits = 0;
+    save_account(&dest, std::mem::size_of::<User>(), dest_info, admin_info, system_program_info)?;
+
+    if !close_source {
+        save_account(&source, std::mem::size_of::<User>(), source_info, admin_info, system_program_info)?;
+        return Ok(())
+    }
+
+    let mut game_config = GameConfig::unpack(&game_config_info.try_borrow_data()?)?;
+    game_config.user_count = game_config.user_count.checked_sub(1).ok_or(GameError::ArithmeticOverflow)?;
+    save_account(&game_config, std::mem::size_of::<GameConfig>(), game_config_info, admin_info, system_program_info)?;
+
+    let source_authority_info = next_account_info(accounts_iter)?;
+    assert_eq!(source_authority_info.key, &source.authority);
+
+    let lamports = source_info.lamports();
+    **source_info.try_borrow_mut_lamports()? = 0;
+    **source_authority_info.try_borrow_mut_lamports()? = source_authority_info
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(GameError::ArithmeticOverflow)?;
+    source_info.try_borrow_mut_data()?.fill(0);
+
+    if let Ok(leaderboard_info) = next_account_info(accounts_iter) {
+        remove_from_leaderboard(leaderboard_info, game_config_info, source_info.key)?;
+    }
+
+    Ok(())
+}
+
+/// Create the leaderboard of a game configuration as its admin
+pub fn create_leaderboard(
+    accounts: &[AccountInfo]
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let leaderboard_info = next_account_info(accounts_iter)?;
+    let game_config_info = next_account_info(accounts_iter)?;
+    let admin_info = next_account_info(accounts_iter)?;
+    let system_program_info = next_account_info(accounts_iter)?;
+
+    assert_eq!(game_config_info.try_get_type()?, AccountType::GameConfig);
+
+    let (game_config_pubkey, _) = Pubkey::find_program_address(&[
+        admin_info.signer_key().ok_or(ProgramError::MissingRequiredSignature)?.as_ref(),
+        GAME_CONFIG_SEED
+    ],
+    &crate::id()
+    );
+
+    assert_eq!(game_config_info.key, &game_config_pubkey);
+
+    let (leaderboard_pubkey, bump) = Pubkey::find_program_address(&[
+            game_config_info.key.as_ref(),
+            LEADERBOARD_SEED
+        ],
+        &crate::id()
+    );
+
+    assert_eq!(leaderboard_info.key, &leaderboard_pubkey);
+
+    if !leaderboard_info.try_data_is_empty()? {
+        return Err(ProgramError::AccountAlreadyInitialized)
+    }
+
+    invoke_signed(
+        &create_account(
+            admin_info.key,
+            leaderboard_info.key,
+            Rent::default().minimum_balance(Leaderboard::LEN),
+            Leaderboard::LEN.try_into().map_err(|_| ProgramError::MaxAccountsDataAllocationsExceeded)?,
+            &crate::id()
+        ),
+        &[
+            admin_info.clone(),
+            leaderboard_info.clone(),
+            system_program_info.clone()
+        ],
+        &[&[game_config_info.key.as_ref(), LEADERBOARD_SEED, &[bump]]]
+    )?;
+
+    let leaderboard = Leaderboard::new(game_config_info.key);
+
+    leaderboard.serialize(&mut leaderboard_info.try_borrow_mut_data()?.as_mut())?;
+
+    Ok(())
+}
+
+/// Freeze or unfreeze a user account as the admin of its game configuration
+pub fn set_user_frozen(
+    frozen: bool,
+    accounts: &[AccountInfo]
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let game_config_info = next_account_info(accounts_iter)?;
+    let user_info = next_account_info(accounts_iter)?;
+    let admin_info = next_account_info(accounts_iter)?;
+    let system_program_info = next_account_info(accounts_iter)?;
+
+    assert_eq!(game_config_info.try_get_type()?, AccountType::GameConfig);
+    assert_eq!(user_info.try_get_type()?, AccountType::User);
+
+    let (game_config_pubkey, _) = Pubkey::find_program_address(&[
+        admin_info.signer_key().ok_or(ProgramError::MissingRequiredSignature)?.as_ref(),
+        GAME_CONFIG_SEED
+    ],
+    &crate::id()
+    );
+
+    assert_eq!(game_config_info.key, &game_config_pubkey);
+
+    let mut user = User::unpack(&user_info.try_borrow_data()?)?;
+
+    let (user_pubkey, _) = Pubkey::find_program_address(&[
+            game_config_info.key.as_ref(),
+            user.authority.as_ref(),
+            USER_SEED
+        ],
+        &crate::id()
+    );
+
+    assert_eq!(user_info.key, &user_pubkey);
+    assert_eq!(&user.game_config, game_config_info.key);
+
+    user.frozen = frozen;
+    save_account(&user, std::mem::size_of::<User>(), user_info, admin_info, system_program_info)?;
+
+    Ok(())
+}
+
+/// Overwrite the credits of a user account as game configuration admin
+pub fn set_user_credits(
+    credits: u32,
+    accounts: &[AccountInfo]
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let game_config_info = next_account_info(accounts_iter)?;
+    let user_info = next_account_info(accounts_iter)?;
+    let admin_info = next_account_info(accounts_iter)?;
+    let system_program_info = next_account_info(accounts_iter)?;
+
+    assert_eq!(game_config_info.try_get_type()?, AccountType::GameConfig);
+    assert_eq!(user_info.try_get_type()?, AccountType::User);
+
+    let (game_config_pubkey, _) = Pubkey::find_program_address(&[
+        admin_info.signer_key().ok_or(ProgramError::MissingRequiredSignature)?.as_ref(),
+        GAME_CONFIG_SEED
+    ],
+    &crate::id()
+    );
+
+    assert_eq!(game_config_info.key, &game_config_pubkey);
+
+    let mut user = User::unpack(&user_info.try_borrow_data()?)?;
+
+    let (user_pubkey, _) = Pubkey::find_program_address(&[
+            game_config_info.key.as_ref(),
+            user.authority.as_ref(),
+            USER_SEED
+        ],
+        &crate::id()
+    );
+
+    assert_eq!(user_info.key, &user_pubkey);
+    assert_eq!(&user.game_config, game_config_info.key);
+
+    msg!("set_user_credits: {} -> {}", user.credits, credits);
+    user.credits = credits;
+    save_account(&user, std::mem::size_of::<User>(), user_info, admin_info, system_program_info)?;
+
+    Ok(())
+}
+
+/// Log the credits a user account needs for its next level
+pub fn next_level_cost(
+    accounts: &[AccountInfo]
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let game_config_info = next_account_info(accounts_iter)?;
+    let admin_info = next_account_info(accounts_iter)?;
+    let user_info = next_account_info(accounts_iter)?;
+
+    assert_eq!(game_config_info.try_get_type()?, AccountType::GameConfig);
+    assert_eq!(user_info.try_get_type()?, AccountType::User);
+
+    let (game_config_pubkey, _) = Pubkey::find_program_address(&[
+        admin_info.key.as_ref(),
+        GAME_CONFIG_SEED
+    ],
+    &crate::id()
+    );
+
+    assert_eq!(game_config_info.key, &game_config_pubkey);
+
+    let game_config = GameConfig::unpack(&game_config_info.try_borrow_data()?)?;
+    let user = User::unpack(&user_info.try_borrow_data()?)?;
+
+    let (user_pubkey, _) = Pubkey::find_program_address(&[
+            game_config_info.key.as_ref(),
+            user.authority.as_ref(),
+            USER_SEED
+        ],
+        &crate::id()
+    );
+
+    assert_eq!(user_info.key, &user_pubkey);
+    assert_eq!(&user.game_config, game_config_info.key);
+
+    let cost = (user.level as u32 + 1)
+        .checked_mul(game_config.credits_per_level_at(Clock::get()?.slot) as u32)
+        .ok_or(GameError::ArithmeticOverflow)?;
+
+    msg!("next_level_cost: {}", cost);
+
+    Ok(())
+}
+
+/// Change the credits per level as game configuration admin, from
+/// `effective_slot` on or right away, recording the change in the game
+/// configuration's credits history. The history account is created on first use
+pub fn update_credits_per_level(
+    credits_per_level: u8,
+    effective_slot: Option<u64>,
+    accounts: &[AccountInfo]
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let game_config_info = next_account_info(accounts_iter)?;
+    let credits_history_info = next_account_info(accounts_iter)?;
+    let admin_info = next_account_info(accounts_iter)?;
+    let system_program_info = next_account_info(accounts_iter)?;
+
+    assert_eq!(game_config_info.try_get_type()?, AccountType::GameConfig);
+
+    let (game_config_pubkey, _) = Pubkey::find_program_address(&[
+        admin_info.signer_key().ok_or(ProgramError::MissingRequiredSignature)?.as_ref(),
+        GAME_CONFIG_SEED
+    ],
+    &crate::id()
+    );
+
+    assert_eq!(game_config_info.key, &game_config_pubkey);
+
+    let (credits_history_pubkey, bump) = Pubkey::find_program_address(&[
+            game_config_info.key.as_ref(),
+            CREDITS_HISTORY_SEED
+        ],
+        &crate::id()
+    );
+
+    assert_eq!(credits_history_info.key, &credits_history_pubkey);
+
+    let mut credits_history = if credits_history_info.try_data_is_empty()? {
+        invoke_signed(
+            &create_account(
+                admin_info.key,
+                credits_history_info.key,
+                Rent::default().minimum_balance(CreditsHistory::LEN),
+                CreditsHistory::LEN.try_into().map_err(|_| ProgramError::MaxAccountsDataAllocationsExceeded)?,
+                &crate::id()
+            ),
+            &[
+                admin_info.clone(),
+                credits_history_info.clone(),
+                system_program_info.clone()
+            ],
+            &[&[game_config_info.key.as_ref(), CREDITS_HISTORY_SEED, &[bump]]]
+        )?;
+        CreditsHistory::new(game_config_info.key)
+    } else {
+        assert_eq!(credits_history_info.try_get_type()?, AccountType::CreditsHistory);
+        CreditsHistory::deserialize(&mut credits_history_info.try_borrow_data()?.as_ref())?
+    };
+
+    let slot = Clock::get()?.slot;
+    let mut game_config = GameConfig::unpack(&game_config_info.try_borrow_data()?)?;
+    let old_credits_per_level = game_config.credits_per_level_at(slot);
+
+    // A pending change that is already in effect becomes the current value,
+    // a new change always replaces one still pending
+    game_config.credits_per_level = old_credits_per_level;
+    let effective_slot = match effective_slot {
+        Some(effective_slot) if effective_slot > slot => {
+            game_config.pending_credits_per_level = credits_per_level;
+            game_config.pending_effective_slot = effective_slot;
+            effective_slot
+        }
+        _ => {
+            game_config.credits_per_level = credits_per_level;
+            game_config.pending_credits_per_level = 0;
+            game_config.pending_effective_slot = 0;
+            slot
+        }
+    };
+    save_account(&game_config, std::mem::size_of::<GameConfig>(), game_config_info, admin_info, system_program_info)?;
+
+    credits_history.record(CreditsChange {
+        slot,
+        old_credits_per_level,
+        new_credits_per_level: credits_per_level,
+        effective_slot
+    });
+    credits_history.serialize(&mut credits_history_info.try_borrow_mut_data()?.as_mut())?;
+
+    Ok(())
+}
+
+/// Log the leaderboard rank of a user account
+pub fn get_user_rank(
+    accounts: &[AccountInfo]
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let game_config_info = next_account_info(accounts_iter)?;
+    let leaderboard_info = next_account_info(accounts_iter)?;
+    let user_info = next_account_info(accounts_iter)?;
+
+    assert_eq!(game_config_info.try_get_type()?, AccountType::GameConfig);
+    assert_eq!(user_info.try_get_type()?, AccountType::User);
+
+    let leaderboard = load_leaderboard(leaderboard_info, game_config_info)?;
+    let user = User::unpack(&user_info.try_borrow_data()?)?;
+
+    let (user_pubkey, _) = Pubkey::find_program_address(&[
+            game_config_info.key.as_ref(),
+            user.authority.as_ref(),
+            USER_SEED
+        ],
+        &crate::id()
+    );
+
+    assert_eq!(user_info.key, &user_pubkey);
+    assert_eq!(&user.game_config, game_config_info.key);
+
+    msg!("rank: {}", leaderboard.rank_of(user_info.key));
+
+    Ok(())
+}
+
+/// Log all fields of a user account on one line
+pub fn get_user(
+    accounts: &[AccountInfo]
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+
+    let game_config_info = next_account_info(accounts_iter)?;
+    let user_info = next_account_info(accounts_iter)?;
+
+    assert_eq!(game_config_info.try_get_type()?, AccountType::GameConfig);
+    assert_eq!(user_info.try_get_type()?, AccountType::User);
+
+    let user = User::unpack(&user_info.try_borrow_data()?)?;
+
+    let (user_pubkey, _) = Pubkey::find_program_address(&[
+            game_config_info.key.as_ref(),
+            user.authority.as_ref(),
+            USER_SEED
+        ],
+        &crate::id()
+    );
+
+    assert_eq!(user_info.key, &user_pubkey);
+    assert_eq!(&user.game_config, game_config_info.key);
+
+    msg!(
+        "user: authority={} credits={} level={} last_level_up_slot={} frozen={}",
+        user.authority,
+        user.credits,
+        user.level,
+        user.last_level_up_slot,
+        user.frozen
+    );
+
+    Ok(())
+}
+
+/// Drop a closed user account from the leaderboard of its game configuration
+fn remove_from_leaderboard(
+    leaderboard_info: &AccountInfo,
+    game_config_info: &AccountInfo,
+    user: &Pubkey
+) -> ProgramResult {
+    let mut leaderboard = load_leaderboard(leaderboard_info, game_config_info)?;
+    leaderboard.remove(user);
+    leaderboard.serialize(&mut leaderboard_info.try_borrow_mut_data()?.as_mut())?;
+    Ok(())
+}
+
+/// Deserialize the leaderboard of a game configuration, checking its address
+fn load_leaderboard(
+    leaderboard_info: &AccountInfo,
+    game_config_info: &AccountInfo
+) -> Result<Leaderboard, ProgramError> {
+    assert_eq!(leaderboard_info.try_get_type()?, AccountType::Leaderboard);
+
+    let (leaderboard_pubkey, _) = Pubkey::find_program_address(&[
+            game_config_info.key.as_ref(),
+            LEADERBOARD_SEED
+        ],
+        &crate::id()
+    );
+
+    assert_eq!(leaderboard_info.key, &leaderboard_pubkey);
+
+    let leaderboard = Leaderboard::deserialize(&mut leaderboard_info.try_borrow_data()?.as_ref())?;
+
+    assert_eq!(&leaderboard.game_config, game_config_info.key);
+
+    Ok(leaderboard)
+}
+
+/// Level reached from `level` by burning up to `credits_to_burn`, and the
+/// credits that costs
+fn level_up_target(
+    level: u8,
+    max_level: u8,
+    credits_per_level: u8,
+    credits_to_burn: u32
+) -> (u8, u32) {
+    let mut iterator: u8 = level;
+    let mut level_credits = iterator as u32 * credits_per_level as u32;
     let mut next_level_credits = level_credits;
     let mut stop = false;
-    
+
     while next_level_credits < credits_to_burn && !stop {
         level_credits = next_level_credits;
-        
-        if iterator < MAX_LEVEL {
-            iterator += 1; 
-            next_level_credits += iterator as u32 * game_config.credits_per_level as u32;
+
+        if iterator < max_level {
+            iterator += 1;
+            next_level_credits += iterator as u32 * credits_per_level as u32;
         } else {
             stop = true;
         }
 
     }
 
-    user.credits -= level_credits;
-    
-    if !(user.credits > 0) {
-        return Err(ProgramError::InsufficientFunds)
-    }
+    (iterator, level_credits)
+}
 
-    user.level = iterator;
+/// Write an account back, growing accounts created with an older, shorter
+/// layout to `size` and topping up their rent from the payer
+fn save_account<'a, T: BorshSerialize>(
+    value: &T,
+    size: usize,
+    account_info: &AccountInfo<'a>,
+    payer_info: &AccountInfo<'a>,
+    system_program_info: &AccountInfo<'a>
+) -> ProgramResult {
+    if account_info.data_len() < size {
+        let missing_rent = Rent::default().minimum_balance(size).saturating_sub(account_info.lamports());
+        if missing_rent > 0 {
+            invoke(
+                &transfer(payer_info.key, account_info.key, missing_rent),
+                &[
+                    payer_info.clone(),
+                    account_info.clone(),
+                    system_program_info.clone()
+                ]
+            )?;
+        }
+        account_info.realloc(size, true)?;
+    }
 
-    user.serialize(&mut user_info.try_borrow_mut_data()?.as_mut())?;
+    value.serialize(&mut account_info.try_borrow_mut_data()?.as_mut())?;
 
     Ok(())
 }
\ No newline at end of file
@@ -14,18 +14,92 @@ pub struct GameConfig {
     account_type: AccountType,
 
     /// credits per level
-    pub credits_per_level: u8
+    pub credits_per_level: u8,
+
+    /// number of open user accounts in this game
+    pub user_count: u64,
+
+    /// highest level a user can reach
+    pub max_level: u8,
+
+    /// credits reclaimed from users by the admin
+    pub reclaimed_pool: u64,
+
+    /// minimum number of slots between two level-ups of a user
+    pub level_up_cooldown_slots: u64,
+
+    /// credits per level taking over at `pending_effective_slot`
+    pub pending_credits_per_level: u8,
+
+    /// slot from which `pending_credits_per_level` applies, 0 when no
+    /// change is pending
+    pub pending_effective_slot: u64
 }
 
 impl GameConfig {
     /// Create a new game configuration account
     pub fn new(
-        credits_per_level: u8
+        credits_per_level: u8,
+        max_level: u8
     ) -> Self {
         Self {
             account_type: AccountType::GameConfig,
-            credits_per_level
+            credits_per_level,
+            user_count: 0,
+            max_level,
+            reclaimed_pool: 0,
+            level_up_cooldown_slots: 0,
+            pending_credits_per_level: 0,
+            pending_effective_slot: 0
+        }
+    }
+
+    /// Credits per level in effect at `slot`, counting a pending change
+    /// whose slot has been reached
+    pub fn credits_per_level_at(&self, slot: u64) -> u8 {
+        if self.pending_effective_slot != 0 && slot >= self.pending_effective_slot {
+            self.pending_credits_per_level
+        } else {
+            self.credits_per_level
+        }
+    }
+
+    /// Deserialize a game configuration, accepting accounts created before
+    /// `user_count` and `max_level`, or later `reclaimed_pool`,
+    /// `level_up_cooldown_slots` and the pending credits per level, were
+    /// added. Missing fields take their defaults
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        let data = &mut &data[..];
+        let mut game_config = Self {
+            account_type: AccountType::deserialize(data)?,
+            credits_per_level: u8::deserialize(data)?,
+            user_count: 0,
+            max_level: crate::constants::MAX_LEVEL,
+            reclaimed_pool: 0,
+            level_up_cooldown_slots: 0,
+            pending_credits_per_level: 0,
+            pending_effective_slot: 0
+        };
+
+        if data.is_empty() {
+            return Ok(game_config)
+        }
+        game_config.user_count = u64::deserialize(data)?;
+        game_config.max_level = u8::deserialize(data)?;
+
+        // Older accounts end in padding too short to hold the next field
+        if data.len() >= std::mem::size_of::<u64>() {
+            game_config.reclaimed_pool = u64::deserialize(data)?;
         }
+        if data.len() >= std::mem::size_of::<u64>() {
+            game_config.level_up_cooldown_slots = u64::deserialize(data)?;
+        }
+        if data.len() >= std::mem::size_of::<u8>() + std::mem::size_of::<u64>() {
+            game_config.pending_credits_per_level = u8::deserialize(data)?;
+            game_config.pending_effective_slot = u64::deserialize(data)?;
+        }
+
+        Ok(game_config)
     }
 }
 
@@ -45,7 +119,13 @@ pub struct User {
     pub credits: u32,
     
     /// user level
-    pub level: u8
+    pub level: u8,
+
+    /// slot of the last level-up
+    pub last_level_up_slot: u64,
+
+    /// set by the admin to block level-ups and merges
+    pub frozen: bool
 }
 
 impl User {
@@ -61,6 +141,145 @@ impl User {
             ..User::default()
         }
     }
+
+    /// Deserialize a user, accepting accounts created before
+    /// `last_level_up_slot` or `frozen` were added
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        let data = &mut &data[..];
+        let mut user = Self {
+            account_type: AccountType::deserialize(data)?,
+            authority: Pubkey::deserialize(data)?,
+            game_config: Pubkey::deserialize(data)?,
+            credits: u32::deserialize(data)?,
+            level: u8::deserialize(data)?,
+            last_level_up_slot: 0,
+            frozen: false
+        };
+
+        // Older accounts end in padding too short to hold the slot
+        if data.len() >= std::mem::size_of::<u64>() {
+            user.last_level_up_slot = u64::deserialize(data)?;
+
+            // Zeroed padding in accounts created before `frozen`
+            if !data.is_empty() {
+                user.frozen = bool::deserialize(data)?;
+            }
+        }
+
+        Ok(user)
+    }
+}
+
+/// Account ranking the highest level users of a game configuration
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct Leaderboard {
+    account_type: AccountType,
+
+    /// address of the game config this leaderboard ranks
+    pub game_config: Pubkey,
+
+    /// ranked user accounts, highest level first
+    pub entries: Vec<LeaderboardEntry>
+}
+
+/// A ranked user account and its level
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct LeaderboardEntry {
+    pub user: Pubkey,
+    pub level: u8
+}
+
+impl Leaderboard {
+    /// Serialized size of a full leaderboard
+    pub const LEN: usize = 1 + 32 + 4 + crate::constants::LEADERBOARD_SIZE * (32 + 1);
+
+    /// Create a new, empty Leaderboard account
+    pub fn new(
+        game_config: &Pubkey
+    ) -> Self {
+        Self {
+            account_type: AccountType::Leaderboard,
+            game_config: *game_config,
+            entries: Vec::new()
+        }
+    }
+
+    /// Move a user account to its place for `level`, after users already at
+    /// that level, dropping whoever falls off the end
+    pub fn update(&mut self, user: &Pubkey, level: u8) {
+        self.entries.retain(|entry| &entry.user != user);
+        let position = self.entries
+            .iter()
+            .position(|entry| entry.level < level)
+            .unwrap_or(self.entries.len());
+        self.entries.insert(position, LeaderboardEntry { user: *user, level });
+        self.entries.truncate(crate::constants::LEADERBOARD_SIZE);
+    }
+
+    /// Drop a user account, e.g. once it is closed
+    pub fn remove(&mut self, user: &Pubkey) {
+        self.entries.retain(|entry| &entry.user != user);
+    }
+
+    /// Rank of a user account starting at 1, or `UNRANKED`
+    pub fn rank_of(&self, user: &Pubkey) -> u32 {
+        self.entries
+            .iter()
+            .position(|entry| &entry.user == user)
+            .map_or(crate::constants::UNRANKED, |position| position as u32 + 1)
+    }
+}
+
+/// Account recording the latest changes to a game configuration's credits
+/// per level
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct CreditsHistory {
+    account_type: AccountType,
+
+    /// address of the game config whose changes are recorded
+    pub game_config: Pubkey,
+
+    /// recorded changes, oldest first
+    pub entries: Vec<CreditsChange>
+}
+
+/// A change of credits per level
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct CreditsChange {
+    /// slot the change was made at
+    pub slot: u64,
+
+    /// credits per level in effect when the change was made
+    pub old_credits_per_level: u8,
+
+    pub new_credits_per_level: u8,
+
+    /// slot from which the new value applies
+    pub effective_slot: u64
+}
+
+impl CreditsHistory {
+    /// Serialized size of a full history
+    pub const LEN: usize = 1 + 32 + 4 + crate::constants::CREDITS_HISTORY_SIZE * (8 + 1 + 1 + 8);
+
+    /// Create a new, empty CreditsHistory account
+    pub fn new(
+        game_config: &Pubkey
+    ) -> Self {
+        Self {
+            account_type: AccountType::CreditsHistory,
+            game_config: *game_config,
+            entries: Vec::new()
+        }
+    }
+
+    /// Append a change, dropping the oldest one when full
+    pub fn record(&mut self, change: CreditsChange) {
+        if self.entries.len() >= crate::constants::CREDITS_HISTORY_SIZE {
+            self.entries.remove(0);
+        }
+        self.entries.push(change);
+    }
 }
 
 /// Account types defined in this program
@@ -68,7 +287,9 @@ impl User {
 pub enum AccountType {
     Uninitialized = 0,
     GameConfig,
-    User
+    User,
+    Leaderboard,
+    CreditsHistory
 }
 
 impl Default for AccountType {
@@ -90,6 +311,8 @@ impl AccountData for AccountInfo<'_> {
         match self.try_borrow_data()?.get(0).ok_or(ProgramError::AccountBorrowFailed)? {
             1 => Ok(AccountType::GameConfig),
             2 => Ok(AccountType::User),
+            3 => Ok(AccountType::Leaderboard),
+            4 => Ok(AccountType::CreditsHistory),
             _ => Err(ProgramError::InvalidAccountData)
         }
     }
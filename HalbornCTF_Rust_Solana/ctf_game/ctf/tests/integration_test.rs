@@ -2,6 +2,7 @@
 // These tests demonstrate the integer underflow vulnerability in user_level_up
 
 use solana_vulnerable_game::{
+    error::GameError,
     instructions::*,
     state::*,
     constants::*,
@@ -12,11 +13,13 @@ use solana_program::pubkey::Pubkey;
 use solana_program_test::*;
 use solana_sdk::{
     account::Account,
+    instruction::InstructionError,
+    transaction::TransactionError,
     signature::{Keypair, Signer},
     transaction::Transaction,
     native_token::LAMPORTS_PER_SOL,
 };
-use borsh::BorshSerialize;
+use borsh::{BorshDeserialize, BorshSerialize};
 
 #[tokio::test]
 async fn test_user_level_up_integer_underflow_vulnerability() {
@@ -65,7 +68,8 @@ async fn test_user_level_up_integer_underflow_vulnerability() {
     let create_game_config_ix = create_game_config(
         game_config_pubkey,
         admin.pubkey(),
-        credits_per_level
+        credits_per_level,
+        MAX_LEVEL
     );
     
     let mut transaction = Transaction::new_with_payer(
@@ -225,7 +229,8 @@ async fn test_user_level_up_validation_after_subtraction_bug() {
     let create_game_config_ix = create_game_config(
         game_config_pubkey,
         admin.pubkey(),
-        credits_per_level
+        credits_per_level,
+        MAX_LEVEL
     );
     
     let mut transaction = Transaction::new_with_payer(
@@ -285,3 +290,1208 @@ async fn test_user_level_up_validation_after_subtraction_bug() {
     let result = banks_client.process_transaction(transaction).await;
     assert!(result.is_err(), "Expected error - validation happens after subtraction");
 }
+
+#[tokio::test]
+async fn test_get_config_logs_config() {
+    let program_id = id();
+
+    let mut program_test = ProgramTest::default();
+    program_test.add_program(
+        "solana_vulnerable_game",
+        program_id,
+        processor!(process_instruction),
+    );
+
+    let admin = Keypair::new();
+    let (game_config_pubkey, _) = Pubkey::find_program_address(
+        &[admin.pubkey().as_ref(), GAME_CONFIG_SEED],
+        &program_id
+    );
+
+    // Seed an initialized game config instead of going through create_game_config
+    let rent = solana_sdk::rent::Rent::default();
+    let mut data = vec![0u8; std::mem::size_of::<GameConfig>()];
+    GameConfig::new(7, MAX_LEVEL).serialize(&mut data.as_mut_slice()).unwrap();
+    program_test.add_account(
+        game_config_pubkey,
+        Account {
+            lamports: rent.minimum_balance(data.len()),
+            data,
+            owner: program_id,
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[get_config(game_config_pubkey, admin.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    let result = banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    assert!(result.result.is_ok());
+
+    let logs = result.metadata.unwrap().log_messages;
+    assert!(logs.contains(&format!("Program log: admin: {}", admin.pubkey())));
+    assert!(logs.contains(&"Program log: credits_per_level: 7".to_string()));
+
+    // A config that wasn't derived from this admin is rejected
+    let other_admin = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[get_config(game_config_pubkey, other_admin.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+}
+
+fn funded_account() -> Account {
+    Account {
+        lamports: 10 * LAMPORTS_PER_SOL,
+        data: vec![],
+        owner: solana_sdk::system_program::id(),
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+fn program_account(data: Vec<u8>) -> Account {
+    Account {
+        lamports: solana_sdk::rent::Rent::default().minimum_balance(data.len()),
+        data,
+        owner: id(),
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+fn user_pda(game_config: &Pubkey, authority: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[game_config.as_ref(), authority.as_ref(), USER_SEED],
+        &id()
+    ).0
+}
+
+async fn fetch_game_config(banks_client: &mut BanksClient, game_config: Pubkey) -> (GameConfig, usize) {
+    let account = banks_client.get_account(game_config).await.unwrap().unwrap();
+    (GameConfig::unpack(&account.data).unwrap(), account.data.len())
+}
+
+#[tokio::test]
+async fn test_user_count_tracks_create_and_close() {
+    let mut program_test = ProgramTest::default();
+    program_test.add_program(
+        "solana_vulnerable_game",
+        id(),
+        processor!(process_instruction),
+    );
+
+    let admin = Keypair::new();
+    let first_authority = Keypair::new();
+    let second_authority = Keypair::new();
+    let (game_config_pubkey, _) = Pubkey::find_program_address(
+        &[admin.pubkey().as_ref(), GAME_CONFIG_SEED],
+        &id()
+    );
+
+    let mut data = vec![0u8; std::mem::size_of::<GameConfig>()];
+    GameConfig::new(10, MAX_LEVEL).serialize(&mut data.as_mut_slice()).unwrap();
+    program_test.add_account(game_config_pubkey, program_account(data));
+    program_test.add_account(first_authority.pubkey(), funded_account());
+    program_test.add_account(second_authority.pubkey(), funded_account());
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    for authority in [&first_authority, &second_authority] {
+        let mut transaction = Transaction::new_with_payer(
+            &[create_user(
+                game_config_pubkey,
+                user_pda(&game_config_pubkey, &authority.pubkey()),
+                authority.pubkey(),
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, authority], recent_blockhash);
+        banks_client.process_transaction(transaction).await.unwrap();
+    }
+    assert_eq!(fetch_game_config(&mut banks_client, game_config_pubkey).await.0.user_count, 2);
+
+    let first_user = user_pda(&game_config_pubkey, &first_authority.pubkey());
+    let mut transaction = Transaction::new_with_payer(
+        &[close_user(game_config_pubkey, first_user, first_authority.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &first_authority], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    assert_eq!(fetch_game_config(&mut banks_client, game_config_pubkey).await.0.user_count, 1);
+    assert!(banks_client.get_account(first_user).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_close_user_ranked_drops_user_from_leaderboard() {
+    let mut program_test = ProgramTest::default();
+    program_test.add_program(
+        "solana_vulnerable_game",
+        id(),
+        processor!(process_instruction),
+    );
+
+    let admin = Keypair::new();
+    let authority = Keypair::new();
+    let (game_config_pubkey, _) = Pubkey::find_program_address(
+        &[admin.pubkey().as_ref(), GAME_CONFIG_SEED],
+        &id()
+    );
+    let (leaderboard_pubkey, _) = Pubkey::find_program_address(
+        &[game_config_pubkey.as_ref(), LEADERBOARD_SEED],
+        &id()
+    );
+    let user_pubkey = user_pda(&game_config_pubkey, &authority.pubkey());
+
+    let mut game_config = GameConfig::new(10, MAX_LEVEL);
+    game_config.user_count = 1;
+    let mut data = vec![0u8; std::mem::size_of::<GameConfig>()];
+    game_config.serialize(&mut data.as_mut_slice()).unwrap();
+    program_test.add_account(game_config_pubkey, program_account(data));
+    program_test.add_account(authority.pubkey(), funded_account());
+
+    let mut data = vec![0u8; std::mem::size_of::<User>()];
+    User::new(&authority.pubkey(), &game_config_pubkey).serialize(&mut data.as_mut_slice()).unwrap();
+    program_test.add_account(user_pubkey, program_account(data));
+
+    let mut leaderboard = Leaderboard::new(&game_config_pubkey);
+    leaderboard.update(&user_pubkey, 3);
+    let mut data = vec![0u8; Leaderboard::LEN];
+    leaderboard.serialize(&mut data.as_mut_slice()).unwrap();
+    program_test.add_account(leaderboard_pubkey, program_account(data));
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[close_user_ranked(game_config_pubkey, user_pubkey, authority.pubkey(), leaderboard_pubkey)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &authority], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    assert!(banks_client.get_account(user_pubkey).await.unwrap().is_none());
+    let account = banks_client.get_account(leaderboard_pubkey).await.unwrap().unwrap();
+    let leaderboard = Leaderboard::deserialize(&mut account.data.as_slice()).unwrap();
+    assert_eq!(leaderboard.rank_of(&user_pubkey), UNRANKED);
+}
+
+#[tokio::test]
+async fn test_user_count_migrates_legacy_game_config() {
+    let mut program_test = ProgramTest::default();
+    program_test.add_program(
+        "solana_vulnerable_game",
+        id(),
+        processor!(process_instruction),
+    );
+
+    let admin = Keypair::new();
+    let authority = Keypair::new();
+    let (game_config_pubkey, _) = Pubkey::find_program_address(
+        &[admin.pubkey().as_ref(), GAME_CONFIG_SEED],
+        &id()
+    );
+
+    // account_type and credits_per_level only, as written before user_count existed
+    program_test.add_account(game_config_pubkey, program_account(vec![AccountType::GameConfig as u8, 10]));
+    program_test.add_account(authority.pubkey(), funded_account());
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[create_user(
+            game_config_pubkey,
+            user_pda(&game_config_pubkey, &authority.pubkey()),
+            authority.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &authority], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let (game_config, len) = fetch_game_config(&mut banks_client, game_config_pubkey).await;
+    assert_eq!(len, std::mem::size_of::<GameConfig>());
+    assert_eq!(game_config.credits_per_level, 10);
+    assert_eq!(game_config.user_count, 1);
+    assert_eq!(game_config.max_level, MAX_LEVEL);
+}
+
+#[tokio::test]
+async fn test_user_level_up_stops_at_max_level() {
+    let mut program_test = ProgramTest::default();
+    program_test.add_program(
+        "solana_vulnerable_game",
+        id(),
+        processor!(process_instruction),
+    );
+
+    let admin = Keypair::new();
+    let authority = Keypair::new();
+    let (game_config_pubkey, _) = Pubkey::find_program_address(
+        &[admin.pubkey().as_ref(), GAME_CONFIG_SEED],
+        &id()
+    );
+    let user_pubkey = user_pda(&game_config_pubkey, &authority.pubkey());
+
+    let mut data = vec![0u8; std::mem::size_of::<GameConfig>()];
+    GameConfig::new(10, 2).serialize(&mut data.as_mut_slice()).unwrap();
+    program_test.add_account(game_config_pubkey, program_account(data));
+
+    let mut user = User::new(&authority.pubkey(), &game_config_pubkey);
+    user.credits = 100;
+    let mut data = vec![0u8; std::mem::size_of::<User>()];
+    user.serialize(&mut data.as_mut_slice()).unwrap();
+    program_test.add_account(user_pubkey, program_account(data));
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    // Enough credits to burn past level 2, the loop stops at the cap
+    let mut transaction = Transaction::new_with_payer(
+        &[user_level_up(game_config_pubkey, user_pubkey, authority.pubkey(), 31)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &authority], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let account = banks_client.get_account(user_pubkey).await.unwrap().unwrap();
+    assert_eq!(User::deserialize(&mut account.data.as_slice()).unwrap().level, 2);
+
+    // Any further level-up is rejected, whatever the amount burned
+    let mut transaction = Transaction::new_with_payer(
+        &[user_level_up(game_config_pubkey, user_pubkey, authority.pubkey(), 11)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &authority], recent_blockhash);
+    let error = banks_client.process_transaction(transaction).await.unwrap_err();
+    assert_eq!(
+        error.unwrap(),
+        TransactionError::InstructionError(0, InstructionError::Custom(GameError::MaxLevelReached as u32))
+    );
+}
+
+#[tokio::test]
+async fn test_reclaim_credits_moves_credits_to_pool() {
+    let mut program_test = ProgramTest::default();
+    program_test.add_program(
+        "solana_vulnerable_game",
+        id(),
+        processor!(process_instruction),
+    );
+
+    let admin = Keypair::new();
+    let authority = Keypair::new();
+    let (game_config_pubkey, _) = Pubkey::find_program_address(
+        &[admin.pubkey().as_ref(), GAME_CONFIG_SEED],
+        &id()
+    );
+    let user_pubkey = user_pda(&game_config_pubkey, &authority.pubkey());
+
+    let mut data = vec![0u8; std::mem::size_of::<GameConfig>()];
+    GameConfig::new(10, MAX_LEVEL).serialize(&mut data.as_mut_slice()).unwrap();
+    program_test.add_account(game_config_pubkey, program_account(data));
+
+    let mut user = User::new(&authority.pubkey(), &game_config_pubkey);
+    user.credits = 50;
+    let mut data = vec![0u8; std::mem::size_of::<User>()];
+    user.serialize(&mut data.as_mut_slice()).unwrap();
+    program_test.add_account(user_pubkey, program_account(data));
+    program_test.add_account(admin.pubkey(), funded_account());
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[reclaim_credits(game_config_pubkey, user_pubkey, admin.pubkey(), 20)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let account = banks_client.get_account(user_pubkey).await.unwrap().unwrap();
+    assert_eq!(User::deserialize(&mut account.data.as_slice()).unwrap().credits, 30);
+    assert_eq!(fetch_game_config(&mut banks_client, game_config_pubkey).await.0.reclaimed_pool, 20);
+
+    // The user only has 30 credits left
+    let mut transaction = Transaction::new_with_payer(
+        &[reclaim_credits(game_config_pubkey, user_pubkey, admin.pubkey(), 31)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin], recent_blockhash);
+    let error = banks_client.process_transaction(transaction).await.unwrap_err();
+    assert_eq!(
+        error.unwrap(),
+        TransactionError::InstructionError(0, InstructionError::InsufficientFunds)
+    );
+}
+
+#[tokio::test]
+async fn test_reclaim_credits_rejects_non_admin() {
+    let mut program_test = ProgramTest::default();
+    program_test.add_program(
+        "solana_vulnerable_game",
+        id(),
+        processor!(process_instruction),
+    );
+
+    let admin = Keypair::new();
+    let attacker = Keypair::new();
+    let authority = Keypair::new();
+    let (game_config_pubkey, _) = Pubkey::find_program_address(
+        &[admin.pubkey().as_ref(), GAME_CONFIG_SEED],
+        &id()
+    );
+    let user_pubkey = user_pda(&game_config_pubkey, &authority.pubkey());
+
+    let mut data = vec![0u8; std::mem::size_of::<GameConfig>()];
+    GameConfig::new(10, MAX_LEVEL).serialize(&mut data.as_mut_slice()).unwrap();
+    program_test.add_account(game_config_pubkey, program_account(data));
+
+    let mut user = User::new(&authority.pubkey(), &game_config_pubkey);
+    user.credits = 50;
+    let mut data = vec![0u8; std::mem::size_of::<User>()];
+    user.serialize(&mut data.as_mut_slice()).unwrap();
+    program_test.add_account(user_pubkey, program_account(data));
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[reclaim_credits(game_config_pubkey, user_pubkey, attacker.pubkey(), 20)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &attacker], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+
+    let account = banks_client.get_account(user_pubkey).await.unwrap().unwrap();
+    assert_eq!(User::deserialize(&mut account.data.as_slice()).unwrap().credits, 50);
+    assert_eq!(fetch_game_config(&mut banks_client, game_config_pubkey).await.0.reclaimed_pool, 0);
+}
+
+#[tokio::test]
+async fn test_get_user_rank_reports_leaderboard_position() {
+    let mut program_test = ProgramTest::default();
+    program_test.add_program(
+        "solana_vulnerable_game",
+        id(),
+        processor!(process_instruction),
+    );
+
+    let admin = Keypair::new();
+    let (game_config_pubkey, _) = Pubkey::find_program_address(
+        &[admin.pubkey().as_ref(), GAME_CONFIG_SEED],
+        &id()
+    );
+    let (leaderboard_pubkey, _) = Pubkey::find_program_address(
+        &[game_config_pubkey.as_ref(), LEADERBOARD_SEED],
+        &id()
+    );
+
+    let mut data = vec![0u8; std::mem::size_of::<GameConfig>()];
+    GameConfig::new(10, MAX_LEVEL).serialize(&mut data.as_mut_slice()).unwrap();
+    program_test.add_account(game_config_pubkey, program_account(data));
+    program_test.add_account(admin.pubkey(), funded_account());
+
+    // Four users with plenty of credits, the last one never levels up
+    let authorities: Vec<Keypair> = (0..4).map(|_| Keypair::new()).collect();
+    for authority in &authorities {
+        let mut user = User::new(&authority.pubkey(), &game_config_pubkey);
+        user.credits = 100;
+        let mut data = vec![0u8; std::mem::size_of::<User>()];
+        user.serialize(&mut data.as_mut_slice()).unwrap();
+        program_test.add_account(user_pda(&game_config_pubkey, &authority.pubkey()), program_account(data));
+    }
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[create_leaderboard(leaderboard_pubkey, game_config_pubkey, admin.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Burning 1, 31 and 11 credits reaches levels 1, 3 and 2
+    for (authority, credits_to_burn) in authorities.iter().zip([1, 31, 11]) {
+        let mut transaction = Transaction::new_with_payer(
+            &[user_level_up_ranked(
+                game_config_pubkey,
+                user_pda(&game_config_pubkey, &authority.pubkey()),
+                authority.pubkey(),
+                leaderboard_pubkey,
+                credits_to_burn,
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, authority], recent_blockhash);
+        banks_client.process_transaction(transaction).await.unwrap();
+    }
+
+    for (authority, rank) in authorities.iter().zip([3, 1, 2, UNRANKED]) {
+        let mut transaction = Transaction::new_with_payer(
+            &[get_user_rank(
+                game_config_pubkey,
+                leaderboard_pubkey,
+                user_pda(&game_config_pubkey, &authority.pubkey()),
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer], recent_blockhash);
+        let result = banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+            .unwrap();
+        assert!(result.result.is_ok());
+        let logs = result.metadata.unwrap().log_messages;
+        assert!(logs.contains(&format!("Program log: rank: {}", rank)));
+    }
+
+    // A leaderboard that doesn't belong to the game config is rejected
+    let mut transaction = Transaction::new_with_payer(
+        &[get_user_rank(
+            game_config_pubkey,
+            game_config_pubkey,
+            user_pda(&game_config_pubkey, &authorities[0].pubkey()),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+}
+
+#[tokio::test]
+async fn test_create_leaderboard_initializes_once() {
+    let mut program_test = ProgramTest::default();
+    program_test.add_program(
+        "solana_vulnerable_game",
+        id(),
+        processor!(process_instruction),
+    );
+
+    let admin = Keypair::new();
+    let (game_config_pubkey, _) = Pubkey::find_program_address(
+        &[admin.pubkey().as_ref(), GAME_CONFIG_SEED],
+        &id()
+    );
+    let (leaderboard_pubkey, _) = Pubkey::find_program_address(
+        &[game_config_pubkey.as_ref(), LEADERBOARD_SEED],
+        &id()
+    );
+
+    let mut data = vec![0u8; std::mem::size_of::<GameConfig>()];
+    GameConfig::new(10, MAX_LEVEL).serialize(&mut data.as_mut_slice()).unwrap();
+    program_test.add_account(game_config_pubkey, program_account(data));
+    program_test.add_account(admin.pubkey(), funded_account());
+
+    let mut context = program_test.start_with_context().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[create_leaderboard(leaderboard_pubkey, game_config_pubkey, admin.pubkey())],
+        Some(&context.payer.pubkey()),
+    );
+    transaction.sign(&[&context.payer, &admin], context.last_blockhash);
+    context.banks_client.process_transaction(transaction).await.unwrap();
+
+    let account = context.banks_client.get_account(leaderboard_pubkey).await.unwrap().unwrap();
+    assert_eq!(account.owner, id());
+    assert_eq!(account.data.len(), Leaderboard::LEN);
+    let leaderboard = Leaderboard::deserialize(&mut account.data.as_slice()).unwrap();
+    assert_eq!(leaderboard.game_config, game_config_pubkey);
+    assert!(leaderboard.entries.is_empty());
+
+    // A second initialization is rejected and leaves the account untouched
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        &[create_leaderboard(leaderboard_pubkey, game_config_pubkey, admin.pubkey())],
+        Some(&context.payer.pubkey()),
+    );
+    transaction.sign(&[&context.payer, &admin], blockhash);
+    let err = context.banks_client.process_transaction(transaction).await.unwrap_err().unwrap();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(0, InstructionError::AccountAlreadyInitialized)
+    );
+    let after = context.banks_client.get_account(leaderboard_pubkey).await.unwrap().unwrap();
+    assert_eq!(after.data, account.data);
+
+    // An address that isn't the derived leaderboard PDA is rejected
+    let mut transaction = Transaction::new_with_payer(
+        &[create_leaderboard(Keypair::new().pubkey(), game_config_pubkey, admin.pubkey())],
+        Some(&context.payer.pubkey()),
+    );
+    transaction.sign(&[&context.payer, &admin], blockhash);
+    assert!(context.banks_client.process_transaction(transaction).await.is_err());
+}
+
+#[tokio::test]
+async fn test_user_level_up_respects_cooldown() {
+    let mut program_test = ProgramTest::default();
+    program_test.add_program(
+        "solana_vulnerable_game",
+        id(),
+        processor!(process_instruction),
+    );
+
+    let admin = Keypair::new();
+    let authority = Keypair::new();
+    let (game_config_pubkey, _) = Pubkey::find_program_address(
+        &[admin.pubkey().as_ref(), GAME_CONFIG_SEED],
+        &id()
+    );
+    let user_pubkey = user_pda(&game_config_pubkey, &authority.pubkey());
+
+    let mut data = vec![0u8; std::mem::size_of::<GameConfig>()];
+    GameConfig::new(10, MAX_LEVEL).serialize(&mut data.as_mut_slice()).unwrap();
+    program_test.add_account(game_config_pubkey, program_account(data));
+    program_test.add_account(admin.pubkey(), funded_account());
+
+    let mut user = User::new(&authority.pubkey(), &game_config_pubkey);
+    user.credits = 100;
+    let mut data = vec![0u8; std::mem::size_of::<User>()];
+    user.serialize(&mut data.as_mut_slice()).unwrap();
+    program_test.add_account(user_pubkey, program_account(data));
+
+    let mut context = program_test.start_with_context().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            set_level_up_cooldown(game_config_pubkey, admin.pubkey(), 100),
+            user_level_up(game_config_pubkey, user_pubkey, authority.pubkey(), 1),
+        ],
+        Some(&context.payer.pubkey()),
+    );
+    transaction.sign(&[&context.payer, &admin, &authority], context.last_blockhash);
+    context.banks_client.process_transaction(transaction).await.unwrap();
+
+    let account = context.banks_client.get_account(user_pubkey).await.unwrap().unwrap();
+    let user = User::deserialize(&mut account.data.as_slice()).unwrap();
+    assert_eq!(user.level, 1);
+    let leveled_up_at = user.last_level_up_slot;
+
+    // Retrying right away is rejected
+    let mut transaction = Transaction::new_with_payer(
+        &[user_level_up(game_config_pubkey, user_pubkey, authority.pubkey(), 11)],
+        Some(&context.payer.pubkey()),
+    );
+    transaction.sign(&[&context.payer, &authority], context.last_blockhash);
+    let error = context.banks_client.process_transaction(transaction).await.unwrap_err();
+    assert_eq!(
+        error.unwrap(),
+        TransactionError::InstructionError(0, InstructionError::Custom(GameError::CooldownActive as u32))
+    );
+
+    // Once the cooldown has elapsed the level-up goes through
+    context.warp_to_slot(leveled_up_at + 100).unwrap();
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+
+    let mut transaction = Transaction::new_with_payer(
+        &[user_level_up(game_config_pubkey, user_pubkey, authority.pubkey(), 11)],
+        Some(&context.payer.pubkey()),
+    );
+    transaction.sign(&[&context.payer, &authority], blockhash);
+    context.banks_client.process_transaction(transaction).await.unwrap();
+
+    let account = context.banks_client.get_account(user_pubkey).await.unwrap().unwrap();
+    let user = User::deserialize(&mut account.data.as_slice()).unwrap();
+    assert!(user.level > 1);
+    assert!(user.last_level_up_slot >= leveled_up_at + 100);
+}
+
+#[tokio::test]
+async fn test_merge_users_combines_credits() {
+    let mut program_test = ProgramTest::default();
+    program_test.add_program(
+        "solana_vulnerable_game",
+        id(),
+        processor!(process_instruction),
+    );
+
+    let admin = Keypair::new();
+    let (game_config_pubkey, _) = Pubkey::find_program_address(
+        &[admin.pubkey().as_ref(), GAME_CONFIG_SEED],
+        &id()
+    );
+    let (leaderboard_pubkey, _) = Pubkey::find_program_address(
+        &[game_config_pubkey.as_ref(), LEADERBOARD_SEED],
+        &id()
+    );
+
+    let mut game_config = GameConfig::new(10, MAX_LEVEL);
+    game_config.user_count = 3;
+    let mut data = vec![0u8; std::mem::size_of::<GameConfig>()];
+    game_config.serialize(&mut data.as_mut_slice()).unwrap();
+    program_test.add_account(game_config_pubkey, program_account(data));
+    program_test.add_account(admin.pubkey(), funded_account());
+
+    let authorities: Vec<Pubkey> = (0..3).map(|_| Keypair::new().pubkey()).collect();
+    let users: Vec<Pubkey> = authorities
+        .iter()
+        .zip([20, 30, 5])
+        .map(|(authority, credits)| {
+            let mut user = User::new(authority, &game_config_pubkey);
+            user.credits = credits;
+            let mut data = vec![0u8; std::mem::size_of::<User>()];
+            user.serialize(&mut data.as_mut_slice()).unwrap();
+            let user_pubkey = user_pda(&game_config_pubkey, authority);
+            program_test.add_account(user_pubkey, program_account(data));
+            program_test.add_account(*authority, funded_account());
+            user_pubkey
+        })
+        .collect();
+
+    let mut leaderboard = Leaderboard::new(&game_config_pubkey);
+    leaderboard.update(&users[1], 2);
+    leaderboard.update(&users[2], 1);
+    let mut data = vec![0u8; Leaderboard::LEN];
+    leaderboard.serialize(&mut data.as_mut_slice()).unwrap();
+    program_test.add_account(leaderboard_pubkey, program_account(data));
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    // Merge the first user into the second, keeping the emptied source
+    let mut transaction = Transaction::new_with_payer(
+        &[merge_users(game_config_pubkey, users[0], users[1], admin.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let account = banks_client.get_account(users[0]).await.unwrap().unwrap();
+    assert_eq!(User::deserialize(&mut account.data.as_slice()).unwrap().credits, 0);
+    let account = banks_client.get_account(users[1]).await.unwrap().unwrap();
+    assert_eq!(User::deserialize(&mut account.data.as_slice()).unwrap().credits, 50);
+
+    // Then the second into the third, closing the source: its lamports go
+    // back to its authority and it leaves the leaderboard
+    let admin_lamports = banks_client.get_balance(admin.pubkey()).await.unwrap();
+    let authority_lamports = banks_client.get_balance(authorities[1]).await.unwrap();
+    let source_lamports = banks_client.get_balance(users[1]).await.unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        &[merge_and_close_users_ranked(
+            game_config_pubkey,
+            users[1],
+            users[2],
+            admin.pubkey(),
+            authorities[1],
+            leaderboard_pubkey,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    assert!(banks_client.get_account(users[1]).await.unwrap().is_none());
+    let account = banks_client.get_account(users[2]).await.unwrap().unwrap();
+    assert_eq!(User::deserialize(&mut account.data.as_slice()).unwrap().credits, 55);
+    assert_eq!(
+        banks_client.get_balance(authorities[1]).await.unwrap(),
+        authority_lamports + source_lamports
+    );
+    assert_eq!(banks_client.get_balance(admin.pubkey()).await.unwrap(), admin_lamports);
+    assert_eq!(fetch_game_config(&mut banks_client, game_config_pubkey).await.0.user_count, 2);
+    let account = banks_client.get_account(leaderboard_pubkey).await.unwrap().unwrap();
+    let leaderboard = Leaderboard::deserialize(&mut account.data.as_slice()).unwrap();
+    assert_eq!(leaderboard.rank_of(&users[1]), UNRANKED);
+    assert_eq!(leaderboard.rank_of(&users[2]), 1);
+
+    // A user can't be merged into itself
+    let mut transaction = Transaction::new_with_payer(
+        &[merge_users(game_config_pubkey, users[2], users[2], admin.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin], recent_blockhash);
+    let error = banks_client.process_transaction(transaction).await.unwrap_err();
+    assert_eq!(
+        error.unwrap(),
+        TransactionError::InstructionError(0, InstructionError::InvalidArgument)
+    );
+}
+
+#[tokio::test]
+async fn test_next_level_cost_reports_cost() {
+    let mut program_test = ProgramTest::default();
+    program_test.add_program(
+        "solana_vulnerable_game",
+        id(),
+        processor!(process_instruction),
+    );
+
+    let admin = Keypair::new();
+    let (game_config_pubkey, _) = Pubkey::find_program_address(
+        &[admin.pubkey().as_ref(), GAME_CONFIG_SEED],
+        &id()
+    );
+
+    let mut data = vec![0u8; std::mem::size_of::<GameConfig>()];
+    GameConfig::new(10, MAX_LEVEL).serialize(&mut data.as_mut_slice()).unwrap();
+    program_test.add_account(game_config_pubkey, program_account(data));
+
+    let authorities: Vec<Keypair> = (0..2).map(|_| Keypair::new()).collect();
+    for (authority, level) in authorities.iter().zip([0, 3]) {
+        let mut user = User::new(&authority.pubkey(), &game_config_pubkey);
+        user.level = level;
+        let mut data = vec![0u8; std::mem::size_of::<User>()];
+        user.serialize(&mut data.as_mut_slice()).unwrap();
+        program_test.add_account(user_pda(&game_config_pubkey, &authority.pubkey()), program_account(data));
+    }
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    // (level + 1) * credits_per_level
+    for (authority, cost) in authorities.iter().zip([10, 40]) {
+        let mut transaction = Transaction::new_with_payer(
+            &[next_level_cost(
+                game_config_pubkey,
+                admin.pubkey(),
+                user_pda(&game_config_pubkey, &authority.pubkey()),
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer], recent_blockhash);
+        let result = banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+            .unwrap();
+        assert!(result.result.is_ok());
+        let logs = result.metadata.unwrap().log_messages;
+        assert!(logs.contains(&format!("Program log: next_level_cost: {}", cost)));
+    }
+
+    // A game config that doesn't derive from the given admin is rejected
+    let mut transaction = Transaction::new_with_payer(
+        &[next_level_cost(
+            game_config_pubkey,
+            payer.pubkey(),
+            user_pda(&game_config_pubkey, &authorities[0].pubkey()),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+}
+
+#[tokio::test]
+async fn test_frozen_user_cannot_level_up() {
+    let mut program_test = ProgramTest::default();
+    program_test.add_program(
+        "solana_vulnerable_game",
+        id(),
+        processor!(process_instruction),
+    );
+
+    let admin = Keypair::new();
+    let authority = Keypair::new();
+    let (game_config_pubkey, _) = Pubkey::find_program_address(
+        &[admin.pubkey().as_ref(), GAME_CONFIG_SEED],
+        &id()
+    );
+    let user_pubkey = user_pda(&game_config_pubkey, &authority.pubkey());
+
+    let mut data = vec![0u8; std::mem::size_of::<GameConfig>()];
+    GameConfig::new(10, MAX_LEVEL).serialize(&mut data.as_mut_slice()).unwrap();
+    program_test.add_account(game_config_pubkey, program_account(data));
+    program_test.add_account(admin.pubkey(), funded_account());
+
+    let mut user = User::new(&authority.pubkey(), &game_config_pubkey);
+    user.credits = 100;
+    let mut data = vec![0u8; std::mem::size_of::<User>()];
+    user.serialize(&mut data.as_mut_slice()).unwrap();
+    program_test.add_account(user_pubkey, program_account(data));
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[set_user_frozen(game_config_pubkey, user_pubkey, admin.pubkey(), true)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let account = banks_client.get_account(user_pubkey).await.unwrap().unwrap();
+    assert!(User::unpack(&account.data).unwrap().frozen);
+
+    let mut transaction = Transaction::new_with_payer(
+        &[user_level_up(game_config_pubkey, user_pubkey, authority.pubkey(), 11)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &authority], recent_blockhash);
+    let error = banks_client.process_transaction(transaction).await.unwrap_err();
+    assert_eq!(
+        error.unwrap(),
+        TransactionError::InstructionError(0, InstructionError::Custom(GameError::UserFrozen as u32))
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[set_user_frozen(game_config_pubkey, user_pubkey, admin.pubkey(), false)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mut transaction = Transaction::new_with_payer(
+        &[user_level_up(game_config_pubkey, user_pubkey, authority.pubkey(), 1)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &authority], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let account = banks_client.get_account(user_pubkey).await.unwrap().unwrap();
+    let user = User::unpack(&account.data).unwrap();
+    assert!(!user.frozen);
+    assert_eq!(user.level, 1);
+
+    // Only the game config admin can freeze
+    let mut transaction = Transaction::new_with_payer(
+        &[set_user_frozen(game_config_pubkey, user_pubkey, authority.pubkey(), true)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &authority], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+}
+
+#[tokio::test]
+async fn test_set_user_credits_overwrites_credits() {
+    let mut program_test = ProgramTest::default();
+    program_test.add_program(
+        "solana_vulnerable_game",
+        id(),
+        processor!(process_instruction),
+    );
+
+    let admin = Keypair::new();
+    let authority = Keypair::new();
+    let (game_config_pubkey, _) = Pubkey::find_program_address(
+        &[admin.pubkey().as_ref(), GAME_CONFIG_SEED],
+        &id()
+    );
+    let user_pubkey = user_pda(&game_config_pubkey, &authority.pubkey());
+
+    let mut data = vec![0u8; std::mem::size_of::<GameConfig>()];
+    GameConfig::new(10, MAX_LEVEL).serialize(&mut data.as_mut_slice()).unwrap();
+    program_test.add_account(game_config_pubkey, program_account(data));
+    program_test.add_account(admin.pubkey(), funded_account());
+
+    let mut user = User::new(&authority.pubkey(), &game_config_pubkey);
+    user.credits = 50;
+    let mut data = vec![0u8; std::mem::size_of::<User>()];
+    user.serialize(&mut data.as_mut_slice()).unwrap();
+    program_test.add_account(user_pubkey, program_account(data));
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    for credits in [500, 7] {
+        let mut transaction = Transaction::new_with_payer(
+            &[set_user_credits(game_config_pubkey, user_pubkey, admin.pubkey(), credits)],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &admin], recent_blockhash);
+        banks_client.process_transaction(transaction).await.unwrap();
+
+        let account = banks_client.get_account(user_pubkey).await.unwrap().unwrap();
+        assert_eq!(User::unpack(&account.data).unwrap().credits, credits);
+    }
+
+    // Only the game config admin can set credits
+    let mut transaction = Transaction::new_with_payer(
+        &[set_user_credits(game_config_pubkey, user_pubkey, authority.pubkey(), 1_000)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &authority], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+}
+
+#[tokio::test]
+async fn test_update_credits_per_level_records_history() {
+    let mut program_test = ProgramTest::default();
+    program_test.add_program(
+        "solana_vulnerable_game",
+        id(),
+        processor!(process_instruction),
+    );
+
+    let admin = Keypair::new();
+    let authority = Keypair::new();
+    let (game_config_pubkey, _) = Pubkey::find_program_address(
+        &[admin.pubkey().as_ref(), GAME_CONFIG_SEED],
+        &id()
+    );
+    let (credits_history_pubkey, _) = Pubkey::find_program_address(
+        &[game_config_pubkey.as_ref(), CREDITS_HISTORY_SEED],
+        &id()
+    );
+    let user_pubkey = user_pda(&game_config_pubkey, &authority.pubkey());
+
+    let mut data = vec![0u8; std::mem::size_of::<GameConfig>()];
+    GameConfig::new(10, MAX_LEVEL).serialize(&mut data.as_mut_slice()).unwrap();
+    program_test.add_account(game_config_pubkey, program_account(data));
+    program_test.add_account(admin.pubkey(), funded_account());
+
+    let user = User::new(&authority.pubkey(), &game_config_pubkey);
+    let mut data = vec![0u8; std::mem::size_of::<User>()];
+    user.serialize(&mut data.as_mut_slice()).unwrap();
+    program_test.add_account(user_pubkey, program_account(data));
+
+    let mut context = program_test.start_with_context().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[update_credits_per_level(game_config_pubkey, credits_history_pubkey, admin.pubkey(), 20, None)],
+        Some(&context.payer.pubkey()),
+    );
+    transaction.sign(&[&context.payer, &admin], context.last_blockhash);
+    context.banks_client.process_transaction(transaction).await.unwrap();
+
+    let game_config = fetch_game_config(&mut context.banks_client, game_config_pubkey).await.0;
+    assert_eq!(game_config.credits_per_level, 20);
+    let account = context.banks_client.get_account(credits_history_pubkey).await.unwrap().unwrap();
+    let history = CreditsHistory::deserialize(&mut account.data.as_slice()).unwrap();
+    assert_eq!(history.game_config, game_config_pubkey);
+    assert_eq!(history.entries.len(), 1);
+    let change = &history.entries[0];
+    assert_eq!((change.old_credits_per_level, change.new_credits_per_level), (10, 20));
+    assert_eq!(change.effective_slot, change.slot);
+    let changed_at = change.slot;
+
+    // A deferred change is recorded but the old value stays in effect
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            update_credits_per_level(game_config_pubkey, credits_history_pubkey, admin.pubkey(), 30, Some(changed_at + 100)),
+            next_level_cost(game_config_pubkey, admin.pubkey(), user_pubkey),
+        ],
+        Some(&context.payer.pubkey()),
+    );
+    transaction.sign(&[&context.payer, &admin], context.last_blockhash);
+    let result = context.banks_client.process_transaction_with_metadata(transaction).await.unwrap();
+    assert!(result.result.is_ok());
+    assert!(result.metadata.unwrap().log_messages.contains(&"Program log: next_level_cost: 20".to_string()));
+
+    let account = context.banks_client.get_account(credits_history_pubkey).await.unwrap().unwrap();
+    let history = CreditsHistory::deserialize(&mut account.data.as_slice()).unwrap();
+    assert_eq!(history.entries.len(), 2);
+    let change = &history.entries[1];
+    assert_eq!((change.old_credits_per_level, change.new_credits_per_level), (20, 30));
+    assert_eq!(change.effective_slot, changed_at + 100);
+
+    // Once the slot is reached the new value applies
+    context.warp_to_slot(changed_at + 100).unwrap();
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+
+    let mut transaction = Transaction::new_with_payer(
+        &[next_level_cost(game_config_pubkey, admin.pubkey(), user_pubkey)],
+        Some(&context.payer.pubkey()),
+    );
+    transaction.sign(&[&context.payer], blockhash);
+    let result = context.banks_client.process_transaction_with_metadata(transaction).await.unwrap();
+    assert!(result.result.is_ok());
+    assert!(result.metadata.unwrap().log_messages.contains(&"Program log: next_level_cost: 30".to_string()));
+
+    // Only the game config admin can change the curve
+    let mut transaction = Transaction::new_with_payer(
+        &[update_credits_per_level(game_config_pubkey, credits_history_pubkey, authority.pubkey(), 1, None)],
+        Some(&context.payer.pubkey()),
+    );
+    transaction.sign(&[&context.payer, &authority], blockhash);
+    assert!(context.banks_client.process_transaction(transaction).await.is_err());
+}
+
+#[tokio::test]
+async fn test_batch_level_up_skips_users_without_credits() {
+    let mut program_test = ProgramTest::default();
+    program_test.add_program(
+        "solana_vulnerable_game",
+        id(),
+        processor!(process_instruction),
+    );
+
+    let admin = Keypair::new();
+    let (game_config_pubkey, _) = Pubkey::find_program_address(
+        &[admin.pubkey().as_ref(), GAME_CONFIG_SEED],
+        &id()
+    );
+
+    let mut data = vec![0u8; std::mem::size_of::<GameConfig>()];
+    GameConfig::new(10, MAX_LEVEL).serialize(&mut data.as_mut_slice()).unwrap();
+    program_test.add_account(game_config_pubkey, program_account(data));
+    program_test.add_account(admin.pubkey(), funded_account());
+
+    let mut user_pubkeys = Vec::new();
+    for credits in [100, 100, 5] {
+        let authority = Keypair::new();
+        let user_pubkey = user_pda(&game_config_pubkey, &authority.pubkey());
+        let mut user = User::new(&authority.pubkey(), &game_config_pubkey);
+        user.credits = credits;
+        let mut data = vec![0u8; std::mem::size_of::<User>()];
+        user.serialize(&mut data.as_mut_slice()).unwrap();
+        program_test.add_account(user_pubkey, program_account(data));
+        user_pubkeys.push(user_pubkey);
+    }
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    // Burning 30 credits reaches level 2 for 0 + 10 credits
+    let mut transaction = Transaction::new_with_payer(
+        &[batch_level_up(game_config_pubkey, admin.pubkey(), &user_pubkeys, 30)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mut users = Vec::new();
+    for user_pubkey in &user_pubkeys {
+        let account = banks_client.get_account(*user_pubkey).await.unwrap().unwrap();
+        let user = User::unpack(&account.data).unwrap();
+        users.push((user.level, user.credits));
+    }
+    assert_eq!(users, vec![(2, 90), (2, 90), (0, 5)]);
+
+    // Users of another game config are rejected
+    let other_admin = Keypair::new();
+    let (other_game_config, _) = Pubkey::find_program_address(
+        &[other_admin.pubkey().as_ref(), GAME_CONFIG_SEED],
+        &id()
+    );
+    let mut transaction = Transaction::new_with_payer(
+        &[batch_level_up(other_game_config, other_admin.pubkey(), &user_pubkeys, 30)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &other_admin], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+}
+
+#[tokio::test]
+async fn test_withdraw_config_lamports_keeps_rent_exemption() {
+    let mut program_test = ProgramTest::default();
+    program_test.add_program(
+        "solana_vulnerable_game",
+        id(),
+        processor!(process_instruction),
+    );
+
+    let admin = Keypair::new();
+    let destination = Pubkey::new_unique();
+    let (game_config_pubkey, _) = Pubkey::find_program_address(
+        &[admin.pubkey().as_ref(), GAME_CONFIG_SEED],
+        &id()
+    );
+
+    let mut data = vec![0u8; std::mem::size_of::<GameConfig>()];
+    GameConfig::new(10, MAX_LEVEL).serialize(&mut data.as_mut_slice()).unwrap();
+    let mut game_config = program_account(data);
+    let minimum_balance = game_config.lamports;
+    game_config.lamports += LAMPORTS_PER_SOL;
+    program_test.add_account(game_config_pubkey, game_config);
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[withdraw_config_lamports(game_config_pubkey, admin.pubkey(), destination, LAMPORTS_PER_SOL / 2)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    assert_eq!(banks_client.get_balance(destination).await.unwrap(), LAMPORTS_PER_SOL / 2);
+    assert_eq!(
+        banks_client.get_balance(game_config_pubkey).await.unwrap(),
+        minimum_balance + LAMPORTS_PER_SOL / 2
+    );
+
+    // Only lamports above the rent-exempt minimum can be withdrawn
+    let mut transaction = Transaction::new_with_payer(
+        &[withdraw_config_lamports(game_config_pubkey, admin.pubkey(), destination, LAMPORTS_PER_SOL / 2 + 1)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin], recent_blockhash);
+    let error = banks_client.process_transaction(transaction).await.unwrap_err();
+    assert_eq!(
+        error.unwrap(),
+        TransactionError::InstructionError(0, InstructionError::InsufficientFunds)
+    );
+}
+
+#[tokio::test]
+async fn test_get_user_logs_user_state() {
+    let mut program_test = ProgramTest::default();
+    program_test.add_program(
+        "solana_vulnerable_game",
+        id(),
+        processor!(process_instruction),
+    );
+
+    let admin = Keypair::new();
+    let authority = Keypair::new();
+    let (game_config_pubkey, _) = Pubkey::find_program_address(
+        &[admin.pubkey().as_ref(), GAME_CONFIG_SEED],
+        &id()
+    );
+    let user_pubkey = user_pda(&game_config_pubkey, &authority.pubkey());
+
+    let mut data = vec![0u8; std::mem::size_of::<GameConfig>()];
+    GameConfig::new(10, MAX_LEVEL).serialize(&mut data.as_mut_slice()).unwrap();
+    program_test.add_account(game_config_pubkey, program_account(data));
+    program_test.add_account(admin.pubkey(), funded_account());
+    program_test.add_account(authority.pubkey(), funded_account());
+
+    let user = User::new(&authority.pubkey(), &game_config_pubkey);
+    let mut data = vec![0u8; std::mem::size_of::<User>()];
+    user.serialize(&mut data.as_mut_slice()).unwrap();
+    program_test.add_account(user_pubkey, program_account(data));
+
+    let mut context = program_test.start_with_context().await;
+    context.warp_to_slot(10).unwrap();
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            mint_credits_to_user(game_config_pubkey, user_pubkey, admin.pubkey(), 100),
+            user_level_up(game_config_pubkey, user_pubkey, authority.pubkey(), 1),
+        ],
+        Some(&context.payer.pubkey()),
+    );
+    transaction.sign(&[&context.payer, &admin, &authority], blockhash);
+    context.banks_client.process_transaction(transaction).await.unwrap();
+
+    let account = context.banks_client.get_account(user_pubkey).await.unwrap().unwrap();
+    let user = User::deserialize(&mut account.data.as_slice()).unwrap();
+    assert_eq!(user.level, 1);
+    assert!(user.last_level_up_slot >= 10);
+
+    let mut transaction = Transaction::new_with_payer(
+        &[get_user(game_config_pubkey, user_pubkey)],
+        Some(&context.payer.pubkey()),
+    );
+    transaction.sign(&[&context.payer], blockhash);
+    let result = context.banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    assert!(result.result.is_ok());
+    let logs = result.metadata.unwrap().log_messages;
+    assert!(logs.contains(&format!(
+        "Program log: user: authority={} credits={} level=1 last_level_up_slot={} frozen=false",
+        authority.pubkey(),
+        user.credits,
+        user.last_level_up_slot
+    )));
+
+    // A user account of another game config is rejected
+    let (other_game_config, _) = Pubkey::find_program_address(
+        &[Keypair::new().pubkey().as_ref(), GAME_CONFIG_SEED],
+        &id()
+    );
+    let mut data = vec![0u8; std::mem::size_of::<GameConfig>()];
+    GameConfig::new(10, MAX_LEVEL).serialize(&mut data.as_mut_slice()).unwrap();
+    context.set_account(&other_game_config, &program_account(data).into());
+    let mut transaction = Transaction::new_with_payer(
+        &[get_user(other_game_config, user_pubkey)],
+        Some(&context.payer.pubkey()),
+    );
+    transaction.sign(&[&context.payer], blockhash);
+    assert!(context.banks_client.process_transaction(transaction).await.is_err());
+}
@@ -89,9 +89,19 @@ impl pallet_balances::Config for Test {
 ord_parameter_types! {
     pub const ShutdownAdmin: u64 = 21;
 }
+parameter_types! {
+    pub const MaxPauseDuration: u64 = 100;
+    pub const MaxReasonLength: u32 = 32;
+    pub const MaxExempt: u32 = 2;
+}
 impl pallet_pause::Config for Test {
     type Event = ();
     type PauseOrigin = EnsureSignedBy<ShutdownAdmin, u64>;
+    type MaxPauseDuration = MaxPauseDuration;
+    type MaxReasonLength = MaxReasonLength;
+    type MaxExempt = MaxExempt;
+    type UnpauseCondition = ();
+    type CallsAllowedWhilePaused = ();
     type WeightInfo = ();
 }
 
@@ -0,0 +1,87 @@
+//! A `BTreeSet` that never holds more than `S::get()` items, for storage
+//! that must stay bounded.
+
+use frame_support::traits::Get;
+use parity_scale_codec::{Decode, Encode, EncodeLike, Error, Input, Output};
+use sp_std::{collections::btree_set::BTreeSet, fmt, marker::PhantomData, ops::Deref};
+
+/// A `BTreeSet` with at most `S::get()` items. Inserting past the limit
+/// fails and decoding a longer set is an error.
+pub struct BoundedBTreeSet<T, S>(BTreeSet<T>, PhantomData<S>);
+
+impl<T: Ord, S: Get<u32>> BoundedBTreeSet<T, S> {
+    pub fn new() -> Self {
+        Self(BTreeSet::new(), PhantomData)
+    }
+
+    /// Like `BTreeSet::insert`, but hands `value` back when the set is full.
+    pub fn try_insert(&mut self, value: T) -> Result<bool, T> {
+        if self.0.contains(&value) {
+            return Ok(false);
+        }
+        if self.0.len() >= S::get() as usize {
+            return Err(value);
+        }
+        Ok(self.0.insert(value))
+    }
+
+    pub fn remove(&mut self, value: &T) -> bool {
+        self.0.remove(value)
+    }
+}
+
+impl<T, S> Deref for BoundedBTreeSet<T, S> {
+    type Target = BTreeSet<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T, S> Default for BoundedBTreeSet<T, S> {
+    fn default() -> Self {
+        Self(BTreeSet::new(), PhantomData)
+    }
+}
+
+impl<T: Clone, S> Clone for BoundedBTreeSet<T, S> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone(), PhantomData)
+    }
+}
+
+impl<T: PartialEq, S> PartialEq for BoundedBTreeSet<T, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<T: Eq, S> Eq for BoundedBTreeSet<T, S> {}
+
+impl<T: fmt::Debug, S> fmt::Debug for BoundedBTreeSet<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("BoundedBTreeSet").field(&self.0).finish()
+    }
+}
+
+impl<T: Encode, S> Encode for BoundedBTreeSet<T, S> {
+    fn size_hint(&self) -> usize {
+        self.0.size_hint()
+    }
+
+    fn encode_to<O: Output + ?Sized>(&self, dest: &mut O) {
+        self.0.encode_to(dest)
+    }
+}
+
+impl<T: Encode, S> EncodeLike for BoundedBTreeSet<T, S> {}
+
+impl<T: Decode + Ord, S: Get<u32>> Decode for BoundedBTreeSet<T, S> {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, Error> {
+        let inner = BTreeSet::<T>::decode(input)?;
+        if inner.len() > S::get() as usize {
+            return Err("BoundedBTreeSet exceeds its limit".into());
+        }
+        Ok(Self(inner, PhantomData))
+    }
+}
@@ -6,11 +6,39 @@
 #[cfg(test)]
 mod tests;
 
+pub mod bounded;
 pub mod weights;
+pub use bounded::BoundedBTreeSet;
 pub use weights::WeightInfo;
 
+use frame_support::{ensure, traits::Filter};
+use malborn_support::IsPaused;
+use parity_scale_codec::{Decode, Encode};
+use sp_runtime::{
+    traits::{DispatchInfoOf, Saturating, SignedExtension},
+    transaction_validity::{
+        InvalidTransaction, TransactionValidity, TransactionValidityError, ValidTransaction,
+    },
+    DispatchResult, RuntimeDebug,
+};
+use sp_std::{fmt, marker::PhantomData, vec::Vec};
+
 pub use pallet::*;
 
+/// Pause state of the chain, as returned by `pause_info`.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct PauseInfo<BlockNumber> {
+    pub paused: bool,
+    /// Block at which a `pause_for` pause is lifted.
+    pub paused_until: Option<BlockNumber>,
+    /// Block at which a scheduled pause engages.
+    pub scheduled_pause: Option<BlockNumber>,
+    /// Reason given with `pause_with_reason` for the current pause.
+    pub reason: Option<Vec<u8>>,
+    /// Number of times the chain has been paused.
+    pub pause_count: u32,
+}
+
 #[frame_support::pallet]
 pub mod pallet {
     use super::*;
@@ -21,6 +49,22 @@ pub mod pallet {
     pub trait Config: frame_system::Config {
         type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
         type PauseOrigin: EnsureOrigin<Self::Origin>;
+        /// Maximum number of blocks `pause_for` may pause the chain for.
+        #[pallet::constant]
+        type MaxPauseDuration: Get<Self::BlockNumber>;
+        /// Maximum length in bytes of the reason given to `pause_with_reason`.
+        #[pallet::constant]
+        type MaxReasonLength: Get<u32>;
+        /// Maximum number of accounts in `PauseExemptAccounts`.
+        #[pallet::constant]
+        type MaxExempt: Get<u32>;
+        /// External signal, e.g. a flag written by another pallet, that lifts
+        /// any pause at the start of the next block once it returns `true`.
+        /// Use `()` to only ever unpause manually.
+        type UnpauseCondition: Get<bool>;
+        /// Calls `CheckPause` still admits from non-exempt signers while the
+        /// chain is paused, e.g. the governance calls that lift the pause.
+        type CallsAllowedWhilePaused: Filter<<Self as frame_system::Config>::Call>;
         /// Weight information for extrinsics in this pallet.
         type WeightInfo: WeightInfo;
     }
@@ -30,7 +74,51 @@ pub mod pallet {
     pub struct Pallet<T>(PhantomData<T>);
 
     #[pallet::hooks]
-    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {}
+    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        /// Lift a timed pause once its deadline block has been reached, lift
+        /// any pause once `UnpauseCondition` holds, and engage a scheduled
+        /// pause once its block arrives.
+        ///
+        /// `Paused` is also read here, once per block, so the read is paid
+        /// for up front. Later reads through `is_paused` or
+        /// `ensure_not_paused` in the same block hit the storage cache, and
+        /// dependent pallets need not add a DB read to their own weights.
+        /// Costs 3 reads, plus 4 writes each time a pause is lifted and
+        /// 2 reads and 4 writes when a scheduled pause engages.
+        /// `UnpauseCondition` is charged 1 more read while paused.
+        fn on_initialize(now: BlockNumberFor<T>) -> Weight {
+            let _ = Self::paused();
+            let mut weight = T::DbWeight::get().reads(3);
+
+            if matches!(Self::paused_until(), Some(until) if now >= until) {
+                Self::lift_pause();
+
+                weight = weight.saturating_add(T::DbWeight::get().writes(4));
+            }
+
+            if Self::paused() {
+                weight = weight.saturating_add(T::DbWeight::get().reads(1));
+                if T::UnpauseCondition::get() {
+                    Self::lift_pause();
+
+                    weight = weight.saturating_add(T::DbWeight::get().writes(4));
+                }
+            }
+
+            // Applied after lifting a timed pause so that a schedule landing on
+            // the same block wins.
+            if matches!(Self::scheduled_pause(), Some(at) if now >= at) {
+                if !Self::paused() {
+                    Self::engage_pause();
+                }
+                <ScheduledPause<T>>::kill();
+
+                weight = weight.saturating_add(T::DbWeight::get().reads_writes(2, 4));
+            }
+
+            weight
+        }
+    }
 
     #[pallet::call]
     impl<T: Config> Pallet<T> {
@@ -41,24 +129,72 @@ pub mod pallet {
                 .map(|_| ())
                 .or_else(ensure_root)?;
 
-            <Paused<T>>::put(Self::paused());
+            Self::put_paused(Self::paused());
             Self::deposit_event(Event::StatusChanged(Self::paused()));
 
             Ok(().into())
         }
 
+        /// Pause, recording the signed caller in a `PausedBy` event.
         #[pallet::weight(T::WeightInfo::pause_base())]
         pub fn pause(origin: OriginFor<T>) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin.clone()).ok();
             T::PauseOrigin::try_origin(origin)
                 .map(|_| ())
                 .or_else(ensure_root)?;
+
+            ensure!(!Self::paused(), Error::<T>::AlreadyPaused);
             
-            <Paused<T>>::put(true);
-            
-            Self::deposit_event(Event::StatusChanged(true));
+            Self::engage_pause();
+            Self::deposit_event(Event::PausedBy(who));
     
             Ok(().into())
         }
+
+        /// Pause like `pause`, recording why for `pause_info`. The reason is
+        /// cleared once the pause is lifted.
+        #[pallet::weight(T::WeightInfo::pause_base())]
+        pub fn pause_with_reason(
+            origin: OriginFor<T>,
+            reason: Vec<u8>,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin.clone()).ok();
+            T::PauseOrigin::try_origin(origin)
+                .map(|_| ())
+                .or_else(ensure_root)?;
+
+            ensure!(!Self::paused(), Error::<T>::AlreadyPaused);
+            ensure!(
+                reason.len() <= T::MaxReasonLength::get() as usize,
+                Error::<T>::ReasonTooLong
+            );
+
+            Self::engage_pause();
+            <PauseReason<T>>::put(reason);
+            Self::deposit_event(Event::PausedBy(who));
+
+            Ok(().into())
+        }
+
+        /// Replace the reason of the ongoing pause, leaving the pause itself
+        /// untouched.
+        #[pallet::weight(T::WeightInfo::pause_base())]
+        pub fn update_reason(origin: OriginFor<T>, reason: Vec<u8>) -> DispatchResultWithPostInfo {
+            T::PauseOrigin::try_origin(origin)
+                .map(|_| ())
+                .or_else(ensure_root)?;
+
+            ensure!(Self::paused(), Error::<T>::NotPaused);
+            ensure!(
+                reason.len() <= T::MaxReasonLength::get() as usize,
+                Error::<T>::ReasonTooLong
+            );
+
+            <PauseReason<T>>::put(reason.clone());
+            Self::deposit_event(Event::ReasonUpdated(reason));
+
+            Ok(().into())
+        }
     
         /// Unpause
         #[pallet::weight(T::WeightInfo::pause_base())]
@@ -66,25 +202,335 @@ pub mod pallet {
             T::PauseOrigin::try_origin(origin)
                 .map(|_| ())
                 .or_else(ensure_root)?;
+
+            ensure!(Self::paused(), Error::<T>::AlreadyUnpaused);
     
-            <Paused<T>>::put(Self::paused());
+            Self::put_paused(Self::paused());
             Self::deposit_event(Event::StatusChanged(false));
     
             Ok(().into())
         }
 
-        
+        /// Pause for `blocks` blocks, after which the pause is lifted automatically.
+        /// Durations above `MaxPauseDuration` are rejected. Starting a pause
+        /// records the caller in `PausedBy` like `pause` does.
+        #[pallet::weight(T::WeightInfo::pause_base())]
+        pub fn pause_for(
+            origin: OriginFor<T>,
+            blocks: T::BlockNumber,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin.clone()).ok();
+            T::PauseOrigin::try_origin(origin)
+                .map(|_| ())
+                .or_else(ensure_root)?;
+
+            ensure!(
+                blocks <= T::MaxPauseDuration::get(),
+                Error::<T>::PauseTooLong
+            );
+
+            let until = <frame_system::Pallet<T>>::block_number().saturating_add(blocks);
+            if !Self::paused() {
+                Self::engage_pause();
+                Self::deposit_event(Event::PausedBy(who));
+            }
+            <PausedUntil<T>>::put(until);
+
+            Ok(().into())
+        }
+
+        /// Schedule a pause to take effect at block `at`, replacing any
+        /// previously scheduled one.
+        #[pallet::weight(T::WeightInfo::pause_base())]
+        pub fn schedule_pause(
+            origin: OriginFor<T>,
+            at: T::BlockNumber,
+        ) -> DispatchResultWithPostInfo {
+            T::PauseOrigin::try_origin(origin)
+                .map(|_| ())
+                .or_else(ensure_root)?;
+
+            ensure!(
+                at > <frame_system::Pallet<T>>::block_number(),
+                Error::<T>::ScheduleInPast
+            );
+
+            <ScheduledPause<T>>::put(at);
+            Self::deposit_event(Event::PauseScheduled(at));
+
+            Ok(().into())
+        }
+
+        /// Cancel the pending scheduled pause.
+        #[pallet::weight(T::WeightInfo::pause_base())]
+        pub fn cancel_scheduled_pause(origin: OriginFor<T>) -> DispatchResultWithPostInfo {
+            T::PauseOrigin::try_origin(origin)
+                .map(|_| ())
+                .or_else(ensure_root)?;
+
+            ensure!(
+                Self::scheduled_pause().is_some(),
+                Error::<T>::NoScheduledPause
+            );
+
+            <ScheduledPause<T>>::kill();
+            Self::deposit_event(Event::ScheduledPauseCancelled);
+
+            Ok(().into())
+        }
+
+        /// Let `who` keep transacting while the chain is paused, see
+        /// `ensure_not_paused_for`. At most `MaxExempt` accounts are exempt.
+        #[pallet::weight(T::WeightInfo::pause_base())]
+        pub fn add_exempt(origin: OriginFor<T>, who: T::AccountId) -> DispatchResultWithPostInfo {
+            T::PauseOrigin::try_origin(origin)
+                .map(|_| ())
+                .or_else(ensure_root)?;
+
+            <PauseExemptAccounts<T>>::try_mutate(|exempt| {
+                ensure!(!exempt.contains(&who), Error::<T>::AlreadyExempt);
+                exempt
+                    .try_insert(who.clone())
+                    .map_err(|_| Error::<T>::TooManyExempt)?;
+                Ok::<_, Error<T>>(())
+            })?;
+            Self::deposit_event(Event::ExemptAdded(who));
+
+            Ok(().into())
+        }
+
+        /// Subject `who` to the pause again.
+        #[pallet::weight(T::WeightInfo::pause_base())]
+        pub fn remove_exempt(
+            origin: OriginFor<T>,
+            who: T::AccountId,
+        ) -> DispatchResultWithPostInfo {
+            T::PauseOrigin::try_origin(origin)
+                .map(|_| ())
+                .or_else(ensure_root)?;
+
+            <PauseExemptAccounts<T>>::try_mutate(|exempt| {
+                ensure!(exempt.remove(&who), Error::<T>::NotExempt);
+                Ok::<_, Error<T>>(())
+            })?;
+            Self::deposit_event(Event::ExemptRemoved(who));
+
+            Ok(().into())
+        }
     }
 
     #[pallet::event]
     #[pallet::generate_deposit(pub(super) fn deposit_event)]
-    #[pallet::metadata()]
+    #[pallet::metadata(
+        T::BlockNumber = "BlockNumber",
+        Option<T::AccountId> = "Option<AccountId>",
+        T::AccountId = "AccountId"
+    )]
     pub enum Event<T: Config> {
         /// Shutdown state was toggled, to either on or off.
         StatusChanged(bool),
+        /// The chain was paused by the given signed account, or `None` when
+        /// the origin was not a signed account, e.g. root or a collective.
+        PausedBy(Option<T::AccountId>),
+        /// A pause was scheduled for the given block.
+        PauseScheduled(T::BlockNumber),
+        /// The scheduled pause was cancelled.
+        ScheduledPauseCancelled,
+        /// The account may now transact while the chain is paused.
+        ExemptAdded(T::AccountId),
+        /// The account is subject to the pause again.
+        ExemptRemoved(T::AccountId),
+        /// The reason of the ongoing pause was replaced.
+        ReasonUpdated(Vec<u8>),
+    }
+
+    #[pallet::error]
+    pub enum Error<T> {
+        /// The requested pause duration exceeds `MaxPauseDuration`.
+        PauseTooLong,
+        /// The chain is paused.
+        Paused,
+        /// A pause can only be scheduled for a future block.
+        ScheduleInPast,
+        /// There is no scheduled pause to cancel.
+        NoScheduledPause,
+        /// `pause` was called while the chain is already paused.
+        AlreadyPaused,
+        /// `unpause` was called while the chain is not paused.
+        AlreadyUnpaused,
+        /// The pause reason is longer than `MaxReasonLength`.
+        ReasonTooLong,
+        /// The account is already exempt from the pause.
+        AlreadyExempt,
+        /// The account is not exempt from the pause.
+        NotExempt,
+        /// `PauseExemptAccounts` already holds `MaxExempt` accounts.
+        TooManyExempt,
+        /// The chain is not paused.
+        NotPaused,
     }
 
     #[pallet::storage]
     #[pallet::getter(fn paused)]
     pub type Paused<T: Config> = StorageValue<_, bool, ValueQuery>;
+
+    /// Block at which a pause started with `pause_for` is lifted.
+    #[pallet::storage]
+    #[pallet::getter(fn paused_until)]
+    pub type PausedUntil<T: Config> = StorageValue<_, T::BlockNumber, OptionQuery>;
+
+    /// Block at which a pause set with `schedule_pause` engages.
+    #[pallet::storage]
+    #[pallet::getter(fn scheduled_pause)]
+    pub type ScheduledPause<T: Config> = StorageValue<_, T::BlockNumber, OptionQuery>;
+
+    /// Reason given with `pause_with_reason`, until the pause is lifted.
+    #[pallet::storage]
+    #[pallet::getter(fn pause_reason)]
+    pub type PauseReason<T: Config> = StorageValue<_, Vec<u8>, OptionQuery>;
+
+    /// Block at which the current pause engaged, cleared once it is lifted.
+    #[pallet::storage]
+    #[pallet::getter(fn paused_since)]
+    pub type PausedSince<T: Config> = StorageValue<_, T::BlockNumber, OptionQuery>;
+
+    /// Number of times the chain went from running to paused.
+    #[pallet::storage]
+    #[pallet::getter(fn pause_count)]
+    pub type PauseCount<T: Config> = StorageValue<_, u32, ValueQuery>;
+
+    /// Accounts that keep transacting while the chain is paused.
+    #[pallet::storage]
+    #[pallet::getter(fn pause_exempt_accounts)]
+    pub type PauseExemptAccounts<T: Config> =
+        StorageValue<_, BoundedBTreeSet<T::AccountId, T::MaxExempt>, ValueQuery>;
+}
+
+impl<T: Config> Pallet<T> {
+    /// Integration point for dependent pallets: call this at the top of any
+    /// extrinsic that must not run while the chain is paused.
+    pub fn ensure_not_paused() -> DispatchResult {
+        ensure!(!Self::is_paused(), Error::<T>::Paused);
+        Ok(())
+    }
+
+    /// Like `ensure_not_paused`, but lets accounts in `PauseExemptAccounts`
+    /// through. Use it where the caller is known, e.g. with the signer of a
+    /// call. Costs 1 more read while paused.
+    pub fn ensure_not_paused_for(who: &T::AccountId) -> DispatchResult {
+        ensure!(!Self::is_paused_for(who), Error::<T>::Paused);
+        Ok(())
+    }
+
+    /// Whether the pause applies to calls made by `who`.
+    pub fn is_paused_for(who: &T::AccountId) -> bool {
+        Self::is_paused() && !Self::pause_exempt_accounts().contains(who)
+    }
+
+    /// Everything about the current pause in one read for tooling.
+    pub fn pause_info() -> PauseInfo<T::BlockNumber> {
+        PauseInfo {
+            paused: Self::paused(),
+            paused_until: Self::paused_until(),
+            scheduled_pause: Self::scheduled_pause(),
+            reason: Self::pause_reason(),
+            pause_count: Self::pause_count(),
+        }
+    }
+
+    fn engage_pause() {
+        Self::put_paused(true);
+        Self::deposit_event(Event::StatusChanged(true));
+    }
+
+    fn lift_pause() {
+        Self::put_paused(false);
+        Self::deposit_event(Event::StatusChanged(false));
+    }
+
+    /// Write `Paused`, keeping the rest of the pause state in step with it:
+    /// a pause that starts is counted and gets its `PausedSince`, while
+    /// running there is no deadline, reason or `PausedSince`.
+    fn put_paused(paused: bool) {
+        let was_paused = Self::paused();
+        <Paused<T>>::put(paused);
+        if paused {
+            if !was_paused {
+                <PauseCount<T>>::mutate(|count| *count = count.saturating_add(1));
+            }
+            if Self::paused_since().is_none() {
+                <PausedSince<T>>::put(<frame_system::Pallet<T>>::block_number());
+            }
+        } else {
+            <PausedUntil<T>>::kill();
+            <PauseReason<T>>::kill();
+            <PausedSince<T>>::kill();
+        }
+    }
+
+    /// Blocks left until a pause started with `pause_for` is lifted, or
+    /// `None` when the chain isn't paused with a deadline. `Some(0)` once the
+    /// deadline is reached but `on_initialize` has not yet lifted the pause.
+    pub fn blocks_until_unpause() -> Option<T::BlockNumber> {
+        if !Self::paused() {
+            return None;
+        }
+        Self::paused_until()
+            .map(|until| until.saturating_sub(<frame_system::Pallet<T>>::block_number()))
+    }
+}
+
+/// Rejects signed transactions from accounts the pause applies to, see
+/// `is_paused_for`, unless `CallsAllowedWhilePaused` lets the call through.
+#[derive(Encode, Decode, Clone, Eq, PartialEq, Default)]
+pub struct CheckPause<T: Config + Send + Sync>(PhantomData<T>);
+
+impl<T: Config + Send + Sync> CheckPause<T> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: Config + Send + Sync> fmt::Debug for CheckPause<T> {
+    #[cfg(feature = "std")]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CheckPause")
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn fmt(&self, _: &mut fmt::Formatter) -> fmt::Result {
+        Ok(())
+    }
+}
+
+impl<T: Config + Send + Sync> SignedExtension for CheckPause<T> {
+    const IDENTIFIER: &'static str = "CheckPause";
+    type AccountId = T::AccountId;
+    type Call = <T as frame_system::Config>::Call;
+    type AdditionalSigned = ();
+    type Pre = ();
+
+    fn additional_signed(&self) -> Result<(), TransactionValidityError> {
+        Ok(())
+    }
+
+    fn validate(
+        &self,
+        who: &Self::AccountId,
+        call: &Self::Call,
+        _info: &DispatchInfoOf<Self::Call>,
+        _len: usize,
+    ) -> TransactionValidity {
+        if Pallet::<T>::is_paused_for(who) && !T::CallsAllowedWhilePaused::filter(call) {
+            return Err(InvalidTransaction::Call.into());
+        }
+        Ok(ValidTransaction::default())
+    }
+}
+
+impl<T: Config> IsPaused for Pallet<T> {
+    /// Already charged for by `on_initialize`, see there.
+    fn is_paused() -> bool {
+        Self::paused()
+    }
 }
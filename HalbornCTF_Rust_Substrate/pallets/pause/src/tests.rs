@@ -2,8 +2,12 @@
 
 use super::*;
 use crate::{self as pallet_pause};
-use frame_support::{assert_noop, assert_ok, ord_parameter_types, parameter_types};
+use frame_support::{
+    assert_noop, assert_ok, ord_parameter_types, parameter_types,
+    traits::{Filter, Get, OnInitialize},
+};
 use frame_system::{EnsureSignedBy, RawOrigin};
+use malborn_support::IsPaused;
 use sp_core::H256;
 use sp_runtime::{
     testing::Header,
@@ -41,7 +45,7 @@ impl frame_system::Config for Test {
     type AccountId = u64;
     type Lookup = IdentityLookup<Self::AccountId>;
     type Header = Header;
-    type Event = ();
+    type Event = Event;
     type BlockHashCount = BlockHashCount;
     type Version = ();
     type PalletInfo = PalletInfo;
@@ -56,9 +60,44 @@ impl frame_system::Config for Test {
 ord_parameter_types! {
     pub const Admin: u64 = 1;
 }
+
+thread_local! {
+    static UNPAUSE_CONDITION: std::cell::RefCell<bool> = std::cell::RefCell::new(false);
+}
+pub struct UnpauseCondition;
+impl UnpauseCondition {
+    pub fn set(value: bool) {
+        UNPAUSE_CONDITION.with(|condition| *condition.borrow_mut() = value);
+    }
+}
+impl Get<bool> for UnpauseCondition {
+    fn get() -> bool {
+        UNPAUSE_CONDITION.with(|condition| *condition.borrow())
+    }
+}
+
+/// Only the pause pallet's own calls go through while paused, so it can
+/// always be lifted.
+pub struct PauseCallsOnly;
+impl Filter<Call> for PauseCallsOnly {
+    fn filter(call: &Call) -> bool {
+        matches!(call, Call::TestModule(_))
+    }
+}
+
+parameter_types! {
+    pub const MaxPauseDuration: u64 = 100;
+    pub const MaxReasonLength: u32 = 32;
+    pub const MaxExempt: u32 = 2;
+}
 impl Config for Test {
-    type Event = ();
+    type Event = Event;
     type PauseOrigin = EnsureSignedBy<Admin, u64>;
+    type MaxPauseDuration = MaxPauseDuration;
+    type MaxReasonLength = MaxReasonLength;
+    type MaxExempt = MaxExempt;
+    type UnpauseCondition = UnpauseCondition;
+    type CallsAllowedWhilePaused = PauseCallsOnly;
     type WeightInfo = ();
 }
 
@@ -81,10 +120,32 @@ fn root_pause() {
 #[test]
 fn pause_origin_unpause() {
     new_test_ext().execute_with(|| {
+        assert_ok!(TestModule::pause(RawOrigin::Root.into()));
         assert_ok!(TestModule::unpause(Origin::signed(Admin::get())));
     })
 }
 
+#[test]
+fn double_pause_fails() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(TestModule::pause(RawOrigin::Root.into()));
+        assert_noop!(
+            TestModule::pause(RawOrigin::Root.into()),
+            Error::<Test>::AlreadyPaused
+        );
+    })
+}
+
+#[test]
+fn unpause_when_not_paused_fails() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            TestModule::unpause(RawOrigin::Root.into()),
+            Error::<Test>::AlreadyUnpaused
+        );
+    })
+}
+
 #[test]
 fn bad_origin_fails() {
     new_test_ext().execute_with(|| {
@@ -137,3 +198,495 @@ fn unpause_bug_does_not_unpause() {
         // System remains paused even after calling unpause
     })
 }
+
+#[test]
+fn pause_for_within_limit() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        assert_ok!(TestModule::pause_for(
+            Origin::signed(Admin::get()),
+            MaxPauseDuration::get()
+        ));
+
+        assert_eq!(TestModule::paused(), true);
+        assert_eq!(TestModule::paused_until(), Some(1 + MaxPauseDuration::get()));
+    })
+}
+
+#[test]
+fn pause_for_over_limit_fails() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            TestModule::pause_for(RawOrigin::Root.into(), MaxPauseDuration::get() + 1),
+            Error::<Test>::PauseTooLong
+        );
+    })
+}
+
+#[test]
+fn pause_for_lifts_at_deadline() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        assert_ok!(TestModule::pause_for(RawOrigin::Root.into(), 10));
+
+        TestModule::on_initialize(10);
+        assert_eq!(TestModule::paused(), true);
+
+        TestModule::on_initialize(11);
+        assert_eq!(TestModule::paused(), false);
+        assert_eq!(TestModule::paused_until(), None);
+    })
+}
+
+#[test]
+fn ensure_not_paused_follows_state() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(TestModule::ensure_not_paused());
+
+        assert_ok!(TestModule::pause(RawOrigin::Root.into()));
+        assert_noop!(TestModule::ensure_not_paused(), Error::<Test>::Paused);
+    })
+}
+
+#[test]
+fn is_paused_follows_state_across_blocks() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        TestModule::on_initialize(1);
+        assert_eq!(TestModule::is_paused(), false);
+
+        assert_ok!(TestModule::pause_for(RawOrigin::Root.into(), 2));
+        assert_eq!(TestModule::is_paused(), true);
+
+        System::set_block_number(2);
+        TestModule::on_initialize(2);
+        assert_eq!(TestModule::is_paused(), true);
+
+        System::set_block_number(3);
+        TestModule::on_initialize(3);
+        assert_eq!(TestModule::is_paused(), false);
+    })
+}
+
+#[test]
+fn ensure_not_paused_short_circuits_work() {
+    new_test_ext().execute_with(|| {
+        let mut work_done = 0;
+        let mut do_work = || -> DispatchResult {
+            TestModule::ensure_not_paused()?;
+            work_done += 1;
+            Ok(())
+        };
+
+        assert_ok!(do_work());
+        assert_ok!(TestModule::pause(RawOrigin::Root.into()));
+        assert_noop!(do_work(), Error::<Test>::Paused);
+        drop(do_work);
+
+        assert_eq!(work_done, 1);
+    })
+}
+
+#[test]
+fn scheduled_pause_engages_at_block() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        assert_ok!(TestModule::schedule_pause(Origin::signed(Admin::get()), 5));
+        assert_eq!(TestModule::scheduled_pause(), Some(5));
+
+        TestModule::on_initialize(4);
+        assert_eq!(TestModule::paused(), false);
+
+        TestModule::on_initialize(5);
+        assert_eq!(TestModule::paused(), true);
+        assert_eq!(TestModule::scheduled_pause(), None);
+    })
+}
+
+#[test]
+fn schedule_pause_in_past_fails() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(5);
+        assert_noop!(
+            TestModule::schedule_pause(RawOrigin::Root.into(), 5),
+            Error::<Test>::ScheduleInPast
+        );
+    })
+}
+
+#[test]
+fn schedule_pause_bad_origin_fails() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(TestModule::schedule_pause(Origin::signed(0), 5), BadOrigin);
+    })
+}
+
+#[test]
+fn cancel_scheduled_pause_prevents_pause() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        assert_ok!(TestModule::schedule_pause(RawOrigin::Root.into(), 5));
+        assert_ok!(TestModule::cancel_scheduled_pause(RawOrigin::Root.into()));
+
+        TestModule::on_initialize(5);
+        assert_eq!(TestModule::paused(), false);
+        assert_noop!(
+            TestModule::cancel_scheduled_pause(RawOrigin::Root.into()),
+            Error::<Test>::NoScheduledPause
+        );
+    })
+}
+
+#[test]
+fn signed_pause_records_caller() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        assert_ok!(TestModule::pause(Origin::signed(Admin::get())));
+
+        assert!(System::events().iter().any(|record| record.event
+            == Event::pallet_pause(pallet_pause::Event::PausedBy(Some(Admin::get())))));
+    })
+}
+
+#[test]
+fn toggle_while_paused_records_no_caller() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        <pallet_pause::Paused<Test>>::put(true);
+        assert_ok!(TestModule::toggle(Origin::signed(Admin::get())));
+
+        assert!(!System::events().iter().any(|record| matches!(
+            record.event,
+            Event::pallet_pause(pallet_pause::Event::PausedBy(_))
+        )));
+    })
+}
+
+#[test]
+fn pause_for_records_caller() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        assert_ok!(TestModule::pause_for(Origin::signed(Admin::get()), 10));
+
+        assert!(System::events().iter().any(|record| record.event
+            == Event::pallet_pause(pallet_pause::Event::PausedBy(Some(Admin::get())))));
+    })
+}
+
+#[test]
+fn root_pause_records_no_caller() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        assert_ok!(TestModule::pause(RawOrigin::Root.into()));
+
+        assert!(System::events().iter().any(|record| record.event
+            == Event::pallet_pause(pallet_pause::Event::PausedBy(None))));
+    })
+}
+
+#[test]
+fn unpause_condition_lifts_pause_next_block() {
+    new_test_ext().execute_with(|| {
+        UnpauseCondition::set(false);
+        System::set_block_number(1);
+        assert_ok!(TestModule::pause(RawOrigin::Root.into()));
+
+        TestModule::on_initialize(2);
+        assert_eq!(TestModule::paused(), true);
+
+        UnpauseCondition::set(true);
+        TestModule::on_initialize(3);
+        assert_eq!(TestModule::paused(), false);
+        UnpauseCondition::set(false);
+    })
+}
+
+#[test]
+fn unpause_condition_clears_timed_pause() {
+    new_test_ext().execute_with(|| {
+        UnpauseCondition::set(false);
+        System::set_block_number(1);
+        assert_ok!(TestModule::pause_for(RawOrigin::Root.into(), 10));
+
+        UnpauseCondition::set(true);
+        TestModule::on_initialize(2);
+        assert_eq!(TestModule::paused(), false);
+        assert_eq!(TestModule::paused_until(), None);
+        UnpauseCondition::set(false);
+    })
+}
+
+#[test]
+fn blocks_until_unpause_counts_down() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        assert_ok!(TestModule::pause_for(RawOrigin::Root.into(), 10));
+        assert_eq!(TestModule::blocks_until_unpause(), Some(10));
+
+        System::set_block_number(5);
+        TestModule::on_initialize(5);
+        assert_eq!(TestModule::blocks_until_unpause(), Some(6));
+
+        System::set_block_number(11);
+        TestModule::on_initialize(11);
+        assert_eq!(TestModule::blocks_until_unpause(), None);
+    })
+}
+
+#[test]
+fn blocks_until_unpause_none_without_deadline() {
+    new_test_ext().execute_with(|| {
+        assert_eq!(TestModule::blocks_until_unpause(), None);
+
+        assert_ok!(TestModule::pause(RawOrigin::Root.into()));
+        assert_eq!(TestModule::blocks_until_unpause(), None);
+    })
+}
+
+#[test]
+fn pause_info_bundles_state() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        assert_eq!(
+            TestModule::pause_info(),
+            PauseInfo {
+                paused: false,
+                paused_until: None,
+                scheduled_pause: None,
+                reason: None,
+                pause_count: 0,
+            }
+        );
+
+        assert_ok!(TestModule::pause_with_reason(
+            Origin::signed(Admin::get()),
+            b"oracle outage".to_vec()
+        ));
+        assert_ok!(TestModule::pause_for(RawOrigin::Root.into(), 10));
+        assert_eq!(
+            TestModule::pause_info(),
+            PauseInfo {
+                paused: true,
+                paused_until: Some(11),
+                scheduled_pause: None,
+                reason: Some(b"oracle outage".to_vec()),
+                pause_count: 1,
+            }
+        );
+
+        TestModule::on_initialize(11);
+        assert_eq!(
+            TestModule::pause_info(),
+            PauseInfo {
+                paused: false,
+                paused_until: None,
+                scheduled_pause: None,
+                reason: None,
+                pause_count: 1,
+            }
+        );
+
+        assert_ok!(TestModule::pause(RawOrigin::Root.into()));
+        assert_eq!(TestModule::pause_count(), 2);
+    })
+}
+
+#[test]
+fn pause_reason_too_long_fails() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            TestModule::pause_with_reason(
+                RawOrigin::Root.into(),
+                vec![0; MaxReasonLength::get() as usize + 1]
+            ),
+            Error::<Test>::ReasonTooLong
+        );
+    })
+}
+
+#[test]
+fn exempt_accounts_bypass_pause() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(TestModule::add_exempt(Origin::signed(Admin::get()), 7));
+        assert_ok!(TestModule::pause(RawOrigin::Root.into()));
+
+        assert_ok!(TestModule::ensure_not_paused_for(&7));
+        assert_noop!(
+            TestModule::ensure_not_paused_for(&8),
+            Error::<Test>::Paused
+        );
+        assert_noop!(TestModule::ensure_not_paused(), Error::<Test>::Paused);
+
+        assert_ok!(TestModule::remove_exempt(RawOrigin::Root.into(), 7));
+        assert_noop!(
+            TestModule::ensure_not_paused_for(&7),
+            Error::<Test>::Paused
+        );
+        assert_noop!(
+            TestModule::remove_exempt(RawOrigin::Root.into(), 7),
+            Error::<Test>::NotExempt
+        );
+    })
+}
+
+#[test]
+fn exempt_accounts_are_bounded() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(TestModule::add_exempt(Origin::signed(0), 7), BadOrigin);
+        for who in 0..MaxExempt::get() as u64 {
+            assert_ok!(TestModule::add_exempt(RawOrigin::Root.into(), who));
+        }
+        assert_noop!(
+            TestModule::add_exempt(RawOrigin::Root.into(), 0),
+            Error::<Test>::AlreadyExempt
+        );
+        assert_noop!(
+            TestModule::add_exempt(RawOrigin::Root.into(), 99),
+            Error::<Test>::TooManyExempt
+        );
+    })
+}
+
+#[test]
+fn exempt_set_over_limit_does_not_decode() {
+    let oversized: std::collections::BTreeSet<u64> = (0..=MaxExempt::get() as u64).collect();
+    assert!(BoundedBTreeSet::<u64, MaxExempt>::decode(&mut &oversized.encode()[..]).is_err());
+
+    let full: std::collections::BTreeSet<u64> = (0..MaxExempt::get() as u64).collect();
+    let decoded = BoundedBTreeSet::<u64, MaxExempt>::decode(&mut &full.encode()[..]).unwrap();
+    assert_eq!(*decoded, full);
+}
+
+fn check_pause(who: u64, call: &Call) -> TransactionValidity {
+    CheckPause::<Test>::new().validate(&who, call, &Default::default(), 0)
+}
+
+#[test]
+fn check_pause_blocks_signed_calls_while_paused() {
+    new_test_ext().execute_with(|| {
+        let remark = Call::System(frame_system::Call::remark(vec![]));
+        assert_ok!(check_pause(8, &remark));
+
+        assert_ok!(TestModule::pause(RawOrigin::Root.into()));
+        assert_eq!(
+            check_pause(8, &remark),
+            Err(InvalidTransaction::Call.into())
+        );
+        assert_ok!(check_pause(
+            Admin::get(),
+            &Call::TestModule(pallet_pause::Call::unpause())
+        ));
+    })
+}
+
+#[test]
+fn check_pause_lets_exempt_accounts_through() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(TestModule::add_exempt(RawOrigin::Root.into(), 7));
+        assert_ok!(TestModule::pause(RawOrigin::Root.into()));
+
+        let remark = Call::System(frame_system::Call::remark(vec![]));
+        assert_ok!(check_pause(7, &remark));
+        assert_eq!(
+            check_pause(8, &remark),
+            Err(InvalidTransaction::Call.into())
+        );
+    })
+}
+
+#[test]
+fn update_reason_while_paused() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        assert_ok!(TestModule::pause_with_reason(
+            RawOrigin::Root.into(),
+            b"oracle outage".to_vec()
+        ));
+
+        assert_ok!(TestModule::update_reason(
+            Origin::signed(Admin::get()),
+            b"oracle patched, verifying".to_vec()
+        ));
+        assert_eq!(
+            TestModule::pause_reason(),
+            Some(b"oracle patched, verifying".to_vec())
+        );
+        assert!(TestModule::paused());
+        assert!(System::events().iter().any(|record| record.event
+            == Event::pallet_pause(pallet_pause::Event::ReasonUpdated(
+                b"oracle patched, verifying".to_vec()
+            ))));
+
+        assert_noop!(TestModule::update_reason(Origin::signed(0), Vec::new()), BadOrigin);
+    })
+}
+
+#[test]
+fn update_reason_when_not_paused_fails() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            TestModule::update_reason(RawOrigin::Root.into(), b"nothing to explain".to_vec()),
+            Error::<Test>::NotPaused
+        );
+    })
+}
+
+#[test]
+fn paused_since_keeps_the_original_pause_block() {
+    new_test_ext().execute_with(|| {
+        assert_eq!(TestModule::paused_since(), None);
+
+        System::set_block_number(3);
+        assert_ok!(TestModule::pause_for(RawOrigin::Root.into(), 10));
+        assert_eq!(TestModule::paused_since(), Some(3));
+
+        // Extending the pause doesn't move its start
+        System::set_block_number(5);
+        assert_ok!(TestModule::pause_for(RawOrigin::Root.into(), 10));
+        TestModule::on_initialize(14);
+        assert_eq!(TestModule::paused(), true);
+        assert_eq!(TestModule::paused_since(), Some(3));
+
+        TestModule::on_initialize(15);
+        assert_eq!(TestModule::paused(), false);
+        assert_eq!(TestModule::paused_since(), None);
+    })
+}
+
+#[test]
+fn toggle_keeps_paused_since_in_step() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(3);
+        assert_ok!(TestModule::toggle(RawOrigin::Root.into()));
+        assert_eq!(TestModule::paused(), false);
+        assert_eq!(TestModule::paused_since(), None);
+
+        // Paused without going through pause, toggle leaves it paused and
+        // records since when
+        <pallet_pause::Paused<Test>>::put(true);
+        System::set_block_number(5);
+        assert_ok!(TestModule::toggle(RawOrigin::Root.into()));
+        assert_eq!(TestModule::paused(), true);
+        assert_eq!(TestModule::paused_since(), Some(5));
+
+        System::set_block_number(7);
+        assert_ok!(TestModule::toggle(RawOrigin::Root.into()));
+        assert_eq!(TestModule::paused_since(), Some(5));
+    })
+}
+
+#[test]
+fn unpause_keeps_paused_since_in_step() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(3);
+        assert_ok!(TestModule::pause_with_reason(RawOrigin::Root.into(), b"upgrade".to_vec()));
+
+        // The chain stays paused, so does its bookkeeping
+        System::set_block_number(5);
+        assert_ok!(TestModule::unpause(RawOrigin::Root.into()));
+        assert_eq!(TestModule::paused(), true);
+        assert_eq!(TestModule::paused_since(), Some(3));
+        assert_eq!(TestModule::pause_reason(), Some(b"upgrade".to_vec()));
+        assert_eq!(TestModule::pause_count(), 1);
+    })
+}
@@ -21,18 +21,41 @@
 use crate::{
     constants,
     pallets_governance::{RootCollective, TechnicalCollective},
-    Allocations, Balances, CompanyReserve, Event, Runtime,
+    Allocations, Balances, Call, CompanyReserve, Event, Runtime,
 };
 
-use frame_support::parameter_types;
+use frame_support::{parameter_types, traits::Filter};
 use malborn_chain_primitives::{AccountId, Balance, BlockNumber, CertificateId};
 use sp_core::u32_trait::{_1, _2};
 use sp_runtime::Perbill;
 
+parameter_types! {
+    pub const MaxPauseDuration: BlockNumber = 7 * constants::DAYS;
+    pub const MaxPauseReasonLength: u32 = 256;
+    pub const MaxPauseExempt: u32 = 16;
+}
+
+/// While paused, signed calls only go through if they can lift the pause:
+/// the pause pallet itself, the root committee voting on it and `Mandate`.
+pub struct PauseGovernanceCalls;
+impl Filter<Call> for PauseGovernanceCalls {
+    fn filter(call: &Call) -> bool {
+        matches!(
+            call,
+            Call::EmergencyShutdown(_) | Call::RootCommittee(_) | Call::Mandate(_)
+        )
+    }
+}
+
 impl pallet_pause::Config for Runtime {
     type Event = Event;
     type PauseOrigin =
         pallet_collective::EnsureProportionMoreThan<_1, _2, AccountId, RootCollective>;
+    type MaxPauseDuration = MaxPauseDuration;
+    type MaxReasonLength = MaxPauseReasonLength;
+    type MaxExempt = MaxPauseExempt;
+    type UnpauseCondition = ();
+    type CallsAllowedWhilePaused = PauseGovernanceCalls;
     type WeightInfo = pallet_pause::weights::SubstrateWeight<Runtime>;
 }
 
@@ -21,3 +21,9 @@
 pub trait WithAccountId<AccountId> {
     fn account_id() -> AccountId;
 }
+
+/// Read access to the chain wide pause state for pallets that should stop
+/// doing work while it is set.
+pub trait IsPaused {
+    fn is_paused() -> bool;
+}